@@ -2,18 +2,18 @@
 
 var engine_internal = engine_internal || {};
 
+// Used as a prototype - each engine context gets its own instance via Object.create
 engine_internal.gl_module = {
-	programs: [null],
-	shaders: [null],
-	buffers: [null],
-
-	framebuffers: [null],
-	renderbuffers: [null],
-	textures: [null],
-	named_textures: {},
+	init: function(canvas) {
+		this.programs = [null];
+		this.shaders = [null];
+		this.buffers = [null];
 
+		this.framebuffers = [null];
+		this.renderbuffers = [null];
+		this.textures = [null];
+		this.named_textures = {};
 
-	init: function(canvas) {
 		this.context = this.create_context(canvas, { stencil: true });
 	},
 
@@ -97,6 +97,7 @@ engine_internal.gl_module = {
 
 			enable: (e) => gl.enable(e),
 			disable: (e) => gl.disable(e),
+			is_enabled: (e) => gl.isEnabled(e),
 
 			blend_func: (s, d) => gl.blendFunc(s, d),
 			
@@ -182,6 +183,11 @@ engine_internal.gl_module = {
 				return pos+1;
 			},
 
+			read_pixels: (x, y, w, h, ptr, len) => {
+				let buf = heap_memory_view(ptr, len);
+				gl.readPixels(x, y, w, h, gl.RGBA, gl.UNSIGNED_BYTE, buf);
+			},
+
 			framebuffer_texture_2d: (tex_id) => {
 				let texture = this.textures[tex_id-1] || null;
 
@@ -325,7 +331,7 @@ engine_internal.gl_module = {
 	},
 
 
-	exports: function(exps) {
+	exports: function(engine_context) {
 		return {
 			register_texture: (id, el) => {
 				if (this.named_textures.hasOwnProperty(id)) {
@@ -356,7 +362,7 @@ engine_internal.gl_module = {
 					|| el instanceof Image;
 
 				if (has_dimensions) {
-					exps.internal_register_texture(tex_id, el.width, el.height);
+					engine_context.call("internal_register_texture", tex_id, el.width, el.height);
 				}
 			},
 		};
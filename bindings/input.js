@@ -2,9 +2,11 @@
 
 var engine_internal = engine_internal || {};
 
+// Used as a prototype - each engine context gets its own instance via Object.create
 engine_internal.input_module = {
-	init: function (canvas) {
+	init: function (canvas, engine_context) {
 		this.canvas = canvas;
+		this.engine_context = engine_context;
 
 		this.has_pointer_lock = 'pointerLockElement' in document ||
 			'mozPointerLockElement' in document ||
@@ -34,130 +36,336 @@ engine_internal.input_module = {
 
 	init_input_listeners: function(passive) {
 		let target = passive? this.canvas : document;
+		this.listener_target = target;
 
 		if (!passive) {
 			// Disable context menus if not in passive mode
 			target.addEventListener('contextmenu', (e) => e.preventDefault(), true);
 		}
 
+		// Keys go to one canvas at a time - whichever was focused or pressed last. Until then, the first to listen
+		if (!passive && !engine_internal.key_canvas) {
+			engine_internal.key_canvas = this.canvas;
+		}
+
 		target.addEventListener('keydown', this.on_key_down.bind(this), true);
 		target.addEventListener('keyup', this.on_key_up.bind(this), true);
 
-		target.addEventListener('mousedown', this.on_mouse_down.bind(this), true);
-		target.addEventListener('mouseup', this.on_mouse_up.bind(this), true);
-		target.addEventListener('mousemove', this.on_mouse_move.bind(this), true);
+		// Mice, pens and fingers all come through pointer events. Touches would otherwise scroll and zoom the page
+		this.canvas.style.touchAction = "none";
+
+		target.addEventListener('pointerdown', this.on_pointer_down.bind(this), true);
+		target.addEventListener('pointerup', this.on_pointer_up.bind(this), true);
+		target.addEventListener('pointermove', this.on_pointer_move.bind(this), true);
+		target.addEventListener('pointercancel', this.on_pointer_cancel.bind(this), true);
+		target.addEventListener('pointerleave', this.on_pointer_cancel.bind(this), true);
 		target.addEventListener('dblclick', (e) => e.preventDefault(), true);
 
-		// TODO: scroll
-		target.addEventListener('touchstart', this.on_touch_down.bind(this), false);
-		target.addEventListener('touchmove', this.on_touch_move.bind(this), false);
-		target.addEventListener('touchend', this.on_touch_up.bind(this), false);
-		target.addEventListener('touchcancel', this.on_touch_up.bind(this), false);
+		// Must not be passive, or the page can't be kept from scrolling
+		target.addEventListener('wheel', this.on_wheel.bind(this), {capture: true, passive: false});
 
-		target.addEventListener('mouseleave', this.on_focus_loss.bind(this), false);
+		target.addEventListener('mouseleave', this.on_pointer_leave.bind(this), false);
+
+		window.addEventListener('focus', this.on_focus_gain.bind(this), false);
+		window.addEventListener('blur', this.on_focus_loss.bind(this), false);
+
+		// The canvas needs to be focusable for clients that only consume input while it has focus
+		if (!this.canvas.hasAttribute('tabindex')) {
+			this.canvas.tabIndex = 0;
+		}
+
+		this.canvas.addEventListener('focus', this.on_canvas_focus_change.bind(this, true), false);
+		this.canvas.addEventListener('blur', this.on_canvas_focus_change.bind(this, false), false);
 	},
 
 
-	// on_focus_gain: function(e) {
-	// 	engine_internal.exports.internal_handle_focus_gain();
-	// },
+	on_focus_gain: function(e) {
+		this.engine_context.call("internal_handle_focus_gain");
+	},
 
 	on_focus_loss: function(e) {
-		engine_internal.exports.internal_handle_focus_loss();
+		this.engine_context.call("internal_handle_focus_loss");
+	},
+
+	on_canvas_focus_change: function(focused, e) {
+		if (focused) {
+			engine_internal.key_canvas = this.canvas;
+		}
+
+		this.engine_context.call("internal_handle_canvas_focus_change", focused);
+	},
+
+	on_pointer_leave: function(e) {
+		this.engine_context.call("internal_handle_pointer_leave");
+	},
+
+
+	// Listeners on the document see keys meant for every other canvas on the page too
+	owns_key_event: function(e) {
+		let in_text_field = this.text_field && e.target === this.text_field;
+		return in_text_field || engine_internal.key_canvas === this.canvas;
+	},
+
+	// Likewise for pointers, which belong to this canvas if they're over it, or captured or locked by it
+	owns_pointer_event: function(e) {
+		return e.target === this.canvas
+			|| (e.pointerId !== undefined && this.canvas.hasPointerCapture && this.canvas.hasPointerCapture(e.pointerId))
+			|| this.pointer_locked();
+	},
+
+	pointer_locked: function() {
+		let lock_element = document.pointerLockElement
+			|| document.mozPointerLockElement
+			|| document.webkitPointerLockElement;
+
+		return lock_element === this.canvas;
 	},
 
 
 	on_key_down: function(e) {
+		if (!this.owns_key_event(e)) {
+			return;
+		}
+
+		// Keys pressed mid-composition belong to the IME
+		if (e.isComposing || e.keyCode === 229) {
+			return;
+		}
+
 		let name = js_str_to_rust(e.code);
-		let consume = engine_internal.exports.internal_handle_key_down(name);
-		if (consume) {
+		let consume = this.engine_context.call("internal_handle_key_down", e.timeStamp, name);
+		if (consume && e.target !== this.text_field) {
 			e.preventDefault();
 		}
 	},
 
 
 	on_key_up: function(e) {
+		if (!this.owns_key_event(e)) {
+			return;
+		}
+
+		if (e.isComposing || e.keyCode === 229) {
+			return;
+		}
+
 		let name = js_str_to_rust(e.code);
-		let consume = engine_internal.exports.internal_handle_key_up(name);
-		if (consume) {
+		let consume = this.engine_context.call("internal_handle_key_up", e.timeStamp, name);
+		if (consume && e.target !== this.text_field) {
 			e.preventDefault();
 		}
 	},
 
 
-	on_mouse_down: function(e) {
-		let button = e.button;
-		let x = e.clientX;
-		let y = e.clientY;
-		let consume = engine_internal.exports.internal_handle_mouse_down(button, x, y);
-		if (consume) {
-			e.preventDefault();
+	// Text input goes through a hidden textarea, since that's the only way to get IME composition and on-screen keyboards.
+	// It's emptied after every change, so everything that comes out of it is a delta
+	set_text_input_enabled: function(enabled) {
+		if (enabled && !this.text_field) {
+			this.text_field = this.create_text_field();
+		}
+
+		if (!this.text_field) {
+			return;
+		}
+
+		this.text_field.value = "";
+		this.composing = false;
+
+		if (enabled) {
+			this.text_field.focus({preventScroll: true});
+		} else {
+			this.text_field.blur();
 		}
 	},
 
 
-	on_mouse_up: function(e) {
-		let button = e.button;
-		let x = e.clientX;
-		let y = e.clientY;
-		let consume = engine_internal.exports.internal_handle_mouse_up(button, x, y);
-		if (consume) {
-			e.preventDefault();
+	create_text_field: function() {
+		let field = document.createElement("textarea");
+		field.setAttribute("autocomplete", "off");
+		field.setAttribute("autocorrect", "off");
+		field.setAttribute("autocapitalize", "off");
+		field.setAttribute("spellcheck", "false");
+		field.setAttribute("aria-hidden", "true");
+
+		field.style.position = "fixed";
+		field.style.left = "0";
+		field.style.top = "0";
+		field.style.width = "1px";
+		field.style.height = "1px";
+		field.style.opacity = "0";
+		field.style.pointerEvents = "none";
+		field.style.resize = "none";
+
+		this.composing = false;
+
+		field.addEventListener('compositionstart', (e) => {
+			this.composing = true;
+		}, false);
+
+		field.addEventListener('compositionupdate', (e) => {
+			this.engine_context.call("internal_handle_text_composition", js_str_to_rust(e.data || ""));
+		}, false);
+
+		field.addEventListener('compositionend', (e) => {
+			this.composing = false;
+			this.engine_context.call("internal_handle_text_composition", js_str_to_rust(""));
+
+			if (e.data) {
+				this.engine_context.call("internal_handle_text_commit", js_str_to_rust(e.data));
+			}
+
+			field.value = "";
+		}, false);
+
+		field.addEventListener('input', (e) => {
+			if (this.composing || e.isComposing) {
+				return;
+			}
+
+			if (field.value.length > 0) {
+				this.engine_context.call("internal_handle_text_commit", js_str_to_rust(field.value));
+			}
+
+			field.value = "";
+		}, false);
+
+		// The field is always empty, so deletions never show up as input events
+		field.addEventListener('keydown', (e) => {
+			if (this.composing || e.isComposing) {
+				return;
+			}
+
+			switch (e.key) {
+				case "Backspace":
+					this.engine_context.call("internal_handle_text_delete", false);
+					e.preventDefault();
+					break;
+
+				case "Delete":
+					this.engine_context.call("internal_handle_text_delete", true);
+					e.preventDefault();
+					break;
+
+				case "Enter":
+					this.engine_context.call("internal_handle_text_commit", js_str_to_rust("\n"));
+					e.preventDefault();
+					break;
+			}
+		}, false);
+
+		// Passive listeners are on the canvas, which won't see keys typed into the field
+		if (this.listener_target && this.listener_target !== document) {
+			field.addEventListener('keydown', this.on_key_down.bind(this), true);
+			field.addEventListener('keyup', this.on_key_up.bind(this), true);
 		}
+
+		document.body.appendChild(field);
+		return field;
+	},
+
+
+	// Everything about a pointer except which button changed, in the order the pointer exports take it
+	pointer_args: function(e) {
+		let pointer_type = {mouse: 0, pen: 1, touch: 2}[e.pointerType] || 0;
+		return [e.timeStamp, e.pointerId, pointer_type, e.isPrimary];
+	},
+
+	// Client coordinates are relative to the page's viewport, and the exports want them relative to the canvas
+	pointer_position: function(e) {
+		let rect = this.canvas.getBoundingClientRect();
+		return [e.clientX - rect.left, e.clientY - rect.top];
 	},
 
+	pointer_details: function(e) {
+		return [e.pressure || 0, e.tiltX || 0, e.tiltY || 0, e.width || 1, e.height || 1];
+	},
+
+
+	on_pointer_down: function(e) {
+		if (!this.owns_pointer_event(e)) {
+			return;
+		}
+
+		// Captured, so a drag that leaves the canvas still ends here
+		if (e.target === this.canvas && this.canvas.setPointerCapture) {
+			this.canvas.setPointerCapture(e.pointerId);
+		}
+
+		engine_internal.key_canvas = this.canvas;
+
+		// Consuming the press can keep the canvas from taking focus, and the client has to know about it before deciding that.
+		// Doesn't steal focus from the text field
+		let text_field_focused = this.text_field && document.activeElement === this.text_field;
+		if (e.target === this.canvas && !text_field_focused) {
+			this.canvas.focus({preventScroll: true});
+		}
+
+		let consume = this.engine_context.call("internal_handle_pointer_down",
+			...this.pointer_args(e), e.button, ...this.pointer_position(e), ...this.pointer_details(e));
 
-	on_mouse_move: function(e) {
-		let x = e.clientX;
-		let y = e.clientY;
-		let dx = e.movementX;
-		let dy = e.movementY;
-		let consume = engine_internal.exports.internal_handle_mouse_move(x, y, dx, dy);
 		if (consume) {
 			e.preventDefault();
 		}
 	},
 
 
-	on_touch_down: function(e) {
-		let consume = false;
-
-		for (let touch of e.changedTouches) {
-			let x = touch.clientX;
-			let y = touch.clientY;
-			consume |= engine_internal.exports.internal_handle_touch_down(touch.identifier, x, y);
+	on_pointer_up: function(e) {
+		if (!this.owns_pointer_event(e)) {
+			return;
 		}
 
+		let consume = this.engine_context.call("internal_handle_pointer_up",
+			...this.pointer_args(e), e.button, ...this.pointer_position(e), ...this.pointer_details(e));
+
 		if (consume) {
 			e.preventDefault();
 		}
 	},
 
 
-	on_touch_up: function(e) {
-		let consume = false;
+	on_pointer_move: function(e) {
+		if (!this.owns_pointer_event(e)) {
+			return;
+		}
 
-		for (let touch of e.changedTouches) {
-			let x = touch.clientX;
-			let y = touch.clientY;
-			consume |= engine_internal.exports.internal_handle_touch_up(touch.identifier, x, y);
+		// Pressing or releasing a mouse button while another is held is reported as a move
+		if (e.pointerType === "mouse" && e.button >= 0) {
+			let mask = [1, 4, 2][e.button] || 0;
+			if (e.buttons & mask) {
+				this.on_pointer_down(e);
+			} else {
+				this.on_pointer_up(e);
+			}
 		}
 
+		let consume = this.engine_context.call("internal_handle_pointer_move",
+			...this.pointer_args(e), ...this.pointer_position(e), e.movementX || 0, e.movementY || 0, ...this.pointer_details(e));
+
 		if (consume) {
 			e.preventDefault();
 		}
 	},
 
 
-	on_touch_move: function(e) {
-		let consume = false;
+	on_pointer_cancel: function(e) {
+		if (!this.owns_pointer_event(e)) {
+			return;
+		}
+
+		// The mouse leaving is handled by on_pointer_leave
+		if (e.pointerType !== "mouse") {
+			this.engine_context.call("internal_handle_pointer_cancel", e.timeStamp, e.pointerId);
+		}
+	},
+
 
-		for (let touch of e.changedTouches) {
-			let x = touch.clientX;
-			let y = touch.clientY;
-			consume |= engine_internal.exports.internal_handle_touch_move(touch.identifier, x, y);
+	on_wheel: function(e) {
+		if (e.target !== this.canvas && !this.pointer_locked()) {
+			return;
 		}
 
+		// Page deltas are in canvas heights, which have to be CSS pixels like everything else
+		let consume = this.engine_context.call("internal_handle_wheel", e.timeStamp, e.deltaX, e.deltaY, e.deltaMode, this.canvas.clientHeight);
 		if (consume) {
 			e.preventDefault();
 		}
@@ -165,13 +373,8 @@ engine_internal.input_module = {
 
 
 	on_pointer_lock_change: function(e) {
-		let lock_element = document.pointerLockElement
-			|| document.mozPointerLockElement
-			|| document.webkitPointerLockElement;
-
-		let enabled = (lock_element === this.canvas);
-
-		engine_internal.exports.internal_notify_pointer_lock_change(enabled);
+		let enabled = this.pointer_locked();
+		this.engine_context.call("internal_notify_pointer_lock_change", enabled);
 	},
 
 	on_pointer_lock_error: function(e) {
@@ -179,19 +382,54 @@ engine_internal.input_module = {
 	},
 
 
+	// Only pads using the "standard" mapping have their buttons and axes in a known order.
+	// Anything else is still reported, but which button is which is up to the browser
+	poll_gamepad: function(index, buttons_ptr, num_buttons, axes_ptr, num_axes) {
+		let pads = navigator.getGamepads ? navigator.getGamepads() : [];
+		let pad = pads[index];
+
+		if (!pad || !pad.connected) {
+			return false;
+		}
+
+		let buttons = heap_memory_view(buttons_ptr, num_buttons);
+		for (let i = 0; i < num_buttons; i++) {
+			buttons[i] = (pad.buttons[i] && pad.buttons[i].pressed) ? 1 : 0;
+		}
+
+		let axis = (i) => pad.axes[i] || 0.0;
+		let trigger = (i) => pad.buttons[i] ? pad.buttons[i].value : 0.0;
+
+		// Sticks are reported positive down
+		let values = [axis(0), -axis(1), axis(2), -axis(3), trigger(6), trigger(7)];
+
+		let axes = new Float32Array(engine_internal.memory.buffer, axes_ptr, num_axes);
+		for (let i = 0; i < num_axes; i++) {
+			axes[i] = values[i] || 0.0;
+		}
+
+		return true;
+	},
+
+
 	imports: function() {
 		return {
 			init_input_listeners: (passive) => this.init_input_listeners(passive),
 
 			request_pointer_lock: () => this.request_pointer_lock(),
 			exit_pointer_lock: () => this.exit_pointer_lock(),
+
+			set_text_input_enabled: (enabled) => this.set_text_input_enabled(enabled),
+
+			poll_gamepad: (index, buttons_ptr, num_buttons, axes_ptr, num_axes) =>
+				this.poll_gamepad(index, buttons_ptr, num_buttons, axes_ptr, num_axes),
 		};
 	},
 
-	exports: function(exps) {
+	exports: function(engine_context) {
 		// TODO: Think about naming
 		return {
-			enable_pointer_lock: exps.engine_enable_pointer_lock,
+			enable_pointer_lock: (e) => engine_context.call("engine_enable_pointer_lock", e),
 		};
 	},
 };
@@ -44,7 +44,10 @@ function js_str_to_rust(str) {
 }
 
 
-async function initialise_engine(engine_module, canvas) {
+// Every canvas gets its own engine instance, gl context and input listeners, all sharing one wasm instance.
+// Any call into an engine goes through `engine_internal.call`, so that imports know which canvas they're for.
+// Set `engine_internal.binary_name` beforehand to namespace stored values - the generated pages do this
+async function initialise_engine(engine_module, ...canvases) {
 	if (TextDecoder && TextEncoder) {
 		engine_internal.text_encoder = new TextEncoder();
 		engine_internal.text_decoder = new TextDecoder();
@@ -63,14 +66,18 @@ async function initialise_engine(engine_module, canvas) {
 		}
 	}
 
-	engine_internal.canvas = canvas;
-	engine_internal.gl_module.init(canvas);
-	engine_internal.input_module.init(canvas);
+	if (canvases.length === 0) {
+		throw "initialise_engine needs at least one canvas";
+	}
+
+	engine_internal.contexts = canvases.map((c) => engine_internal.create_context(c));
+	engine_internal.active_context = null;
+	engine_internal.started = false;
 
 	let wasm_params = {
 		// one page = 64kiB
 		mem: new WebAssembly.Memory({initial: 10, maximum: 100}),
-		env: engine_internal.initialise_imports()
+		env: engine_internal.initialise_imports(engine_internal.contexts[0])
 	};
 
 	let engine_instance = await WebAssembly.instantiate(engine_module, wasm_params);
@@ -79,24 +86,65 @@ async function initialise_engine(engine_module, canvas) {
 	engine_internal.memory = engine_instance.exports.memory;
 	engine_internal.exports = engine_instance.exports;
 
-	let engine = {};
-	engine_internal.initialise_exports(engine);
+	let engine = {
+		instances: engine_internal.contexts.map((ctx) => ctx.exports),
+
+		add_canvas: (canvas) => {
+			let ctx = engine_internal.create_context(canvas);
+			engine_internal.contexts.push(ctx);
+
+			if (engine_internal.started) {
+				engine_internal.start_context(ctx);
+			}
+
+			engine.instances.push(ctx.exports);
+			return ctx.exports;
+		},
+	};
+
+	// Single canvas pages can keep treating the engine as one instance
+	merge_objects(engine, engine.instances[0]);
 
 	let update_fn = function(time) {
-		canvas.width = canvas.clientWidth;
-		canvas.height = canvas.clientHeight;
-		
-		let client_width = engine_internal.gl_module.context.drawingBufferWidth;
-		let client_height = engine_internal.gl_module.context.drawingBufferHeight;
-
-		engine_internal.exports.internal_update_viewport(client_width, client_height);
-		engine_internal.exports.internal_update(time);
+		for (let ctx of engine_internal.contexts) {
+			let canvas = ctx.canvas;
+			let device_pixel_ratio = window.devicePixelRatio || 1;
+			let pixel_ratio = device_pixel_ratio * ctx.call("internal_get_render_scale");
+
+			let width = Math.max(1, Math.round(canvas.clientWidth * pixel_ratio));
+			let height = Math.max(1, Math.round(canvas.clientHeight * pixel_ratio));
+
+			// Resizing clears the canvas, even to the same size
+			if (canvas.width !== width || canvas.height !== height) {
+				canvas.width = width;
+				canvas.height = height;
+			}
+
+			let client_width = ctx.gl_module.context.drawingBufferWidth;
+			let client_height = ctx.gl_module.context.drawingBufferHeight;
+
+			ctx.call("internal_update_viewport", client_width, client_height, device_pixel_ratio);
+			ctx.call("internal_update", time);
+		}
+
 		window.requestAnimationFrame(update_fn);
 	};
 
 	// Delay initialisation
 	window.requestAnimationFrame((time) => {
 		engine_internal.exports.main();
+		engine_internal.started = true;
+
+		for (let ctx of engine_internal.contexts) {
+			engine_internal.start_context(ctx);
+		}
+
+		document.addEventListener('visibilitychange', () => {
+			for (let ctx of engine_internal.contexts) {
+				ctx.call("internal_handle_visibility_change", !document.hidden);
+			}
+		}, false);
+
 		update_fn(time);
 	});
 
@@ -104,7 +152,108 @@ async function initialise_engine(engine_module, canvas) {
 }
 
 
-engine_internal.initialise_imports = function() {
+engine_internal.create_context = function(canvas) {
+	let ctx = {
+		id: null,
+		canvas: canvas,
+		gl_module: Object.create(this.gl_module),
+		input_module: Object.create(this.input_module),
+	};
+
+	ctx.call = (name, ...args) => this.call(ctx, name, ...args);
+
+	ctx.gl_module.init(canvas);
+	ctx.input_module.init(canvas, ctx);
+
+	let util_imports = {
+		canvas_width: () => canvas.width,
+		canvas_height: () => canvas.height,
+	};
+
+	ctx.imports = merge_objects({},
+		util_imports,
+		ctx.gl_module.imports(),
+		ctx.input_module.imports(),
+	);
+
+	let core_exports = {
+		restart: () => ctx.call("engine_restart"),
+		has_failed: () => !!ctx.call("engine_has_failed"),
+		show_profiler: (e) => ctx.call("engine_show_profiler", e),
+		dump_profile: () => ctx.call("engine_dump_profile"),
+
+		// Restarts the client first by default, so that the recording can be replayed exactly
+		start_recording: (restart = true) => ctx.call("engine_start_recording", restart),
+
+		// Returns the recorded input log as a Uint8Array
+		stop_recording: () => {
+			let len = ctx.call("engine_stop_recording");
+			let ptr = engine_internal.exports.internal_export_buffer_ptr();
+			return len > 0 ? heap_memory_view(ptr, len).slice() : new Uint8Array(0);
+		},
+
+		replay: (log, restart = true) => {
+			let ptr = engine_internal.exports.internal_allocate_u8_vec(log.length);
+			heap_memory_view(ptr, log.length).set(log);
+			return !!ctx.call("engine_start_replay", ptr, log.length, restart);
+		},
+
+		// Debug builds only. `name` is the asset's path from the crate root, e.g. "src/bin/fish/scene.glsl",
+		// and `bytes` a Uint8Array or string of its new contents
+		reload_asset: (name, bytes) => {
+			if (typeof bytes === "string") {
+				bytes = engine_internal.text_encoder.encode(bytes);
+			}
+
+			let ptr = engine_internal.exports.internal_allocate_u8_vec(bytes.length);
+			heap_memory_view(ptr, bytes.length).set(bytes);
+			return !!ctx.call("engine_reload_asset", js_str_to_rust(name), ptr, bytes.length);
+		},
+	};
+
+	ctx.exports = merge_objects({},
+		core_exports,
+		ctx.input_module.exports(ctx),
+		ctx.gl_module.exports(ctx),
+	);
+
+	return ctx;
+};
+
+
+engine_internal.start_context = function(ctx) {
+	let prev_context = this.active_context;
+	this.active_context = ctx;
+
+	try {
+		ctx.id = this.exports.internal_create_engine();
+	} finally {
+		this.active_context = prev_context;
+	}
+
+	if (document.hidden) {
+		ctx.call("internal_handle_visibility_change", false);
+	}
+};
+
+
+engine_internal.call = function(ctx, name, ...args) {
+	if (ctx.id === null) {
+		return;
+	}
+
+	let prev_context = this.active_context;
+	this.active_context = ctx;
+
+	try {
+		return this.exports[name](ctx.id, ...args);
+	} finally {
+		this.active_context = prev_context;
+	}
+};
+
+
+engine_internal.initialise_imports = function(template_context) {
 	let io_imports = {
 		console_log_raw: (ptr, len) => {
 			console.log(rust_str_to_js(ptr, len));
@@ -117,34 +266,72 @@ engine_internal.initialise_imports = function() {
 		},
 	};
 
-	let util_imports = {
-		canvas_width: () => this.canvas.width,
-		canvas_height: () => this.canvas.height,
-	};
-
 	let math_imports = {
 		math_random: Math.random, 
 	};
 
-	return merge_objects({},
-		io_imports,
-		util_imports,
-		math_imports,
+	let time_imports = {
+		performance_now: () => performance.now(),
+	};
 
-		this.gl_module.imports(),
-		this.input_module.imports(),
-	);
-};
+	// Keys are prefixed so that toys served from the same origin don't share values.
+	// localStorage can be missing or throw (e.g. private browsing), which is treated the same as a missing key
+	let storage_prefix = (this.binary_name || "wasm-toys") + "/";
+
+	let storage_imports = {
+		storage_get: (key_ptr, key_len) => {
+			try {
+				let value = window.localStorage.getItem(storage_prefix + rust_str_to_js(key_ptr, key_len));
+				return value === null ? 0 : js_str_to_rust(value);
+			} catch (e) {
+				return 0;
+			}
+		},
 
+		storage_set: (key_ptr, key_len, value_ptr, value_len) => {
+			try {
+				let key = storage_prefix + rust_str_to_js(key_ptr, key_len);
+				window.localStorage.setItem(key, rust_str_to_js(value_ptr, value_len));
+				return true;
+			} catch (e) {
+				console.warn("localStorage.setItem failed", e);
+				return false;
+			}
+		},
 
-engine_internal.initialise_exports = function(engine) {
-	let exps = this.exports;
+		storage_remove: (key_ptr, key_len) => {
+			try {
+				window.localStorage.removeItem(storage_prefix + rust_str_to_js(key_ptr, key_len));
+			} catch (e) {}
+		},
 
-	merge_objects(
-		engine,
+		storage_clear: () => {
+			try {
+				let keys = [];
+				for (let i = 0; i < window.localStorage.length; i++) {
+					let key = window.localStorage.key(i);
+					if (key.startsWith(storage_prefix)) {
+						keys.push(key);
+					}
+				}
+
+				keys.forEach((key) => window.localStorage.removeItem(key));
+			} catch (e) {}
+		},
+	};
 
-		this.input_module.exports(exps),
-		this.gl_module.exports(exps),
+	// Forward everything that belongs to a canvas to whichever engine is currently being called into
+	let context_imports = {};
+	for (let name in template_context.imports) {
+		context_imports[name] = (...args) => this.active_context.imports[name](...args);
+	}
+
+	return merge_objects({},
+		io_imports,
+		math_imports,
+		time_imports,
+		storage_imports,
+		context_imports,
 	);
 };
 
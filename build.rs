@@ -64,6 +64,8 @@ fn main() {
 		mappings_file.write_all(mapping.as_bytes()).unwrap();
 	}
 
+	println!("cargo:rustc-check-cfg=cfg(debug)");
+
 	if profile == "debug" {
 		println!("cargo:rustc-cfg=debug");
 	}
@@ -0,0 +1,236 @@
+// Files baked into the binary - shaders, .toy projects.
+// In debug builds every embedded asset is also registered by name, so the page can push new contents
+// for it with `reload` and have it picked up without a rebuild. Release builds only ever see the embedded bytes.
+//
+// Assets are named by their path relative to the crate root, e.g. "src/bin/fish/scene.glsl",
+// so a file watcher can push changes straight through.
+
+use std::borrow::Cow;
+use std::ops::Deref;
+#[cfg(debug)]
+use std::rc::Rc;
+
+use crate::engine::EngineResult;
+
+/// Embeds the file at `path`, relative to the invoking source file like `include_bytes!`, as an `Asset`
+#[macro_export]
+macro_rules! embed_asset {
+	($path:expr) => {
+		$crate::assets::Asset::new(file!(), $path, include_bytes!($path))
+	};
+}
+
+
+/// Handle to an embedded file, or in debug builds, whatever was most recently pushed in its place
+#[derive(Copy, Clone)]
+pub struct Asset {
+	embedded: &'static [u8],
+
+	#[cfg(debug)]
+	id: usize,
+}
+
+impl Asset {
+	/// Prefer `embed_asset!`. `path` is relative to `source_file`
+	#[cfg(debug)]
+	pub fn new(source_file: &str, path: &str, embedded: &'static [u8]) -> Asset {
+		let id = registry().register(asset_name(source_file, path));
+		Asset { embedded, id }
+	}
+
+	#[cfg(not(debug))]
+	pub fn new(_: &str, _: &str, embedded: &'static [u8]) -> Asset {
+		Asset { embedded }
+	}
+
+	/// Never copies - reloaded contents are shared with the registry
+	#[cfg(debug)]
+	pub fn bytes(&self) -> AssetBytes {
+		match registry().entries[self.id].reloaded.as_ref() {
+			Some(bytes) => AssetBytes::Reloaded(bytes.clone()),
+			None => AssetBytes::Embedded(self.embedded),
+		}
+	}
+
+	#[cfg(not(debug))]
+	pub fn bytes(&self) -> AssetBytes {
+		AssetBytes::Embedded(self.embedded)
+	}
+
+	/// Invalid utf-8 is replaced rather than treated as an error, since a bad reload shouldn't take a toy down.
+	/// Reloaded contents are copied, so check `generation` before calling this every frame
+	pub fn text(&self) -> Cow<'static, str> {
+		match self.bytes() {
+			AssetBytes::Embedded(bytes) => String::from_utf8_lossy(bytes),
+			#[cfg(debug)]
+			AssetBytes::Reloaded(bytes) => Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()),
+		}
+	}
+
+	/// Bumped every time the asset is reloaded. Always 0 in release builds
+	#[cfg(debug)]
+	pub fn generation(&self) -> u32 { registry().entries[self.id].generation }
+
+	#[cfg(not(debug))]
+	pub fn generation(&self) -> u32 { 0 }
+}
+
+
+/// The contents of an `Asset`, as of when `Asset::bytes` was called
+#[derive(Clone)]
+pub enum AssetBytes {
+	Embedded(&'static [u8]),
+	#[cfg(debug)]
+	Reloaded(Rc<[u8]>),
+}
+
+impl Deref for AssetBytes {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		match self {
+			AssetBytes::Embedded(bytes) => bytes,
+			#[cfg(debug)]
+			AssetBytes::Reloaded(bytes) => bytes,
+		}
+	}
+}
+
+
+/// A value built from an asset, which is rebuilt when the asset is reloaded.
+/// Clients own these in place of e.g. a `toy::Project`, and call `reload_if_changed` once per frame
+pub struct Reloadable<T> {
+	asset: Asset,
+	generation: u32,
+	loader: fn(&[u8]) -> EngineResult<T>,
+	value: T,
+}
+
+impl<T> Reloadable<T> {
+	pub fn new(asset: Asset, loader: fn(&[u8]) -> EngineResult<T>) -> EngineResult<Self> {
+		let generation = asset.generation();
+		let value = loader(&asset.bytes())?;
+
+		Ok(Reloadable { asset, generation, loader, value })
+	}
+
+	/// Returns whether the value was rebuilt, so anything derived from it can be rebuilt too.
+	/// If the new contents fail to load, the previous value is kept and the error is logged rather than returned
+	pub fn reload_if_changed(&mut self) -> bool {
+		let generation = self.asset.generation();
+		if generation == self.generation { return false }
+
+		self.generation = generation;
+
+		match (self.loader)(&self.asset.bytes()) {
+			Ok(value) => {
+				self.value = value;
+				true
+			}
+
+			Err(err) => {
+				console_error!("Failed to reload asset: {}", err);
+				false
+			}
+		}
+	}
+
+	pub fn asset(&self) -> Asset { self.asset }
+}
+
+impl<T> Deref for Reloadable<T> {
+	type Target = T;
+	fn deref(&self) -> &T { &self.value }
+}
+
+
+pub fn is_hot_reload_enabled() -> bool { cfg!(debug) }
+
+/// Replaces the contents of the asset called `name`. Returns false if no asset by that name has been embedded
+#[cfg(debug)]
+pub fn reload(name: &str, bytes: Vec<u8>) -> bool {
+	let registry = registry();
+
+	let entry = match registry.entries.iter_mut().find(|e| e.name == name) {
+		Some(entry) => entry,
+		None => {
+			console_warn!("Tried to reload unknown asset '{}'", name);
+			return false;
+		}
+	};
+
+	entry.reloaded = Some(bytes.into());
+	entry.generation = entry.generation.wrapping_add(1);
+
+	console_log!("Reloaded asset '{}'", name);
+	true
+}
+
+#[cfg(not(debug))]
+pub fn reload(name: &str, _: Vec<u8>) -> bool {
+	console_warn!("Tried to reload asset '{}', but hot reload is only available in debug builds", name);
+	false
+}
+
+/// Names of every asset embedded so far. Assets are registered the first time they're used,
+/// so this won't include anything the client hasn't loaded yet. Always empty in release builds
+#[cfg(debug)]
+pub fn registered_names() -> Vec<String> {
+	registry().entries.iter().map(|e| e.name.clone()).collect()
+}
+
+#[cfg(not(debug))]
+pub fn registered_names() -> Vec<String> { Vec::new() }
+
+
+
+#[cfg(debug)]
+static mut REGISTRY: Option<Registry> = None;
+
+#[cfg(debug)]
+fn registry() -> &'static mut Registry {
+	unsafe { REGISTRY.get_or_insert_with(|| Registry { entries: Vec::new() }) }
+}
+
+#[cfg(debug)]
+struct Entry {
+	name: String,
+	generation: u32,
+	reloaded: Option<Rc<[u8]>>,
+}
+
+#[cfg(debug)]
+struct Registry {
+	entries: Vec<Entry>,
+}
+
+#[cfg(debug)]
+impl Registry {
+	// The same asset is embedded again every time the code that embeds it runs - e.g. once per engine,
+	// or again on restart - and every copy shares one entry, so a reload reaches all of them
+	fn register(&mut self, name: String) -> usize {
+		if let Some(id) = self.entries.iter().position(|e| e.name == name) {
+			return id;
+		}
+
+		self.entries.push(Entry { name, generation: 0, reloaded: None });
+		self.entries.len() - 1
+	}
+}
+
+/// Joins `path` onto the directory of `source_file`, resolving any `..`s
+#[cfg(debug)]
+fn asset_name(source_file: &str, path: &str) -> String {
+	let mut segments: Vec<&str> = source_file.split(|c| c == '/' || c == '\\').collect();
+	segments.pop();
+
+	for segment in path.split(|c| c == '/' || c == '\\') {
+		match segment {
+			"." | "" => {}
+			".." => { segments.pop(); }
+			_ => segments.push(segment),
+		}
+	}
+
+	segments.join("/")
+}
@@ -1,5 +1,6 @@
 extern crate wasm_toys as engine;
 use engine::prelude::*;
+use engine::input::{GamepadAxis, Consume};
 
 pub type Mesh = DynamicMesh<vertex::ColorVertex>;
 
@@ -11,6 +12,7 @@ fn main() {
 
 struct Bubble {
 	camera: Camera,
+	file: Option<Reloadable<toy::Project>>,
 	scene: Mesh,
 	portal: Mesh,
 
@@ -18,14 +20,13 @@ struct Bubble {
 
 	yaw_vel: f32,
 	yaw: f32,
+	zoom: f32,
 }
 
 impl Bubble {
 	fn new() -> Bubble {
-		let (scene, portal) = init_scene().expect("Error loading scene!");
-
-		let shader = Shader::from_combined(
-			include_str!("clipped_color.glsl"),
+		let shader = Shader::from_asset(
+			embed_asset!("clipped_color.glsl"),
 			&["position", "color"]
 		);
 
@@ -34,20 +35,58 @@ impl Bubble {
 
 		Bubble {
 			camera,
-			scene, portal,
+			file: None,
+			scene: Mesh::new(),
+			portal: Mesh::new(),
 			shader,
 
 			yaw_vel: 0.0,
 			yaw: 0.0,
+			zoom: 1.0,
 		}
 	}
 }
 
 impl engine::EngineClient for Bubble {
 	fn uses_passive_input(&self) -> bool { false }
-	fn drag_threshold(&self) -> Option<u32> { None } // Always drag
+	fn drag_threshold(&self) -> Option<f32> { None } // Always drag
+	fn consumes_wheel(&self) -> Consume { Consume::Always }
+
+	fn init(&mut self) -> EngineResult<()> {
+		let file = Reloadable::new(embed_asset!("bubble.toy"), load_toy)?;
+
+		let (scene, portal) = init_scene(&file)?;
+		self.scene = scene;
+		self.portal = portal;
+		self.file = Some(file);
+		Ok(())
+	}
+
+	fn simulate(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		if ctx.input.dragging() {
+			return Ok(());
+		}
+
+		if ctx.input_raw.gamepad_axis(GamepadAxis::RightX) != 0.0 {
+			let delta = -ctx.input_raw.gamepad_axis(GamepadAxis::RightX) * PI * DT;
+			self.yaw_vel += (delta - self.yaw_vel) / 5.0;
+		} else {
+			self.yaw_vel *= 1.0 - 3.0*DT;
+		}
+
+		self.yaw += self.yaw_vel;
+		Ok(())
+	}
+
+	fn update(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		if let Some(file) = self.file.as_mut() {
+			if file.reload_if_changed() {
+				let (scene, portal) = init_scene(file)?;
+				self.scene = scene;
+				self.portal = portal;
+			}
+		}
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
 		unsafe {
 			gl::enable(gl::Capability::StencilTest);
 			gl::stencil_mask(0xFF);
@@ -60,22 +99,21 @@ impl engine::EngineClient for Bubble {
 
 		self.shader.bind();
 
-		// spin
+		// spin. Drags follow the pointer, so they're applied per frame rather than in simulate
 		if ctx.input.dragging() {
 			let raw_delta = ctx.input.frame_delta();
 			let delta = -raw_delta.x as f32 * PI * self.camera.aspect();
 			self.yaw_vel += (delta - self.yaw_vel) / 5.0;
-
-		} else {
-			self.yaw_vel *= 1.0 - 3.0*DT;
+			self.yaw += self.yaw_vel;
 		}
 
-		self.yaw += self.yaw_vel;
+		self.yaw += ctx.input.twist_delta();
+		self.zoom = (self.zoom / ctx.input.pinch_delta()).max(0.5).min(3.0);
 
 
 		// position camera
 		let quat = Quat::new(Vec3::from_y(1.0), self.yaw);
-		let position = quat * Vec3::from_z(2.0) + Vec3::from_y(2.0);
+		let position = quat * Vec3::from_z(2.0 * self.zoom) + Vec3::from_y(2.0);
 
 		self.camera.update(ctx.viewport);
 		self.camera.set_orientation(quat);
@@ -104,14 +142,19 @@ impl engine::EngineClient for Bubble {
 
 		// TODO bubble shine
 		// TODO floaties
+
+		Ok(())
 	}
 }
 
 
 
-fn init_scene() -> EngineResult<(Mesh, Mesh)> {
-	let file = toy::load(include_bytes!("bubble.toy"))?;
+fn load_toy(bytes: &[u8]) -> EngineResult<toy::Project> {
+	Ok(toy::load(bytes)?)
+}
+
 
+fn init_scene(file: &toy::Project) -> EngineResult<(Mesh, Mesh)> {
 	let mut scene_mesh = Mesh::new();
 	let mut portal_mesh = Mesh::new();
 
@@ -122,12 +165,12 @@ fn init_scene() -> EngineResult<(Mesh, Mesh)> {
 		.map(|&id| &file.entities[id as usize - 1]);
 
 	for e in entities {
-		bake_entity_to_mesh(&mut scene_mesh, &file, e)?;
+		bake_entity_to_mesh(&mut scene_mesh, file, e)?;
 	}
 
 	let portal_ent = file.find_entity("portal")
 		.ok_or_else(|| format_err!("Couldn't find entity 'portal' in toy file"))?;
-	bake_entity_to_mesh(&mut portal_mesh, &file, &portal_ent)?;
+	bake_entity_to_mesh(&mut portal_mesh, file, &portal_ent)?;
 
 	scene_mesh.apply(|vert| {
 		let rgb = vert.color;
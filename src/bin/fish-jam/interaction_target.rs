@@ -25,22 +25,24 @@ pub struct InteractionTarget {
 }
 
 
-pub fn interaction_targets_in_scene<'s>(file: &'s toy::Project, scene_name: &str) -> impl Iterator<Item=InteractionTarget> + 's {
-	entities_in_scene(file, scene_name)
+pub fn interaction_targets_in_scene<'s>(file: &'s toy::Project, scene_name: &str) -> EngineResult<impl Iterator<Item=InteractionTarget> + 's> {
+	let targets = entities_in_scene(file, scene_name)?
 		.filter(|e| e.name.starts_with("IT_"))
 		.map(|e| InteractionTarget {
 			name: e.name.clone(),
 			pos: e.position,
 			suitability: None,
-		})
+		});
+
+	Ok(targets)
 }
 
 
-pub fn interaction_targets_in_range(file: &toy::Project, scene_name: &str, ply: &PlayerController) -> Vec<InteractionTarget> {
+pub fn interaction_targets_in_range(file: &toy::Project, scene_name: &str, ply: &PlayerController) -> EngineResult<Vec<InteractionTarget>> {
 	let player_pos = ply.pos.to_xz();
 	let player_fwd = ply.rot.forward().to_xz();
 
-	let mut its = interaction_targets_in_scene(file, scene_name)
+	let mut its = interaction_targets_in_scene(file, scene_name)?
 		.map(move |it| {
 			let diff = it.pos.to_xz() - player_pos;
 			let dist = diff.length();
@@ -52,7 +54,7 @@ pub fn interaction_targets_in_range(file: &toy::Project, scene_name: &str, ply:
 
 	its.sort_by_key(|(_, dist, _)| ordify(dist));
 
-	its.into_iter()
+	let its = its.into_iter()
 		.map(|(angle, dist, mut it)| {
 			if angle > INTERACTION_ARC || dist > INTERACTION_DIST {
 				it.suitability = Some(Suitability::Nearby);
@@ -62,5 +64,7 @@ pub fn interaction_targets_in_range(file: &toy::Project, scene_name: &str, ply:
 
 			it
 		})
-		.collect()
+		.collect();
+
+	Ok(its)
 }
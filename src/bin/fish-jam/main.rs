@@ -1,5 +1,6 @@
 extern crate wasm_toys as engine;
 use engine::prelude::*;
+use engine::input::{Bindings, Binding, KeyCode, GamepadAxis};
 
 use vertex::BasicVertex;
 
@@ -32,8 +33,9 @@ enum PlayState {
 
 struct App {
 	camera: Camera,
+	bindings: Bindings,
 
-	file: toy::Project,
+	file: Option<toy::Project>,
 
 	it_shader: Shader,
 	interaction_target_mesh: BasicDynamicMesh<SceneVertex>,
@@ -53,13 +55,13 @@ impl App {
 		let mut camera = Camera::new();
 		camera.set_near_far(0.1, 3000.0);
 
-		let it_shader = Shader::from_combined(
-			include_str!("interaction_target.glsl"),
+		let it_shader = Shader::from_asset(
+			embed_asset!("interaction_target.glsl"),
 			&["position", "color"]
 		);
 
-		let screen_transition_shader = Shader::from_combined(
-			include_str!("transition.glsl"),
+		let screen_transition_shader = Shader::from_asset(
+			embed_asset!("transition.glsl"),
 			&["position"]
 		);
 
@@ -71,18 +73,16 @@ impl App {
 			BasicVertex(Vec3::new( 1.0, -1.0, 0.0)),
 		]);
 
-		let file = toy::load(include_bytes!("main.toy")).unwrap();
-		let scene_view = SceneView::new(&file);
-
 		App {
 			camera,
+			bindings: default_bindings(),
 
-			file,
+			file: None,
 
 			it_shader,
 			interaction_target_mesh: BasicDynamicMesh::new(),
 
-			scene_view,
+			scene_view: SceneView::new(),
 			game_state: GameState::new(),
 
 			player_controller: PlayerController::new(),
@@ -93,40 +93,18 @@ impl App {
 		}
 	}
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
-		unsafe {
-			let (r,g,b,_) = Color::hsv(193.0, 0.15, 0.9).to_tuple();
-
-			gl::clear_color(r, g, b, 1.0);
-			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-		}
-
-		self.camera.update(ctx.viewport);
-
-		let static_interaction_targets = interaction_targets_in_range(&self.file, "main", &self.player_controller);
+	fn simulate(&mut self, ctx: engine::UpdateContext) {
+		let dt = ctx.fixed_dt;
 
 		match self.play_state {
-			PlayState::Normal => {
-				self.player_controller.update(&ctx, self.camera.aspect());
-				self.player_controller.update_camera(&mut self.camera);
-
-				if ctx.input.tap() {
-					if let Some(it) = static_interaction_targets.iter().find(|it| it.suitability == Some(Suitability::Interactible)) {
-						self.game_state.interact(&it.name);
-
-						if self.game_state.in_bed {
-							self.play_state = PlayState::EnterSleep(0.0);
-						}
-					}
-				}
-			}
+			PlayState::Normal => {}
 
 			PlayState::EnterSleep(t) => {
 				if t > 1.0 {
 					self.game_state = GameState::new();
 					self.play_state = PlayState::Sleeping(0.0);
 				} else {
-					self.play_state = PlayState::EnterSleep(t + DT);
+					self.play_state = PlayState::EnterSleep(t + dt);
 				}
 			}
 
@@ -134,7 +112,7 @@ impl App {
 				if t > 1.0 {
 					self.play_state = PlayState::LeaveSleep(0.0);
 				} else {
-					self.play_state = PlayState::Sleeping(t + 1.5*DT);
+					self.play_state = PlayState::Sleeping(t + 1.5*dt);
 				}
 			}
 
@@ -142,13 +120,42 @@ impl App {
 				if t > 1.0 {
 					self.play_state = PlayState::Normal;
 				} else {
-					self.play_state = PlayState::LeaveSleep(t + 0.8*DT);
+					self.play_state = PlayState::LeaveSleep(t + 0.8*dt);
+				}
+			}
+		}
+	}
+
+	fn update(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		unsafe {
+			let (r,g,b,_) = Color::hsv(193.0, 0.15, 0.9).to_tuple();
+
+			gl::clear_color(r, g, b, 1.0);
+			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+		}
+
+		self.camera.update(ctx.viewport);
+
+		let file = loaded_file(&self.file)?;
+		let static_interaction_targets = interaction_targets_in_range(file, "main", &self.player_controller)?;
+
+		if let PlayState::Normal = self.play_state {
+			self.player_controller.update(&ctx, self.camera.aspect());
+			self.player_controller.update_camera(&mut self.camera);
+
+			if ctx.input.tap() {
+				if let Some(it) = static_interaction_targets.iter().find(|it| it.suitability == Some(Suitability::Interactible)) {
+					self.game_state.interact(&it.name);
+
+					if self.game_state.in_bed {
+						self.play_state = PlayState::EnterSleep(0.0);
+					}
 				}
 			}
 		}
 
 		// Draw scene
-		self.scene_view.draw(self.camera.projection_view(), &self.file, &self.game_state);
+		self.scene_view.draw(self.camera.projection_view(), file, &self.game_state)?;
 
 		// Draw interaction targets
 		let it_size = PARTICLE_EXTENT * ctx.viewport.x.min(ctx.viewport.y) as f32;
@@ -176,7 +183,7 @@ impl App {
 			gl::clear(gl::DEPTH_BUFFER_BIT);
 		}
 
-		let time = ctx.ticks as f32 * engine::DT;
+		let time = ctx.time();
 
 		let ui_transform = self.camera.projection_matrix()
 			* Mat4::translate(Vec3::new(0.0, -0.3, -1.0))
@@ -219,21 +226,58 @@ impl App {
 		}
 
 		self.screen_transition_mesh.draw(gl::DrawMode::Triangles);
+
+		Ok(())
 	}
 }
 
 impl EngineClient for App {
 	fn uses_passive_input(&self) -> bool { false }
 	fn captures_input(&self) -> bool { true }
+	fn bindings(&self) -> Option<&Bindings> { Some(&self.bindings) }
+
 
+	fn init(&mut self) -> EngineResult<()> {
+		let file = toy::load(include_bytes!("main.toy"))?;
+		self.scene_view.rebuild(&file)?;
+		self.file = Some(file);
 
-	fn init(&mut self) {
 		self.player_controller.update_camera(&mut self.camera);
+		Ok(())
+	}
+
+	fn simulate(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		self.simulate(ctx);
+		Ok(())
 	}
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
-		self.update(ctx);
+	fn update(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		self.update(ctx)
 	}
 }
 
 
+fn default_bindings() -> Bindings {
+	let mut bindings = Bindings::new();
+
+	bindings
+		.bind_axis("move_forward", Binding::Key(KeyCode::W), 1.0)
+		.bind_axis("move_forward", Binding::Key(KeyCode::Up), 1.0)
+		.bind_axis("move_forward", Binding::Key(KeyCode::S), -1.0)
+		.bind_axis("move_forward", Binding::Key(KeyCode::Down), -1.0)
+
+		.bind_axis("move_right", Binding::Key(KeyCode::D), 1.0)
+		.bind_axis("move_right", Binding::Key(KeyCode::Right), 1.0)
+		.bind_axis("move_right", Binding::Key(KeyCode::A), -1.0)
+		.bind_axis("move_right", Binding::Key(KeyCode::Left), -1.0)
+		.bind("move_forward", Binding::GamepadAxis(GamepadAxis::LeftY))
+		.bind("move_right", Binding::GamepadAxis(GamepadAxis::LeftX));
+
+	bindings
+}
+
+
+// Only None if `init` failed, in which case nothing else is called
+fn loaded_file(file: &Option<toy::Project>) -> EngineResult<&toy::Project> {
+	file.as_ref().ok_or_else(|| format_err!("main.toy hasn't been loaded"))
+}
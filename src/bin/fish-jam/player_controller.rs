@@ -42,7 +42,7 @@ impl PlayerController {
 				let drag_move_offset = drag_move_thresh.copysign(drag_delta.y);
 				let delta = (drag_delta.y + drag_move_offset) * PLAYER_SPEED * 2.0;
 
-				self.pos += self.rot.forward() * DT * delta.clamp(-PLAYER_SPEED, PLAYER_SPEED);
+				self.pos += self.rot.forward() * ctx.frame_time * delta.clamp(-PLAYER_SPEED, PLAYER_SPEED);
 			}
 
 		} else if ctx.input_raw.is_pointer_locked() {
@@ -63,23 +63,8 @@ impl PlayerController {
 
 		self.rot = Quat::new(Vec3::from_y(1.0), self.yaw);
 		// movement
-		use engine::input::*;
-
-		if ctx.input_raw.button_state(KeyCode::W).is_down() {
-			self.pos += self.rot.forward() * PLAYER_SPEED * DT;
-		}
-
-		if ctx.input_raw.button_state(KeyCode::S).is_down() {
-			self.pos -= self.rot.forward() * PLAYER_SPEED * DT;
-		}
-
-		if ctx.input_raw.button_state(KeyCode::D).is_down() {
-			self.pos += self.rot.right() * PLAYER_SPEED * DT;
-		}
-
-		if ctx.input_raw.button_state(KeyCode::A).is_down() {
-			self.pos -= self.rot.right() * PLAYER_SPEED * DT;
-		}
+		self.pos += self.rot.forward() * ctx.actions.axis("move_forward") * PLAYER_SPEED * ctx.frame_time;
+		self.pos += self.rot.right() * ctx.actions.axis("move_right") * PLAYER_SPEED * ctx.frame_time;
 
 		// keep near the center
 		let player_dist = self.pos.to_xz().length();
@@ -87,7 +72,7 @@ impl PlayerController {
 			let to_center = -self.pos.to_xz() / player_dist;
 
 			let amt = (player_dist - MAX_PLAYER_DIST).powi(2);
-			self.pos += (to_center * amt).to_x0z() * DT;
+			self.pos += (to_center * amt).to_x0z() * ctx.frame_time;
 		}
 	}
 
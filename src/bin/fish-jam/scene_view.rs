@@ -17,17 +17,16 @@ pub struct SceneView {
 
 
 impl SceneView {
-	pub fn new(file: &toy::Project) -> Self {
-		let scene_shader = Shader::from_combined(
-			include_str!("scene.glsl"),
+	/// Empty until the first `rebuild`
+	pub fn new() -> Self {
+		let scene_shader = Shader::from_asset(
+			embed_asset!("scene.glsl"),
 			&["position", "color"]
 		);
 
-		let static_mesh = bake_static_scene_mesh(&file, "main").unwrap();
-
 		SceneView {
 			scene_shader,
-			static_mesh,
+			static_mesh: DynamicMesh::new(),
 			dynamic_mesh: DynamicMesh::new(),
 			ui_mesh: DynamicMesh::new(),
 
@@ -36,14 +35,21 @@ impl SceneView {
 	}
 
 
-	pub fn draw(&mut self, proj_view: Mat4, file: &toy::Project, game_state: &GameState) {
+	/// Bakes the static parts of the main scene
+	pub fn rebuild(&mut self, file: &toy::Project) -> EngineResult<()> {
+		self.static_mesh = bake_static_scene_mesh(file, "main")?;
+		Ok(())
+	}
+
+
+	pub fn draw(&mut self, proj_view: Mat4, file: &toy::Project, game_state: &GameState) -> EngineResult<()> {
 		let mut hasher = DefaultHasher::new();
 		game_state.hash(&mut hasher); 
 		let new_hash = hasher.finish();
 
 		if self.prev_game_state != new_hash {
-			self.build_dynamic(file, game_state).unwrap();
-			self.build_ui(file, game_state).unwrap();
+			self.build_dynamic(file, game_state)?;
+			self.build_ui(file, game_state)?;
 			self.prev_game_state = new_hash;
 		}
 
@@ -52,6 +58,8 @@ impl SceneView {
 
 		self.static_mesh.draw(gl::DrawMode::Triangles);
 		self.dynamic_mesh.draw(gl::DrawMode::Triangles);
+
+		Ok(())
 	}
 
 
@@ -192,7 +200,7 @@ pub type SceneVertex = vertex::ColorVertex;
 pub fn bake_static_scene_mesh(file: &toy::Project, scene_name: &str) -> EngineResult<DynamicMesh<SceneVertex>> {
 	let mut scene_mesh = DynamicMesh::new();
 
-	let ents_with_meshes = entities_in_scene(file, scene_name)
+	let ents_with_meshes = entities_in_scene(file, scene_name)?
 		.filter(|e| e.mesh_id != 0 && !e.name.contains('_'));
 
 	for e in ents_with_meshes {
@@ -258,8 +266,10 @@ pub fn find_scene<'s>(file: &'s toy::Project, name: &str) -> EngineResult<&'s to
 }
 
 
-pub fn entities_in_scene<'s>(file: &'s toy::Project, scene_name: &str) -> impl Iterator<Item=&'s toy::EntityData> {
-	let scene = find_scene(&file, scene_name).unwrap();
-	scene.entities.iter()
-		.map(move |&id| &file.entities[id as usize - 1])
+pub fn entities_in_scene<'s>(file: &'s toy::Project, scene_name: &str) -> EngineResult<impl Iterator<Item=&'s toy::EntityData>> {
+	let scene = find_scene(&file, scene_name)?;
+	let entities = scene.entities.iter()
+		.map(move |&id| &file.entities[id as usize - 1]);
+
+	Ok(entities)
 }
\ No newline at end of file
@@ -7,6 +7,9 @@ const INTERACTION_ARC: f32 = PI / 8.0;
 
 const VISIBILITY_DIST: f32 = 4.0;
 
+// Targets are picked as spheres of this radius around their position
+const PICK_RADIUS: f32 = 0.5;
+
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Suitability {
@@ -24,6 +27,16 @@ pub struct InteractionTarget {
 }
 
 
+/// The nearest target in reach that `ray` passes through, regardless of which way the player is facing
+pub fn interaction_target_under_ray<'t>(targets: &'t [InteractionTarget], ray: &Ray) -> Option<&'t InteractionTarget> {
+	targets.iter()
+		.filter_map(|it| ray.intersect_sphere(it.pos, PICK_RADIUS).map(|dist| (dist, it)))
+		.filter(|(dist, _)| *dist < INTERACTION_DIST)
+		.min_by_key(|(dist, _)| ordify(dist))
+		.map(|(_, it)| it)
+}
+
+
 pub fn interaction_targets_in_range(scene: toy::SceneRef, ply: &PlayerController) -> Vec<InteractionTarget> {
 	let player_pos = ply.pos.to_xz();
 	let player_fwd = ply.rot.forward().to_xz().normalize();
@@ -1,5 +1,7 @@
 extern crate wasm_toys as engine;
 use engine::prelude::*;
+use engine::state::{State, Transition, TransitionPhase};
+use engine::input::{Bindings, Binding, KeyCode, GamepadAxis, GamepadButton, VirtualControlsLayout, Anchor};
 
 use vertex::BasicVertex;
 
@@ -15,25 +17,23 @@ use game_state::*;
 
 
 fn main() {
-	engine::init_engine(App::new);
+	engine::init_engine_with_states(App::new, || Transition::push(Exploring).fade_in(LEAVE_SLEEP_DURATION));
 }
 
 // world space particle size / 2
 const PARTICLE_EXTENT: f32 = 1.0 / 15.0;
 
+const ENTER_SLEEP_DURATION: f32 = 1.0;
+const SLEEP_DURATION: f32 = 1.0 / 1.5;
+const LEAVE_SLEEP_DURATION: f32 = 1.0 / 0.8;
 
-#[derive(Debug, Copy, Clone)]
-enum PlayState {
-	Normal,
-	EnterSleep(f32),
-	Sleeping(f32),
-	LeaveSleep(f32),
-}
 
 struct App {
 	camera: Camera,
+	bindings: Bindings,
+	virtual_controls: VirtualControlsLayout,
 
-	file: toy::Project,
+	file: Option<Reloadable<toy::Project>>,
 
 	it_shader: Shader,
 	interaction_target_mesh: BasicDynamicMesh<SceneVertex>,
@@ -45,7 +45,6 @@ struct App {
 
 	screen_transition_shader: Shader,
 	screen_transition_mesh: BasicDynamicMesh<BasicVertex>,
-	play_state: PlayState,
 }
 
 impl App {
@@ -53,13 +52,13 @@ impl App {
 		let mut camera = Camera::new();
 		camera.set_near_far(0.1, 3000.0);
 
-		let it_shader = Shader::from_combined(
-			include_str!("interaction_target.glsl"),
+		let it_shader = Shader::from_asset(
+			embed_asset!("interaction_target.glsl"),
 			&["position", "color"]
 		); 
 
-		let screen_transition_shader = Shader::from_combined(
-			include_str!("transition.glsl"),
+		let screen_transition_shader = Shader::from_asset(
+			embed_asset!("transition.glsl"),
 			&["position"]
 		);
 
@@ -71,89 +70,35 @@ impl App {
 			BasicVertex(Vec3::new( 1.0, -1.0, 0.0)),
 		]);
 
-		let file = toy::load(include_bytes!("main.toy")).unwrap();
-		let scene_view = SceneView::new(&file);
-
 		App {
 			camera,
+			bindings: default_bindings(),
+			virtual_controls: default_virtual_controls(),
 
-			file,
+			file: None,
 
 			it_shader,
 			interaction_target_mesh: BasicDynamicMesh::new(),
 
-			scene_view,
+			scene_view: SceneView::new(),
 			game_state: GameState::new(),
 
 			player_controller: PlayerController::new(),
 
 			screen_transition_shader,
 			screen_transition_mesh,
-			play_state: PlayState::LeaveSleep(0.0),
 		}
 	}
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
-		unsafe {
-			let (r,g,b,_) = Color::hsv(193.0, 0.15, 0.9).to_tuple();
-
-			gl::clear_color(r, g, b, 1.0);
-			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-		}
-
-		if ctx.input_raw.button_state(engine::input::KeyCode::F1).is_pressed() {
-			self.player_controller.toggle_cheat_hacker_mode();
-		}
+	fn interaction_targets(&self) -> EngineResult<Vec<InteractionTarget>> {
+		let main_scene = loaded_file(&self.file)?.find_scene("main")
+			.ok_or_else(|| format_err!("Couldn't find scene 'main' in toy file"))?;
 
-		self.camera.update(ctx.viewport);
-
-		let main_scene = self.file.find_scene("main").unwrap();
-		let static_interaction_targets = interaction_targets_in_range(main_scene, &self.player_controller);
-
-		match self.play_state {
-			PlayState::Normal => {
-				self.player_controller.update(&ctx, self.camera.aspect());
-				self.player_controller.update_camera(&mut self.camera);
-
-				if ctx.input.tap() {
-					if let Some(it) = static_interaction_targets.iter().find(|it| it.suitability == Some(Suitability::Interactible)) {
-						self.game_state.interact(&it.name);
-
-						if self.game_state.in_bed {
-							self.play_state = PlayState::EnterSleep(0.0);
-						}
-					}
-				}
-			}
-
-			PlayState::EnterSleep(t) => {
-				if t > 1.0 {
-					self.game_state = GameState::new();
-					self.play_state = PlayState::Sleeping(0.0);
-				} else {
-					self.play_state = PlayState::EnterSleep(t + DT);
-				}
-			}
-
-			PlayState::Sleeping(t) => {
-				if t > 1.0 {
-					self.play_state = PlayState::LeaveSleep(0.0);
-				} else {
-					self.play_state = PlayState::Sleeping(t + 1.5*DT);
-				}
-			}
-
-			PlayState::LeaveSleep(t) => {
-				if t > 1.0 {
-					self.play_state = PlayState::Normal;
-				} else {
-					self.play_state = PlayState::LeaveSleep(t + 0.8*DT);
-				}
-			}
-		}
+		Ok(interaction_targets_in_range(main_scene, &self.player_controller))
+	}
 
-		// Draw scene
-		self.scene_view.update(&self.file, &self.game_state);
+	fn draw_scene(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		self.scene_view.update(loaded_file(&self.file)?, &self.game_state)?;
 		self.scene_view.draw(self.camera.projection_view());
 
 		// Draw interaction targets
@@ -164,7 +109,7 @@ impl App {
 		self.it_shader.set_uniform("particle_scale", it_size);
 
 		self.interaction_target_mesh.clear();
-		for it in static_interaction_targets.iter() {
+		for it in self.interaction_targets()?.iter() {
 			if self.game_state.can_interact(&it.name) {
 				let color = match it.suitability.unwrap() {
 					Suitability::Nearby => Vec3::splat(0.6),
@@ -182,7 +127,7 @@ impl App {
 			gl::clear(gl::DEPTH_BUFFER_BIT);
 		}
 
-		let time = ctx.ticks as f32 * engine::DT;
+		let time = ctx.time();
 
 		let ui_transform = self.camera.projection_matrix()
 			* Mat4::translate(Vec3::new(0.0, -0.3, -1.0))
@@ -192,7 +137,11 @@ impl App {
 
 		self.scene_view.draw_ui(ui_transform);
 
-		// Draw screen fade
+		Ok(())
+	}
+
+	// 1.0 is fully faded, anything at or below `max_fade` is fully clear
+	fn draw_screen_fade<F>(&mut self, fade: F) where F: FnOnce(f32) -> f32 {
 		unsafe {
 			gl::clear(gl::DEPTH_BUFFER_BIT);
 		}
@@ -203,43 +152,166 @@ impl App {
 		self.screen_transition_shader.bind();
 		self.screen_transition_shader.set_uniform("aspect", aspect);
 		self.screen_transition_shader.set_uniform("fade_color", Color::hsv(301.0, 0.46, 0.28).to_vec4());
+		self.screen_transition_shader.set_uniform("fade_amount", fade(max_fade));
 
-		match self.play_state {
-			PlayState::Normal => {
-				self.screen_transition_shader.set_uniform("fade_amount", max_fade);
-			}
+		self.screen_transition_mesh.draw(gl::DrawMode::Triangles);
+	}
+}
 
-			PlayState::EnterSleep(t) => {
-				let amt = t.ease_exp_inout().lerp(max_fade, 1.0);
-				self.screen_transition_shader.set_uniform("fade_amount", amt);
-			}
 
-			PlayState::Sleeping(_) => {
-				self.screen_transition_shader.set_uniform("fade_amount", 1.0);
-			}
+struct Exploring;
+
+impl State<App> for Exploring {
+	fn update(&mut self, app: &mut App, ctx: engine::UpdateContext) -> EngineResult<Transition<App>> {
+		app.player_controller.update(&ctx, app.camera.aspect());
+		app.player_controller.update_camera(&mut app.camera);
+
+		if ctx.input.tap() {
+			let interaction_targets = app.interaction_targets()?;
+
+			// Under pointer lock the cursor is always the middle of the screen
+			let tap_pos = if ctx.input_raw.is_pointer_locked() { Vec2::zero() } else { ctx.input.position() };
+			let tap_ray = app.camera.screen_ray(tap_pos);
 
-			PlayState::LeaveSleep(t) => {
-				let amt = t.ease_exp_in().lerp(1.0, max_fade);
-				self.screen_transition_shader.set_uniform("fade_amount", amt);
+			let tapped = interaction_target_under_ray(&interaction_targets, &tap_ray)
+				.filter(|it| app.game_state.can_interact(&it.name))
+				.or_else(|| interaction_targets.iter().find(|it| it.suitability == Some(Suitability::Interactible)));
+
+			if let Some(it) = tapped {
+				app.game_state.interact(&it.name);
+
+				if app.game_state.in_bed {
+					return Ok(Transition::replace(Sleeping(0.0)).fade_out(ENTER_SLEEP_DURATION));
+				}
 			}
 		}
 
-		self.screen_transition_mesh.draw(gl::DrawMode::Triangles);
+		Ok(Transition::none())
+	}
+
+	fn render(&mut self, app: &mut App, ctx: engine::UpdateContext, transition: Option<TransitionPhase>) -> EngineResult<()> {
+		app.draw_scene(ctx)?;
+
+		app.draw_screen_fade(|max_fade| match transition {
+			Some(TransitionPhase::Out(t)) => t.ease_exp_inout().lerp(max_fade, 1.0),
+			Some(TransitionPhase::In(t)) => t.ease_exp_in().lerp(1.0, max_fade),
+			None => max_fade,
+		});
+
+		Ok(())
+	}
+}
+
+
+struct Sleeping(f32);
+
+impl State<App> for Sleeping {
+	fn enter(&mut self, app: &mut App) {
+		app.game_state = GameState::new();
+	}
+
+	fn simulate(&mut self, _: &mut App, ctx: engine::UpdateContext) -> EngineResult<Transition<App>> {
+		self.0 += ctx.fixed_dt;
+
+		if self.0 > SLEEP_DURATION {
+			Ok(Transition::replace(Exploring).fade_in(LEAVE_SLEEP_DURATION))
+		} else {
+			Ok(Transition::none())
+		}
+	}
+
+	fn render(&mut self, app: &mut App, ctx: engine::UpdateContext, _: Option<TransitionPhase>) -> EngineResult<()> {
+		app.draw_scene(ctx)?;
+		app.draw_screen_fade(|_| 1.0);
+		Ok(())
 	}
 }
 
+
 impl EngineClient for App {
 	fn uses_passive_input(&self) -> bool { false }
 	fn captures_input(&self) -> bool { true }
+	fn bindings(&self) -> Option<&Bindings> { Some(&self.bindings) }
+	fn virtual_controls(&self) -> Option<&VirtualControlsLayout> { Some(&self.virtual_controls) }
+
 
+	fn init(&mut self) -> EngineResult<()> {
+		let file = Reloadable::new(embed_asset!("main.toy"), load_toy)?;
+		self.scene_view.rebuild(&file)?;
+		self.file = Some(file);
 
-	fn init(&mut self) {
 		self.player_controller.update_camera(&mut self.camera);
+		Ok(())
 	}
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
-		self.update(ctx);
+	fn update(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		unsafe {
+			let (r,g,b,_) = Color::hsv(193.0, 0.15, 0.9).to_tuple();
+
+			gl::clear_color(r, g, b, 1.0);
+			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+		}
+
+		if let Some(file) = self.file.as_mut() {
+			if file.reload_if_changed() {
+				self.scene_view.rebuild(file)?;
+			}
+		}
+
+		if ctx.actions.pressed("toggle_cheats") {
+			self.player_controller.toggle_cheat_hacker_mode();
+		}
+
+		self.camera.update(ctx.viewport);
+		Ok(())
 	}
 }
 
 
+fn default_bindings() -> Bindings {
+	let mut bindings = Bindings::new();
+
+	bindings
+		.bind_axis("move_forward", Binding::Key(KeyCode::W), 1.0)
+		.bind_axis("move_forward", Binding::Key(KeyCode::Up), 1.0)
+		.bind_axis("move_forward", Binding::Key(KeyCode::S), -1.0)
+		.bind_axis("move_forward", Binding::Key(KeyCode::Down), -1.0)
+
+		.bind_axis("move_right", Binding::Key(KeyCode::D), 1.0)
+		.bind_axis("move_right", Binding::Key(KeyCode::Right), 1.0)
+		.bind_axis("move_right", Binding::Key(KeyCode::A), -1.0)
+		.bind_axis("move_right", Binding::Key(KeyCode::Left), -1.0)
+		.bind("move_forward", Binding::GamepadAxis(GamepadAxis::LeftY))
+		.bind("move_right", Binding::GamepadAxis(GamepadAxis::LeftX))
+		.bind("move_forward", Binding::VirtualStickY)
+		.bind("move_right", Binding::VirtualStickX)
+
+		.bind("look_right", Binding::GamepadAxis(GamepadAxis::RightX))
+		.bind("look_up", Binding::GamepadAxis(GamepadAxis::RightY))
+
+		.bind("sprint", Binding::Key(KeyCode::ShiftLeft))
+		.bind("sprint", Binding::Key(KeyCode::ShiftRight))
+		.bind("sprint", Binding::GamepadButton(GamepadButton::LeftStick))
+
+		.bind("toggle_cheats", Binding::Key(KeyCode::F1))
+		.bind("toggle_cheats", Binding::GamepadButton(GamepadButton::Select));
+
+	bindings
+}
+
+// Dragging anywhere else still looks around
+fn default_virtual_controls() -> VirtualControlsLayout {
+	let mut layout = VirtualControlsLayout::new();
+	layout.set_stick(Anchor::BottomLeft, Vec2::new(96.0, 96.0), 56.0);
+	layout
+}
+
+
+fn load_toy(bytes: &[u8]) -> EngineResult<toy::Project> {
+	Ok(toy::load(bytes)?)
+}
+
+// Only None if `init` failed, in which case nothing else is called
+fn loaded_file(file: &Option<Reloadable<toy::Project>>) -> EngineResult<&toy::Project> {
+	file.as_deref().ok_or_else(|| format_err!("main.toy hasn't been loaded"))
+}
@@ -3,6 +3,7 @@ use engine::prelude::*;
 const MAX_PLAYER_DIST: f32 = 92.0;
 const PLAYER_SPEED: f32 = 6.0;
 const MAX_PITCH: f32 = PI / 3.0;
+const STICK_LOOK_SPEED: f32 = PI; // radians per second at full tilt
 
 pub struct PlayerController {
 	pub pos: Vec3,
@@ -34,21 +35,14 @@ impl PlayerController {
 	}
 
 	pub fn update(&mut self, ctx: &engine::UpdateContext, aspect: f32) {
+		let look = Vec2::new(ctx.actions.axis("look_right"), ctx.actions.axis("look_up"));
+
 		// spin
 		if ctx.input.dragging() {
 			let raw_delta = ctx.input.frame_delta();
 			let delta = -raw_delta.x as f32 * PI * aspect;
 			self.yaw_vel += (delta - self.yaw_vel) / 3.0;
 
-			let drag_delta = ctx.input.drag_delta();
-			let drag_move_thresh = 10.0 / ctx.viewport.y as f32;
-			if drag_delta.y.abs() > drag_move_thresh {
-				let drag_move_offset = drag_move_thresh.copysign(drag_delta.y);
-				let delta = (drag_delta.y + drag_move_offset) * PLAYER_SPEED * 2.0;
-
-				self.pos += self.rot.forward() * DT * delta.clamp(-PLAYER_SPEED, PLAYER_SPEED);
-			}
-
 		} else if ctx.input_raw.is_pointer_locked() {
 			let raw_delta = ctx.input.frame_delta();
 			let yaw_delta = -raw_delta.x as f32 * PI * aspect;
@@ -56,6 +50,12 @@ impl PlayerController {
 			self.yaw_vel += (yaw_delta / 2.0 - self.yaw_vel) / 5.0;
 			self.pitch_vel += (pitch_delta / 4.0 - self.pitch_vel) / 3.0;
 
+		} else if look.x != 0.0 || look.y != 0.0 {
+			let yaw_delta = -look.x * STICK_LOOK_SPEED * ctx.frame_time;
+			let pitch_delta = look.y * STICK_LOOK_SPEED * ctx.frame_time;
+			self.yaw_vel += (yaw_delta - self.yaw_vel) / 3.0;
+			self.pitch_vel += (pitch_delta - self.pitch_vel) / 3.0;
+
 		} else {
 			self.yaw_vel *= 0.5;
 			self.pitch_vel *= 0.5;
@@ -72,29 +72,13 @@ impl PlayerController {
 
 
 		// movement
-		use engine::input::*;
-
 		let mut player_speed = PLAYER_SPEED;
-		if self.cheat_hacker_mode && ctx.input_raw.button_state(KeyCode::Shift).is_down() {
+		if self.cheat_hacker_mode && ctx.actions.down("sprint") {
 			player_speed *= 4.0;
 		}
 
-
-		if ctx.input_raw.button_state(KeyCode::W).is_down() {
-			self.pos += self.rot.forward() * player_speed * DT;
-		}
-
-		if ctx.input_raw.button_state(KeyCode::S).is_down() {
-			self.pos -= self.rot.forward() * player_speed * DT;
-		}
-
-		if ctx.input_raw.button_state(KeyCode::D).is_down() {
-			self.pos += self.rot.right() * player_speed * DT;
-		}
-
-		if ctx.input_raw.button_state(KeyCode::A).is_down() {
-			self.pos -= self.rot.right() * player_speed * DT;
-		}
+		self.pos += self.rot.forward() * ctx.actions.axis("move_forward") * player_speed * ctx.frame_time;
+		self.pos += self.rot.right() * ctx.actions.axis("move_right") * player_speed * ctx.frame_time;
 
 		// keep near the center
 		let player_dist = self.pos.to_xz().length();
@@ -102,7 +86,7 @@ impl PlayerController {
 			let to_center = -self.pos.to_xz() / player_dist;
 
 			let amt = (player_dist - MAX_PLAYER_DIST).powi(2);
-			self.pos += (to_center * amt).to_x0z() * DT;
+			self.pos += (to_center * amt).to_x0z() * ctx.frame_time;
 		}
 	}
 
@@ -20,18 +20,16 @@ pub struct SceneView {
 
 
 impl SceneView {
-	pub fn new(file: &toy::Project) -> Self {
-		let scene_shader = Shader::from_combined(
-			include_str!("scene.glsl"),
+	/// Empty until the first `rebuild`
+	pub fn new() -> Self {
+		let scene_shader = Shader::from_asset(
+			embed_asset!("scene.glsl"),
 			&["position", "color"]
 		);
 
-		let main_scene = file.find_scene("main").unwrap();
-		let static_mesh = bake_static_scene_mesh(main_scene).unwrap();
-
 		SceneView {
 			scene_shader,
-			static_mesh,
+			static_mesh: DynamicMesh::new(),
 			dynamic_mesh: DynamicMesh::new(),
 			ui_mesh: DynamicMesh::new(),
 
@@ -39,16 +37,32 @@ impl SceneView {
 		}
 	}
 
-	pub fn update(&mut self, file: &toy::Project, game_state: &GameState) {
+	/// Rebakes everything from scratch, e.g. after `file` has been loaded or reloaded
+	pub fn rebuild(&mut self, file: &toy::Project) -> EngineResult<()> {
+		let main_scene = file.find_scene("main")
+			.ok_or_else(|| format_err!("Couldn't find scene 'main' in toy file"))?;
+
+		self.static_mesh = bake_static_scene_mesh(main_scene)?;
+
+		// Forces the dynamic and ui meshes to be rebaked on the next update
+		self.prev_game_state = 0;
+		Ok(())
+	}
+
+	pub fn update(&mut self, file: &toy::Project, game_state: &GameState) -> EngineResult<()> {
 		let mut hasher = DefaultHasher::new();
 		game_state.hash(&mut hasher); 
 		let new_hash = hasher.finish();
 
 		if self.prev_game_state != new_hash {
-			self.build_dynamic(file, game_state).unwrap();
-			self.build_ui(file, game_state).unwrap();
+			profile_scope!("bake");
+
+			self.build_dynamic(file, game_state)?;
+			self.build_ui(file, game_state)?;
 			self.prev_game_state = new_hash;
 		}
+
+		Ok(())
 	}
 
 
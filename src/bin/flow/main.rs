@@ -1,5 +1,6 @@
 extern crate wasm_toys as engine;
 use engine::prelude::*;
+use engine::input::Consume;
 
 use vertex::ColorVertex;
 
@@ -13,6 +14,7 @@ struct App {
 	camera: Camera,
 	camera_focal_point: Vec3,
 	camera_dist: f32,
+	zoom: f32,
 
 	main_shader: Shader,
 	particle_mesh: BasicDynamicMesh<ParticleVertex>,
@@ -33,13 +35,13 @@ impl App {
 		let mut camera = Camera::new();
 		camera.set_near_far(0.1, 3000.0);
 
-		let main_shader = Shader::from_combined(
-			include_str!("main.glsl"),
+		let main_shader = Shader::from_asset(
+			embed_asset!("main.glsl"),
 			&["position", "part_info"]
 		); 
 
-		let color_shader = Shader::from_combined(
-			include_str!("color.glsl"),
+		let color_shader = Shader::from_asset(
+			embed_asset!("color.glsl"),
 			&["position", "color"]
 		); 
 
@@ -51,6 +53,7 @@ impl App {
 			camera,
 			camera_focal_point: Vec3::zero(),
 			camera_dist: 2.0,
+			zoom: 1.0,
 
 			main_shader, color_shader,
 			particle_mesh, lines_mesh, grid_lines_mesh,
@@ -62,25 +65,11 @@ impl App {
 		}
 	}
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
-		unsafe {
-			let (r,g,b,_) = Color::hsv(310.0, 0.3, 0.15).to_tuple();
-
-			gl::clear_color(r, g, b, 1.0);
-			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-		}
-
+	fn simulate(&mut self, ctx: engine::UpdateContext) {
 		self.regen_noise_timer -= DT;
 
-		if ctx.input.tap() || self.regen_noise_timer < 0.0 {
-			for _ in 0..100 {
-				self.spawn_particle();
-			}
-
-			self.perlin = noise::Perlin::new((rand() * 6.0) as usize + 3);
-			self.build_lines();
-
-			self.regen_noise_timer = rand() * 10.0 + 5.0;
+		if self.regen_noise_timer < 0.0 {
+			self.regenerate_noise();
 
 		} else if ctx.input.holding() {
 			for p in self.particles.iter_mut() {
@@ -90,8 +79,6 @@ impl App {
 
 		self.update_particles();
 
-		let t = ctx.ticks as f32 * DT;
-
 		let part_min = self.particles.iter()
 			.map(|p| p.pos)
 			.fold(
@@ -113,17 +100,40 @@ impl App {
 
 		self.camera_focal_point = (DT/3.0).lerp(self.camera_focal_point, part_center);
 		self.camera_dist = (DT/3.0).lerp(self.camera_dist, max_spread);
+	}
+
+	fn update(&mut self, ctx: engine::UpdateContext) {
+		unsafe {
+			let (r,g,b,_) = Color::hsv(310.0, 0.3, 0.15).to_tuple();
+
+			gl::clear_color(r, g, b, 1.0);
+			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+		}
+
+		if ctx.input.tap() {
+			self.regenerate_noise();
+		}
+
+		self.zoom = (self.zoom / ctx.input.pinch_delta()).max(0.3).min(3.0);
 
+		let t = ctx.time();
 
 		let ori = Quat::new(Vec3::from_y(1.0), t/7.0)
 			* Quat::new(Vec3::from_x(1.0), (t/8.0).cos() * -PI/15.0);
 
 		self.camera.update(ctx.viewport);
 		self.camera.set_orientation(ori);
-		self.camera.set_position(self.camera_focal_point + ori * Vec3::from_z(self.camera_dist));
+		self.camera.set_position(self.camera_focal_point + ori * Vec3::from_z(self.camera_dist * self.zoom));
 
 		let it_size = PARTICLE_EXTENT * ctx.viewport.x.min(ctx.viewport.y) as f32;
 
+		self.particle_mesh.clear();
+
+		for p in self.particles.iter() {
+			let v = p.vel.extend(p.lifetime);
+			self.particle_mesh.add_vertex(ParticleVertex(p.pos, v));
+		}
+
 		self.main_shader.bind();
 		self.main_shader.set_uniform("proj_view", self.camera.projection_view());
 		self.main_shader.set_uniform("particle_scale", it_size);
@@ -137,6 +147,8 @@ impl App {
 	}
 
 	fn update_particles(&mut self) {
+		profile_scope!("particles");
+
 		for p in self.particles.iter_mut() {
 			let v = Self::sample(&self.perlin, p.pos);
 
@@ -153,13 +165,17 @@ impl App {
 				self.spawn_particle();
 			}
 		}
+	}
 
-		self.particle_mesh.clear();
-
-		for p in self.particles.iter() {
-			let v = p.vel.extend(p.lifetime);
-			self.particle_mesh.add_vertex(ParticleVertex(p.pos, v));
+	fn regenerate_noise(&mut self) {
+		for _ in 0..100 {
+			self.spawn_particle();
 		}
+
+		self.perlin = noise::Perlin::new((rand() * 6.0) as usize + 3);
+		self.build_lines();
+
+		self.regen_noise_timer = rand() * 10.0 + 5.0;
 	}
 
 	fn spawn_particle(&mut self) {
@@ -246,18 +262,28 @@ impl EngineClient for App {
 	fn uses_passive_input(&self) -> bool { true }
 	fn captures_input(&self) -> bool { false }
 	fn hold_threshold(&self) -> Option<Ticks> { Some(20) }
+	fn keyboard_gestures(&self) -> bool { true }
+	// Embedded in a page, so only zoom once it has been clicked into
+	fn consumes_wheel(&self) -> Consume { Consume::WhileFocused }
 
-	fn init(&mut self) {
+	fn init(&mut self) -> EngineResult<()> {
 		for _ in 0..6000 {
 			self.spawn_particle();
 		}
 
 		self.build_grid();
 		self.build_lines();
+		Ok(())
 	}
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
+	fn simulate(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		self.simulate(ctx);
+		Ok(())
+	}
+
+	fn update(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
 		self.update(ctx);
+		Ok(())
 	}
 }
 
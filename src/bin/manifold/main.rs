@@ -17,6 +17,9 @@ fn main() {
 type Vertex = vertex::ColorVertex;
 type Mesh = BasicDynamicMesh<Vertex>;
 
+// Points are tiny, so they're drawn bigger for picking than they look
+const PICK_POINT_SIZE: f32 = 12.0;
+
 struct ManifoldObject {
 	pos: Vec2,
 	color: Vec3,
@@ -27,6 +30,11 @@ struct App {
 	shader: Shader,
 	mesh: Mesh,
 
+	picker: IdPicker,
+	id_mesh: BasicDynamicMesh<vertex::IdVertex>,
+	// Where the tap waiting on the picker was, on the manifold
+	pending_tap: Option<(PickQuery, Vec2)>,
+
 	manifold: ToroidManifold,
 
 	position: Vec2, // on the manifold
@@ -36,8 +44,8 @@ struct App {
 
 impl App {
 	fn new() -> App {
-		let shader = Shader::from_combined(
-			include_str!("main.glsl"),
+		let shader = Shader::from_asset(
+			embed_asset!("main.glsl"),
 			&["position", "color"]
 		);
 
@@ -65,6 +73,10 @@ impl App {
 		App {
 			shader,
 			mesh: Mesh::new(),
+
+			picker: IdPicker::new(),
+			id_mesh: BasicDynamicMesh::new(),
+			pending_tap: None,
 			manifold: ToroidManifold::new(Vec2::splat(10.0)),
 			position: Vec2::zero(),
 			velocity: Vec2::zero(),
@@ -74,12 +86,16 @@ impl App {
 
 	fn rebuild_chart(&mut self) {
 		self.mesh.clear();
+		self.id_mesh.clear();
 		self.mesh.add_vertex(Vertex::new(Vec3::zero(), Vec3::splat(1.0)));
 
 		let chart = self.manifold.chart(self.position);
-		for obj in self.objects.iter() {
+		for (idx, obj) in self.objects.iter().enumerate() {
 			if let Some(chart_pos) = chart.from_manifold(obj.pos) {
 				self.mesh.add_vertex(Vertex::new(chart_pos.extend(0.0), obj.color));
+
+				// 0 is nothing picked
+				self.id_mesh.add_vertex(vertex::IdVertex::new(chart_pos.extend(0.0), idx as u32 + 1));
 			}
 		}
 	}
@@ -87,40 +103,17 @@ impl App {
 
 impl EngineClient for App {
 	fn uses_passive_input(&self) -> bool { false }
-	fn drag_threshold(&self) -> Option<u32> { Some(10) }
+	fn drag_threshold(&self) -> Option<f32> { Some(2.5) }
 	fn hold_threshold(&self) -> Option<Ticks> { Some(30) }
+	fn keyboard_gestures(&self) -> bool { true }
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
-		unsafe {
-			let (r,g,b,_) = Color::hsv(301.0, 0.46, 0.28).to_tuple();
-
-			gl::clear_color(r, g, b, 1.0);
-			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-		}
-
-		let chart = self.manifold.chart(self.position);
-
-		if ctx.input.dragging() {
-			self.velocity -= ctx.input.frame_delta() * 4.0;
-		} else {
+	fn simulate(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		if !ctx.input.dragging() {
 			self.velocity *= 1.0 - 4.0*DT;
 		}
 
 		self.position += self.velocity * DT;
 
-		if ctx.input.tap() {
-			let chart_pos = ctx.input.position();
-			let manifold_pos = chart.to_manifold(chart_pos).unwrap();
-
-			for _ in 0..100 {
-				self.objects.push(ManifoldObject {
-					pos: manifold_pos,
-					color: Vec3::new(0.3, 1.0, 0.3),
-					vel: Vec2::from_angle(rand() * 2.0 * PI) * rand(),
-				});
-			}
-		}
-
 		for obj in self.objects.iter_mut() {
 			let diff = self.manifold.difference(self.position, obj.pos);
 			let dist = diff.length().powf(2.0).max(0.05);
@@ -140,11 +133,71 @@ impl EngineClient for App {
 			obj.pos += obj.vel * DT;
 		}
 
+		Ok(())
+	}
+
+	fn update(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		unsafe {
+			let (r,g,b,_) = Color::hsv(301.0, 0.46, 0.28).to_tuple();
+
+			gl::clear_color(r, g, b, 1.0);
+			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+		}
+
+		let chart = self.manifold.chart(self.position);
+
+		if ctx.input.dragging() {
+			self.velocity -= ctx.input.frame_delta() * 4.0;
+		}
+
+		if ctx.input.tap() && self.pending_tap.is_none() {
+			let chart_pos = ctx.input.position();
+			let manifold_pos = chart.to_manifold(chart_pos)
+				.ok_or_else(|| format_err!("Tapped outside of chart at {:?}", chart_pos))?;
+
+			self.pending_tap = Some((self.picker.request(chart_pos), manifold_pos));
+		}
+
 		self.rebuild_chart();
 
+		if self.picker.has_pending() {
+			let pass = self.picker.begin_pass(ctx.viewport);
+			pass.set_point_size(PICK_POINT_SIZE * ctx.pixel_ratio);
+			self.id_mesh.draw(gl::DrawMode::Points);
+		}
+
+		// Tapping a point picks it out, tapping anywhere else spawns more
+		if let Some((query, manifold_pos)) = self.pending_tap {
+			match self.picker.status(query) {
+				PickStatus::Pending => {}
+
+				PickStatus::Picked(Some(id)) => {
+					if let Some(obj) = self.objects.get_mut(id as usize - 1) {
+						obj.color = Vec3::new(1.0, 1.0, 0.3);
+					}
+
+					self.pending_tap = None;
+				}
+
+				PickStatus::Picked(None) | PickStatus::Expired => {
+					for _ in 0..100 {
+						self.objects.push(ManifoldObject {
+							pos: manifold_pos,
+							color: Vec3::new(0.3, 1.0, 0.3),
+							vel: Vec2::from_angle(rand() * 2.0 * PI) * rand(),
+						});
+					}
+
+					self.pending_tap = None;
+				}
+			}
+		}
+
 		self.shader.bind();
 		self.shader.set_uniform("u_proj_view", Mat4::ident());
 
 		self.mesh.draw(gl::DrawMode::Points);
+
+		Ok(())
 	}
 }
\ No newline at end of file
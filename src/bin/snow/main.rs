@@ -34,13 +34,13 @@ impl SnowApp {
 		let mut camera = Camera::new();
 		camera.set_near_far(0.1, 100.0);
 
-		let scene_shader = Shader::from_combined(
-			include_str!("scene.glsl"),
+		let scene_shader = Shader::from_asset(
+			embed_asset!("scene.glsl"),
 			&["position"]
 		);
 
-		let snow_shader = Shader::from_combined(
-			include_str!("snow.glsl"),
+		let snow_shader = Shader::from_asset(
+			embed_asset!("snow.glsl"),
 			&["position", "sprite_stage"]
 		);
 
@@ -144,7 +144,7 @@ impl SnowApp {
 			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
 		}
 
-		let time = ctx.ticks as f32 * engine::DT;
+		let time = ctx.time();
 
 		let quat = Quat::new(Vec3::from_y(1.0), (time/3.0).sin()*PI/24.0);
 		let position = quat * Vec3::from_z(3.0) + Vec3::from_y(0.0);
@@ -216,16 +216,23 @@ struct Particle {
 impl EngineClient for SnowApp {
 	fn uses_passive_input(&self) -> bool { false }
 
-	fn init(&mut self) {
+	fn init(&mut self) -> EngineResult<()> {
 		// Prebake some particles
 		for _ in 0..600 {
 			self.update_particles();
 		}
+
+		Ok(())
 	}
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
+	fn simulate(&mut self, _: engine::UpdateContext) -> EngineResult<()> {
 		self.update_particles();
+		Ok(())
+	}
+
+	fn update(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
 		self.render(ctx);
+		Ok(())
 	}
 }
 
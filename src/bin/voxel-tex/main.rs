@@ -1,5 +1,6 @@
 extern crate wasm_toys as engine;
 use engine::prelude::*;
+use engine::input::{GamepadAxis, Consume};
 
 fn main() {
 	engine::init_engine(VoxelApp::new);
@@ -15,12 +16,13 @@ struct VoxelApp {
 
 	angle_vel: Vec2,
 	angle: Vec2,
+	distance: f32,
 }
 
 impl VoxelApp {
 	fn new() -> VoxelApp {
-		let program = Shader::from_combined(
-			include_str!("color.glsl"),
+		let program = Shader::from_asset(
+			embed_asset!("color.glsl"),
 			&["position", "normal", "voxel_pos"]
 		);
 
@@ -65,15 +67,34 @@ impl VoxelApp {
 
 			angle_vel: Vec2::zero(),
 			angle: Vec2::zero(),
+			distance: 10.0,
 		}
 	}
 }
 
 impl EngineClient for VoxelApp {
 	fn uses_passive_input(&self) -> bool { false }
-	fn drag_threshold(&self) -> Option<u32> { None } // Always drag
+	fn drag_threshold(&self) -> Option<f32> { None } // Always drag
+	fn consumes_wheel(&self) -> Consume { Consume::Always }
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
+	fn simulate(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
+		if ctx.input.dragging() {
+			return Ok(());
+		}
+
+		if ctx.input_raw.gamepad_axis(GamepadAxis::RightX) != 0.0 || ctx.input_raw.gamepad_axis(GamepadAxis::RightY) != 0.0 {
+			let delta_x = -ctx.input_raw.gamepad_axis(GamepadAxis::RightX) * PI * DT;
+			let delta_y = -ctx.input_raw.gamepad_axis(GamepadAxis::RightY) * PI * DT;
+			self.angle_vel += (Vec2::new(delta_x, delta_y) - self.angle_vel) / 5.0;
+		} else {
+			self.angle_vel *= 1.0 - 3.0*DT;
+		}
+
+		self.angle += self.angle_vel;
+		Ok(())
+	}
+
+	fn update(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
 		unsafe {
 			let (r,g,b,_) = Color::hsv(301.0, 0.46, 0.28).to_tuple();
 
@@ -85,25 +106,24 @@ impl EngineClient for VoxelApp {
 
 		self.program.bind();
 
-		// spin
+		// spin. Drags follow the pointer, so they're applied per frame rather than in simulate
 		if ctx.input.dragging() {
 			let raw_delta = ctx.input.frame_delta();
 			let delta_x = -raw_delta.x as f32 * PI * self.camera.aspect();
 			let delta_y =  raw_delta.y as f32 * PI;
 			self.angle_vel += (Vec2::new(delta_x, delta_y) - self.angle_vel) / 5.0;
-
-		} else {
-			self.angle_vel *= 1.0 - 3.0*DT;
+			self.angle += self.angle_vel;
 		}
 
-		self.angle += self.angle_vel;
+		self.angle.x += ctx.input.twist_delta();
+		self.distance = (self.distance / ctx.input.pinch_delta()).max(3.0).min(40.0);
 
 
 		// position camera
 		let quat_x = Quat::new(Vec3::from_y(1.0), self.angle.x);
 		let quat_y = Quat::new(Vec3::from_x(1.0), self.angle.y);
 		let quat = quat_x * quat_y;
-		let position = quat * Vec3::from_z(10.0) + Vec3::new(16.0, 4.0, 16.0);
+		let position = quat * Vec3::from_z(self.distance) + Vec3::new(16.0, 4.0, 16.0);
 
 		self.camera.update(ctx.viewport);
 		self.camera.set_orientation(quat);
@@ -118,6 +138,8 @@ impl EngineClient for VoxelApp {
 		self.program.set_uniform("u_voxel_data", 0);
 		self.program.set_uniform("u_voxel_chunk_size", chunk_size);
 
+		profile_scope!("chunks");
+
 		for z in 0..8 {
 			for x in 0..8 {
 				let transform = proj_view * Mat4::translate(Vec3::new(x as f32 * chunk_size, 0.0, z as f32 * chunk_size));
@@ -126,6 +148,8 @@ impl EngineClient for VoxelApp {
 				self.voxel_chunk_mesh.draw(gl::DrawMode::Triangles);
 			}
 		}
+
+		Ok(())
 	}
 }
 
@@ -25,8 +25,8 @@ impl App {
 		camera.set_projection(camera::Projection::Orthographic{ scale: 1.0 });
 		camera.set_near_far(-1.0, 1.0);
 
-		let shader = Shader::from_combined(
-			include_str!("main.glsl"),
+		let shader = Shader::from_asset(
+			embed_asset!("main.glsl"),
 			&["position", "color"]
 		);
 
@@ -79,17 +79,21 @@ impl App {
 }
 
 impl EngineClient for App {
-	fn init(&mut self) {
+	fn init(&mut self) -> EngineResult<()> {
 		self.generate_waves();
+		Ok(())
 	}
 
-	fn update(&mut self, ctx: engine::UpdateContext) {
+	fn simulate(&mut self, _: engine::UpdateContext) -> EngineResult<()> {
+		for wave in self.waves.iter_mut() { wave.update() }
+		Ok(())
+	}
+
+	fn update(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
 		if ctx.input.tap() {
 			self.generate_waves();
 		}
 
-		for wave in self.waves.iter_mut() { wave.update() }
-
 		self.build_waves();
 
 		unsafe {
@@ -106,6 +110,8 @@ impl EngineClient for App {
 		self.shader.set_uniform("proj_view", self.camera.projection_view());
 
 		self.mesh.draw(gl::DrawMode::Triangles);
+
+		Ok(())
 	}
 }
 
@@ -29,8 +29,8 @@ impl App {
 		camera.set_projection(camera::Projection::Orthographic{ scale: WORLD_SCALE });
 		camera.set_near_far(-1.0, 1.0);
 
-		let shader = Shader::from_combined(
-			include_str!("main.glsl"),
+		let shader = Shader::from_asset(
+			embed_asset!("main.glsl"),
 			&["pos_part_a", "pos_b", "color", "side", "body_pos", "width"]
 		);
 
@@ -46,7 +46,29 @@ impl App {
 
 
 impl EngineClient for App {
-	fn update(&mut self, ctx: engine::UpdateContext) {
+	fn simulate(&mut self, _: engine::UpdateContext) -> EngineResult<()> {
+		let largest_aspect = self.camera.aspect().max(1.0 / self.camera.aspect());
+		let screen_radius = WORLD_SCALE.hypot(WORLD_SCALE * largest_aspect) + 1.0;
+
+		for worm in self.worms.iter_mut() {
+			worm.update();
+
+			let head_dist = worm.head().length();
+			let tail_dist = worm.tail().length();
+
+			let least_dist = tail_dist.min(head_dist);
+
+			if least_dist > screen_radius {
+				let centre_dir = -worm.head().normalize();
+				worm.heading = centre_dir.to_angle();
+				worm.heading_tendency = 0.0;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn update(&mut self, ctx: engine::UpdateContext) -> EngineResult<()> {
 		unsafe {
 			let (r,g,b,_) = Color::rgb8(199, 145, 70).to_tuple();
 
@@ -67,32 +89,17 @@ impl EngineClient for App {
 
 		self.mesh.clear();
 
-		let largest_aspect = self.camera.aspect().max(1.0 / self.camera.aspect());
-		let screen_radius = WORLD_SCALE.hypot(WORLD_SCALE * largest_aspect) + 1.0;
-
-		for worm in self.worms.iter_mut() {
-			worm.update();
-
-			let head_dist = worm.head().length();
-			let tail_dist = worm.tail().length();
-
-			let least_dist = tail_dist.min(head_dist);
-
-			if least_dist > screen_radius {
-				let centre_dir = -worm.head().normalize();
-				worm.heading = centre_dir.to_angle();
-				worm.heading_tendency = 0.0;
-			}
-		}
-
 		for worm in self.worms.iter() {
 			draw_worm(&mut self.mesh, worm);
 		}
 
 		self.mesh.draw(gl::DrawMode::Triangles);
+
+		Ok(())
 	}
 
-	fn drag_threshold(&self) -> Option<u32> { Some(30) }
+	fn drag_threshold(&self) -> Option<f32> { Some(8.0) }
+	fn keyboard_gestures(&self) -> bool { true }
 }
 
 
@@ -1,82 +1,811 @@
 use common::math::*;
-use crate::input::{InputContext, GestureTracker};
+use common::Color;
+use crate::input::{InputContext, GestureTracker, Actions, Bindings, InputEvent, TimedInputEvent, InputLog, InputReplay};
+use crate::input::{GamepadButton, GamepadAxis, MAX_GAMEPADS};
+use crate::input::{VirtualControls, VirtualControlsLayout};
+use crate::input::{KeyCode, Consume};
+use crate::input::keyboard_gestures::KeyboardCursorRenderer;
+use crate::imports::input;
 use crate::imports::gl;
+use crate::graphics::text::{TextRenderer, wrap_text, measure_text, GLYPH_WIDTH};
+use crate::profiler;
+use crate::DT;
 
 pub type Ticks = u32;
 pub type EngineResult<T> = Result<T, failure::Error>;
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EngineID(pub u32);
+
+// Frames longer than this are clamped, so a stall doesn't turn into a flood of catch-up steps
+const MAX_FRAME_TIME: f64 = 0.25;
+
+const ERROR_TEXT_SCALE: f32 = 2.0;
+const ERROR_MARGIN: f32 = 16.0;
+
+const PROFILER_TEXT_SCALE: f32 = 1.0;
+const PROFILER_MARGIN: f32 = 8.0;
+
 pub struct Engine {
 	pub client: Box<dyn EngineClient>,
 
 	pub input_context: InputContext,
 	pub gesture_tracker: GestureTracker,
+	pub actions: Actions,
+	pub virtual_controls: VirtualControls,
 
+	/// In framebuffer pixels
 	pub viewport: Vec2i,
+	/// As reported by the browser - physical pixels per CSS pixel
+	pub device_pixel_ratio: f32,
+	/// Framebuffer pixels per CSS pixel - the device pixel ratio times the client's `render_scale`
+	pub pixel_ratio: f32,
 	pub time_ticks: Ticks,
+
+	/// Input that's arrived since the last frame, waiting to be applied at the start of the next
+	input_queue: Vec<TimedInputEvent>,
+
+	prev_frame_timestamp: Option<f64>,
+	frame_time: f32,
+	accumulator: f64,
+
+	focused: bool,
+	suspended: bool,
+
+	failure: Option<failure::Error>,
+	show_profiler: bool,
+	overlay_text: Option<TextRenderer>,
+	keyboard_cursor_renderer: Option<KeyboardCursorRenderer>,
+
+	recording: Option<InputLog>,
+	replay: Option<InputReplay>,
 }
 
 impl Engine {
-	pub fn new<C: EngineClient + 'static>(client: C) -> Self {
-		unsafe {
-			gl::enable_attribute(0);
+	/// `init` must be called before the first update
+	pub fn new(client: Box<dyn EngineClient>) -> Self {
+		reset_gl_state();
+
+		let hooks = innermost(&*client);
+		let mut input_context = InputContext::new(hooks.uses_passive_input());
+		input_context.enable_pointer_lock(hooks.captures_input());
+
+		let gesture_tracker = create_gesture_tracker(hooks);
+
+		Engine {
+			client,
+			input_context,
+			gesture_tracker,
+			actions: Actions::new(),
+			virtual_controls: VirtualControls::new(),
+
+			viewport: Vec2i::new(0, 0),
+			device_pixel_ratio: 1.0,
+			pixel_ratio: 1.0,
+			time_ticks: 0,
+
+			input_queue: Vec::new(),
+
+			prev_frame_timestamp: None,
+			frame_time: DT,
+			accumulator: 0.0,
+
+			focused: true,
+			suspended: false,
+
+			failure: None,
+			show_profiler: false,
+			overlay_text: None,
+			keyboard_cursor_renderer: None,
+
+			recording: None,
+			replay: None,
+		}
+	}
+
+	pub fn init(&mut self) {
+		let result = self.client.init()
+			.and_then(|_| self.hooks_mut().load_state());
+
+		if let Err(err) = result {
+			self.fail(err);
+		}
+	}
+
+	/// Throws away the current client and any failure, and starts again from scratch with `client`
+	pub fn restart(&mut self, client: Box<dyn EngineClient>) {
+		reset_gl_state();
+
+		// Input listeners are already set up, so the context is kept rather than recreated
+		self.input_queue.clear();
+		self.input_context.reset_inputs();
+		self.input_context.enable_text_input(false);
+		self.recording = None;
+		self.replay = None;
+
+		self.input_context.enable_pointer_lock(innermost(&*client).captures_input());
+		self.gesture_tracker = create_gesture_tracker(innermost(&*client));
+		self.actions = Actions::new();
+		self.virtual_controls = VirtualControls::new();
+		self.client = client;
+
+		self.time_ticks = 0;
+		self.prev_frame_timestamp = None;
+		self.frame_time = DT;
+		self.accumulator = 0.0;
+		self.failure = None;
+
+		self.init();
 
-			gl::enable(gl::Capability::DepthTest);
-			gl::enable(gl::Capability::Blend);
-			gl::blend_func(gl::BlendFactor::One, gl::BlendFactor::OneMinusSrcAlpha);
+		// The new client has never seen the viewport or focus state
+		if self.failure.is_none() {
+			let viewport = self.viewport;
+			self.hooks_mut().on_resize(Vec2i::new(0, 0), viewport);
+		}
+
+		if self.failure.is_none() && !self.focused {
+			self.hooks_mut().on_focus_changed(false);
+		}
+	}
+
+	/// The error that stopped the client, if any. Once failed, the client gets no more calls until `restart`
+	pub fn failure(&self) -> Option<&failure::Error> { self.failure.as_ref() }
+	pub fn has_failed(&self) -> bool { self.failure.is_some() }
+
+	/// `viewport` is the size of the framebuffer, which the page sizes to the canvas' CSS size times `device_pixel_ratio`
+	/// times `render_scale`
+	pub fn set_viewport(&mut self, viewport: Vec2i, device_pixel_ratio: f32) {
+		self.device_pixel_ratio = device_pixel_ratio;
+		self.pixel_ratio = device_pixel_ratio * self.render_scale();
+		self.input_context.pixel_ratio = self.pixel_ratio;
+
+		if viewport == self.viewport { return }
+
+		let prev = self.viewport;
+		self.viewport = viewport;
 
-			let mut input_context = InputContext::new(client.uses_passive_input());
-			input_context.enable_pointer_lock(client.captures_input());
+		if self.failure.is_none() {
+			self.hooks_mut().on_resize(prev, viewport);
+		}
+	}
+
+	/// The client that every hook but `init`, `simulate` and `update` is called on - see `EngineClient::inner`
+	pub fn hooks(&self) -> &dyn EngineClient { innermost(&*self.client) }
+	fn hooks_mut(&mut self) -> &mut dyn EngineClient { innermost_mut(&mut *self.client) }
+
+	/// The client's `render_scale`, within reason
+	pub fn render_scale(&self) -> f32 {
+		self.hooks().render_scale().max(0.1).min(4.0)
+	}
+
+	/// Live input goes through here, so that it can be recorded, or ignored while a replay is running.
+	/// Events are queued, and applied in order at the start of the next `update`
+	pub fn handle_input(&mut self, event: InputEvent) {
+		self.handle_input_at(profiler::now(), event);
+	}
 
-			let drag_threshold = client.drag_threshold().unwrap_or(0);
-			let hold_threshold = client.hold_threshold().unwrap_or(std::u32::MAX);
+	/// `timestamp` is the browser's time for the event, in milliseconds
+	pub fn handle_input_at(&mut self, timestamp: f64, event: InputEvent) {
+		if self.replay.is_some() { return }
 
-			Engine {
-				client: Box::new(client),
-				input_context,
-				gesture_tracker: GestureTracker::new(drag_threshold, hold_threshold),
+		self.input_context.handle_user_gesture(&event);
+		self.input_queue.push(TimedInputEvent { timestamp, event });
+	}
 
-				viewport: Vec2i::new(0, 0),
-				time_ticks: 0,
+	fn process_input_queue(&mut self) {
+		for TimedInputEvent { timestamp, event } in std::mem::take(&mut self.input_queue) {
+			if let Some(log) = self.recording.as_mut() {
+				log.push_event(self.time_ticks, event.clone());
 			}
+
+			self.input_context.process_event(timestamp, event);
+		}
+	}
+
+	/// Starts logging input from a clean slate. For an exact reproduction the log should be replayed into
+	/// a client in the same state as this one - simplest is to `restart` before both recording and replaying
+	pub fn start_recording(&mut self) {
+		self.input_queue.clear();
+		self.input_context.reset_inputs();
+		self.recording = Some(InputLog::new());
+	}
+
+	pub fn stop_recording(&mut self) -> Option<InputLog> {
+		self.recording.take()
+	}
+
+	/// Replaces live input with `log` until it runs out. Frame times are taken from the log too,
+	/// so the client sees exactly the same sequence of simulate steps and input edges as when it was recorded
+	pub fn start_replay(&mut self, log: InputLog) {
+		self.input_queue.clear();
+		self.input_context.reset_inputs();
+		self.recording = None;
+		self.replay = Some(InputReplay::new(log));
+	}
+
+	pub fn is_recording(&self) -> bool { self.recording.is_some() }
+	pub fn is_replaying(&self) -> bool { self.replay.is_some() }
+
+	pub fn set_focused(&mut self, focused: bool) {
+		self.handle_input(InputEvent::Reset);
+
+		if focused == self.focused { return }
+
+		self.focused = focused;
+
+		if self.failure.is_none() {
+			self.hooks_mut().on_focus_changed(focused);
 		}
 	}
 
-	pub fn update(&mut self, _time: f64) {
+	pub fn set_visible(&mut self, visible: bool) {
+		if visible != self.suspended { return }
+
+		self.suspended = !visible;
+		self.handle_input(InputEvent::Reset);
+
+		if self.failure.is_some() { return }
+
+		if self.suspended {
+			self.hooks_mut().on_suspend();
+
+			if let Err(err) = self.hooks_mut().save_state() {
+				self.fail(err);
+			}
+		} else {
+			// Forget about the time spent hidden, otherwise the first frame back would try to catch up on it
+			self.prev_frame_timestamp = None;
+			self.accumulator = 0.0;
+
+			if let Err(err) = self.hooks_mut().load_state() {
+				self.fail(err);
+				return;
+			}
+
+			self.hooks_mut().on_resume();
+		}
+	}
+
+	/// Draws per scope timings over the top of the client. Only has an effect with the `profiler` feature enabled
+	pub fn set_profiler_overlay(&mut self, show: bool) {
+		if show && !profiler::is_enabled() {
+			console_warn!("Profiler overlay requested, but the profiler is disabled - rebuild with `--features profiler`");
+		}
+
+		self.show_profiler = show;
+	}
+
+	pub fn is_focused(&self) -> bool { self.focused }
+	pub fn is_suspended(&self) -> bool { self.suspended }
+
+	/// `timestamp` is in milliseconds, as passed to requestAnimationFrame callbacks
+	pub fn update(&mut self, timestamp: f64) {
+		if self.suspended { return }
+
+		profiler::begin_frame(timestamp);
+
+		self.poll_gamepads();
+		self.process_input_queue();
+
+		if self.failure.is_some() {
+			self.draw_error_screen();
+			self.input_context.clear_frame_state();
+			return;
+		}
+
+		let live_frame_time = match self.prev_frame_timestamp {
+			Some(prev) => ((timestamp - prev) / 1000.0).max(0.0).min(MAX_FRAME_TIME),
+			None => DT as f64,
+		};
+
+		self.prev_frame_timestamp = Some(timestamp);
+
+		let frame_time = match self.replay.as_mut().map(|r| r.next_frame(&mut self.input_context, timestamp)) {
+			Some(Some(recorded_frame_time)) => recorded_frame_time,
+			Some(None) => {
+				console_log!("Input replay finished at tick {}", self.time_ticks);
+				self.replay = None;
+				live_frame_time
+			}
+
+			None => live_frame_time,
+		};
+
+		if let Some(log) = self.recording.as_mut() {
+			log.push_frame(self.time_ticks, frame_time);
+		}
+
+		for &(index, connected) in self.input_context.gamepad_connection_changes() {
+			innermost_mut(&mut *self.client).on_gamepad_connection_changed(index, connected);
+		}
+
+		self.frame_time = frame_time as f32;
+		self.accumulator += frame_time;
+
 		unsafe {
 			let Vec2i{x, y} = self.viewport;
 			gl::viewport(0, 0, x, y);
 		}
 
+		let css_size = self.viewport.to_vec2() / self.pixel_ratio;
+		self.virtual_controls.update(innermost(&*self.client).virtual_controls(), &mut self.input_context, css_size);
+
 		self.gesture_tracker.update(&self.input_context, self.viewport, self.time_ticks);
+		self.actions.update(innermost(&*self.client).bindings(), &self.input_context, &self.gesture_tracker, &self.virtual_controls);
+
+		while self.accumulator >= DT as f64 {
+			profile_scope!("simulate");
+
+			self.accumulator -= DT as f64;
+
+			let sim_ctx = UpdateContext {
+				ticks: self.time_ticks,
+				viewport: self.viewport,
+				pixel_ratio: self.pixel_ratio,
+				input: &self.gesture_tracker,
+				input_raw: &self.input_context,
+				actions: &self.actions,
+
+				frame_time: self.frame_time,
+				fixed_dt: DT,
+				alpha: 0.0,
+			};
+
+			if let Err(err) = self.client.simulate(sim_ctx) {
+				self.fail(err);
+				self.input_context.clear_frame_state();
+				return;
+			}
+
+			self.time_ticks = self.time_ticks.wrapping_add(1);
+		}
 
 		let upd_ctx = UpdateContext {
 			ticks: self.time_ticks,
 			viewport: self.viewport,
+			pixel_ratio: self.pixel_ratio,
 			input: &self.gesture_tracker,
 			input_raw: &self.input_context,
+			actions: &self.actions,
+
+			frame_time: self.frame_time,
+			fixed_dt: DT,
+			alpha: (self.accumulator / DT as f64) as f32,
+		};
+
+		let result = {
+			profile_scope!("update");
+			self.client.update(upd_ctx)
 		};
 
-		self.client.update(upd_ctx);
+		if let Err(err) = result {
+			self.fail(err);
+		} else {
+			self.virtual_controls.draw(innermost(&*self.client).virtual_controls(), self.viewport, self.pixel_ratio);
+
+			if let Some(cursor) = self.gesture_tracker.keyboard_cursor_state() {
+				self.keyboard_cursor_renderer.get_or_insert_with(KeyboardCursorRenderer::new)
+					.draw(cursor, self.viewport, self.pixel_ratio);
+			}
+
+			if self.show_profiler && profiler::is_enabled() {
+				self.draw_profiler_overlay();
+			}
+		}
 
 		self.input_context.clear_frame_state();
-		self.time_ticks = self.time_ticks.wrapping_add(1);
+	}
+
+	/// The Gamepad API has no events for buttons or axes, so pads are polled once per frame and any changes
+	/// are turned into events, which means they're recorded and replayed like everything else
+	fn poll_gamepads(&mut self) {
+		let mut buttons = [0u8; GamepadButton::Count as usize];
+		let mut axes = [0.0f32; GamepadAxis::Count as usize];
+
+		for index in 0..MAX_GAMEPADS {
+			let connected = unsafe {
+				input::poll_gamepad(index as u32, buttons.as_mut_ptr(), buttons.len(), axes.as_mut_ptr(), axes.len())
+			};
+
+			let prev = self.input_context.gamepads[index];
+
+			if connected != prev.is_connected() {
+				self.handle_input(InputEvent::GamepadConnection(index, connected));
+			}
+
+			if !connected { continue }
+
+			for (idx, &down) in buttons.iter().enumerate() {
+				let button = GamepadButton::from_index(idx).unwrap();
+				if (down != 0) != prev.button_state(button).is_down() {
+					self.handle_input(InputEvent::GamepadButton(index, button, down != 0));
+				}
+			}
+
+			for (idx, &value) in axes.iter().enumerate() {
+				let axis = GamepadAxis::from_index(idx).unwrap();
+				if value != prev.raw_axis(axis) {
+					self.handle_input(InputEvent::GamepadAxis(index, axis, value));
+				}
+			}
+		}
+	}
+
+	fn fail(&mut self, err: failure::Error) {
+		console_error!("Client failed: {}", err);
+
+		for cause in err.iter_causes() {
+			console_error!("    caused by: {}", cause);
+		}
+
+		// Give the cursor and keyboard back, there's nothing left to capture them for
+		self.input_context.enable_pointer_lock(false);
+		self.input_context.enable_text_input(false);
+		self.failure = Some(err);
+	}
+
+	fn draw_error_screen(&mut self) {
+		let failure = match self.failure.as_ref() {
+			Some(failure) => failure,
+			None => return,
+		};
+
+		let viewport = self.viewport;
+		let text = self.overlay_text.get_or_insert_with(TextRenderer::new);
+
+		unsafe {
+			// The client may have failed halfway through drawing, with any old state set
+			gl::bind_framebuffer(gl::FramebufferID(0));
+			gl::viewport(0, 0, viewport.x, viewport.y);
+
+			gl::disable(gl::Capability::StencilTest);
+			gl::disable(gl::Capability::ScissorTest);
+			gl::disable(gl::Capability::CullFace);
+			gl::color_mask(true, true, true, true);
+			gl::depth_mask(true);
+			gl::stencil_mask(0xFF);
+
+			let (r,g,b,_) = Color::rgb8(40, 14, 14).to_tuple();
+			gl::clear_color(r, g, b, 1.0);
+			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+		}
+
+		let text_scale = ERROR_TEXT_SCALE * self.pixel_ratio;
+		let margin = ERROR_MARGIN * self.pixel_ratio;
+		let columns = ((viewport.x as f32 - margin * 2.0) / (GLYPH_WIDTH as f32 * text_scale)).max(1.0) as usize;
+
+		let mut message = format!("{}", failure);
+		for cause in failure.iter_causes() {
+			message.push_str(&format!("\n\ncaused by: {}", cause));
+		}
+
+		text.clear();
+
+		let origin = Vec2::new(margin, margin);
+		let title_size = text.add_text(origin, text_scale * 1.5, Color::rgb8(255, 110, 80), "Something went wrong");
+
+		let body_pos = origin + Vec2::new(0.0, title_size.y + margin);
+		text.add_text(body_pos, text_scale, Color::grey(0.9), &wrap_text(&message, columns));
+
+		text.draw(viewport);
+	}
+
+	fn draw_profiler_overlay(&mut self) {
+		let mut table = format!("{:<16}{:>7}{:>7}{:>7}", "ms", "min", "avg", "max");
+
+		for s in profiler::stats() {
+			let name = format!("{:indent$}{}", "", s.name, indent = s.depth);
+			table.push_str(&format!("\n{:<16.16}{:>7.2}{:>7.2}{:>7.2}", name, s.min, s.avg, s.max));
+		}
+
+		let text_scale = PROFILER_TEXT_SCALE * self.pixel_ratio;
+		let size = measure_text(&table, text_scale);
+		let text = self.overlay_text.get_or_insert_with(TextRenderer::new);
+
+		let margin = Vec2::splat(PROFILER_MARGIN * self.pixel_ratio);
+
+		text.clear();
+		text.add_rect(Vec2::zero(), size + margin * 2.0, Color::grey(0.1));
+		text.add_text(margin, text_scale, Color::grey(0.9), &table);
+		text.draw(self.viewport);
+	}
+}
+
+
+fn reset_gl_state() {
+	unsafe {
+		gl::enable_attribute(0);
+
+		gl::enable(gl::Capability::DepthTest);
+		gl::enable(gl::Capability::Blend);
+		gl::blend_func(gl::BlendFactor::One, gl::BlendFactor::OneMinusSrcAlpha);
 	}
 }
 
+/// Follows `EngineClient::inner` down to the client that doesn't wrap another
+fn innermost(client: &dyn EngineClient) -> &dyn EngineClient {
+	match client.inner() {
+		Some(inner) => innermost(inner),
+		None => client,
+	}
+}
+
+fn innermost_mut(client: &mut dyn EngineClient) -> &mut dyn EngineClient {
+	if client.inner().is_none() { return client }
+	innermost_mut(client.inner_mut().unwrap())
+}
+
+fn create_gesture_tracker(client: &dyn EngineClient) -> GestureTracker {
+	let drag_threshold = client.drag_threshold().unwrap_or(0.0);
+	let hold_threshold = client.hold_threshold().unwrap_or(std::u32::MAX);
+
+	let mut tracker = GestureTracker::new(drag_threshold, hold_threshold);
+	tracker.set_double_tap_interval(client.double_tap_interval());
+	tracker.set_swipe_threshold(client.swipe_threshold());
+	tracker.set_long_press_drag(client.long_press_drag());
+	tracker.set_keyboard_gestures(client.keyboard_gestures());
+	tracker
+}
+
+#[derive(Copy, Clone)]
 pub struct UpdateContext<'eng> {
+	/// Number of fixed steps simulated so far
 	pub ticks: Ticks,
+	/// In framebuffer pixels
 	pub viewport: Vec2i,
+	/// Framebuffer pixels per CSS pixel. Scale anything sized in pixels by this to keep its size on high DPI screens
+	pub pixel_ratio: f32,
 	pub input: &'eng GestureTracker,
 	pub input_raw: &'eng InputContext,
+	/// Named actions, driven by the client's `bindings`
+	pub actions: &'eng Actions,
+
+	/// Real time in seconds since the previous animation frame
+	pub frame_time: f32,
+	/// Time in seconds covered by each call to `simulate`
+	pub fixed_dt: f32,
+	/// How far between the last simulated step and the next one this frame is, in [0, 1).
+	/// Always 0 in `simulate`
+	pub alpha: f32,
+}
+
+impl<'eng> UpdateContext<'eng> {
+	/// Simulation time in seconds, including the fraction of a step not yet simulated
+	pub fn time(&self) -> f32 {
+		(self.ticks as f32 + self.alpha) * self.fixed_dt
+	}
 }
 
 pub trait EngineClient {
 	fn uses_passive_input(&self) -> bool { true }
 	fn captures_input(&self) -> bool { false }
-	fn drag_threshold(&self) -> Option<u32> { Some(5) }
+	/// Distance thresholds are in millimetres, and converted to CSS pixels with `input::DP_PER_MM`
+	fn drag_threshold(&self) -> Option<f32> { Some(1.5) }
 	fn hold_threshold(&self) -> Option<Ticks> { None } // Holding disabled by default
+	fn double_tap_interval(&self) -> Option<Ticks> { None }
+	/// In millimetres per second
+	fn swipe_threshold(&self) -> Option<f32> { None }
+	/// Report drags that start after a hold through `GestureTracker::long_press_dragging`. Needs a `hold_threshold`
+	fn long_press_drag(&self) -> bool { false }
+
+	/// Multiplies the device pixel ratio to get the resolution rendered at.
+	/// Below 1 trades sharpness for fill rate. Read every frame
+	fn render_scale(&self) -> f32 { 1.0 }
+
+	/// Which keys are kept from the page. Defaults to keys that would scroll it - see `Consume::default_for_key`
+	fn consumes_key(&self, key: KeyCode) -> Consume { Consume::default_for_key(key) }
+	/// Whether pointer presses and releases are kept from the page, e.g. to stop text selection or focus changes
+	fn consumes_pointer(&self) -> Consume { Consume::Always }
+	/// Whether wheel and trackpad scrolling over the canvas should be kept from scrolling the page
+	fn consumes_wheel(&self) -> Consume { Consume::Never }
+
+	/// What drives `UpdateContext::actions`. Read every frame, so rebinding takes effect on the next one
+	fn bindings(&self) -> Option<&Bindings> { None }
+
+	/// On-screen controls to show while input is coming from touches. Read every frame
+	fn virtual_controls(&self) -> Option<&VirtualControlsLayout> { None }
+
+	/// Lets gestures be made with the keyboard alone: the arrows move a cursor, Space or Enter press at it,
+	/// and shift + arrows drag. Gets in the way of clients that use the arrows or Space themselves
+	fn keyboard_gestures(&self) -> bool { false }
+
+	fn init(&mut self) -> EngineResult<()> { Ok(()) }
+
+	fn on_focus_changed(&mut self, _focused: bool) {}
+	fn on_resize(&mut self, _old: Vec2i, _new: Vec2i) {}
+
+	/// Called at the start of the frame after a pad at `index` is connected or disconnected.
+	/// Browsers only report a pad once one of its buttons has been pressed
+	fn on_gamepad_connection_changed(&mut self, _index: usize, _connected: bool) {}
+
+	/// Called when the page is hidden. No updates will happen until `on_resume`
+	fn on_suspend(&mut self) {}
+	fn on_resume(&mut self) {}
+
+	/// Called after `on_suspend`, which is the last chance to persist anything before the page may be closed - see `storage`
+	fn save_state(&mut self) -> EngineResult<()> { Ok(()) }
+
+	/// Called after a successful `init`, and before `on_resume`
+	fn load_state(&mut self) -> EngineResult<()> { Ok(()) }
+
+	/// Called zero or more times per animation frame, once per `DT` of real time that has passed.
+	/// Input edges (presses, taps) are per animation frame, so may be seen by several steps or none - prefer handling them in `update`
+	fn simulate(&mut self, _: UpdateContext<'_>) -> EngineResult<()> { Ok(()) }
 
-	fn init(&mut self) {}
-	fn update(&mut self, _: UpdateContext<'_>) {}
+	/// Called once per animation frame, after any simulation steps. Render here.
+	/// Returning an error from any of `init`, `simulate` or `update` stops the client and shows the error in the canvas
+	fn update(&mut self, _: UpdateContext<'_>) -> EngineResult<()> { Ok(()) }
+
+	/// Clients that drive another client, like `StatefulClient`, return it here. The engine then calls every other hook
+	/// on the innermost client directly, so only `init`, `simulate` and `update` go through the wrapper
+	fn inner(&self) -> Option<&dyn EngineClient> { None }
+	fn inner_mut(&mut self) -> Option<&mut dyn EngineClient> { None }
 }
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::imports::gl::{Call, CallLog};
+	use std::rc::Rc;
+	use std::cell::{Cell, RefCell};
+
+	const VIEWPORT: Vec2i = Vec2i::new(320, 240);
+
+	#[derive(Default)]
+	struct Counts {
+		simulates: Cell<u32>,
+		updates: Cell<u32>,
+		update_ticks: RefCell<Vec<Ticks>>,
+		fail_on_update: Cell<bool>,
+	}
+
+	struct TestClient(Rc<Counts>);
+
+	impl EngineClient for TestClient {
+		fn simulate(&mut self, _: UpdateContext<'_>) -> EngineResult<()> {
+			self.0.simulates.set(self.0.simulates.get() + 1);
+			Ok(())
+		}
+
+		fn update(&mut self, ctx: UpdateContext<'_>) -> EngineResult<()> {
+			self.0.updates.set(self.0.updates.get() + 1);
+			self.0.update_ticks.borrow_mut().push(ctx.ticks);
+
+			if self.0.fail_on_update.get() {
+				failure::bail!("test failure");
+			}
+
+			unsafe {
+				gl::clear(gl::COLOR_BUFFER_BIT);
+				gl::draw_arrays(gl::DrawMode::Triangles, 0, 3);
+			}
+
+			Ok(())
+		}
+	}
+
+	fn test_engine() -> (Engine, Rc<Counts>, CallLog) {
+		let log = gl::install_recording_backend();
+		let counts = Rc::new(Counts::default());
+
+		let mut engine = Engine::new(Box::new(TestClient(counts.clone())));
+		engine.init();
+		engine.set_viewport(VIEWPORT, 1.0);
+
+		(engine, counts, log)
+	}
+
+	#[test]
+	fn first_frame_simulates_one_step() {
+		let (mut engine, counts, _) = test_engine();
+
+		engine.update(1000.0);
+
+		assert_eq!(counts.simulates.get(), 1);
+		assert_eq!(counts.updates.get(), 1);
+		assert_eq!(engine.time_ticks, 1);
+	}
+
+	#[test]
+	fn fixed_steps_follow_frame_time() {
+		let (mut engine, counts, _) = test_engine();
+
+		// 40ms frames are 2.4 steps each, so the remainder carries over into a third step every few frames
+		let mut expected_ticks = Vec::new();
+		for (frame, steps) in [1, 2, 2, 3].iter().enumerate() {
+			let before = counts.simulates.get();
+			engine.update(frame as f64 * 40.0);
+
+			assert_eq!(counts.simulates.get() - before, *steps, "frame {}", frame);
+			expected_ticks.push(counts.simulates.get());
+		}
+
+		assert_eq!(counts.updates.get(), 4);
+		assert_eq!(engine.time_ticks, 8);
+		assert_eq!(*counts.update_ticks.borrow(), expected_ticks);
+	}
+
+	#[test]
+	fn long_frames_are_clamped() {
+		let (mut engine, counts, _) = test_engine();
+
+		engine.update(0.0);
+		engine.update(10_000.0);
+
+		let max_steps = 1 + (MAX_FRAME_TIME / DT as f64).ceil() as u32;
+		assert!(counts.simulates.get() <= max_steps, "{} steps after a stall", counts.simulates.get());
+	}
+
+	#[test]
+	fn frames_are_drawn_through_the_backend() {
+		let (mut engine, _, log) = test_engine();
+
+		let setup = log.calls().clone();
+		assert!(setup.iter().any(|c| matches!(c, Call::Enable(gl::Capability::Blend))));
+		log.clear();
+
+		for frame in 0..3 {
+			engine.update(frame as f64 * 1000.0 / 60.0);
+		}
+
+		assert_eq!(log.count(|c| matches!(c, Call::Viewport(0, 0, 320, 240))), 3);
+		assert_eq!(log.count(|c| matches!(c, Call::Clear(_))), 3);
+		assert_eq!(log.draw_calls(), 3);
+	}
+
+	#[test]
+	fn suspended_engines_skip_frames() {
+		let (mut engine, counts, log) = test_engine();
+
+		engine.set_visible(false);
+		log.clear();
+
+		engine.update(0.0);
+		engine.update(100.0);
+
+		assert!(engine.is_suspended());
+		assert_eq!(counts.updates.get(), 0);
+		assert_eq!(log.len(), 0);
+	}
+
+	#[test]
+	fn hooks_skip_wrapping_clients() {
+		struct ScaledClient;
+
+		impl EngineClient for ScaledClient {
+			fn render_scale(&self) -> f32 { 0.5 }
+		}
+
+		gl::install_recording_backend();
+
+		let client = crate::state::StatefulClient::new(ScaledClient, crate::state::Transition::none());
+		let mut engine = Engine::new(Box::new(client));
+		engine.init();
+		engine.set_viewport(VIEWPORT, 2.0);
+
+		assert_eq!(engine.render_scale(), 0.5);
+		assert_eq!(engine.pixel_ratio, 1.0);
+	}
+
+	#[test]
+	fn failed_clients_get_no_more_calls() {
+		let (mut engine, counts, log) = test_engine();
+
+		counts.fail_on_update.set(true);
+		engine.update(0.0);
+
+		assert!(engine.has_failed());
+		assert_eq!(counts.updates.get(), 1);
+		assert_eq!(log.draw_calls(), 0);
+
+		// The error screen is drawn instead
+		log.clear();
+		engine.update(1000.0 / 60.0);
+
+		assert_eq!(counts.simulates.get(), 1);
+		assert_eq!(counts.updates.get(), 1);
+		assert!(log.draw_calls() > 0);
+	}
+}
@@ -2,7 +2,7 @@
 
 use common::*;
 
-use crate::get_engine_mut;
+use crate::{get_engine_mut, EngineID};
 use crate::string_arena::JSString;
 use crate::input::*;
 
@@ -12,13 +12,23 @@ pub fn force_linkage() {}
 // core
 
 #[no_mangle]
-pub fn internal_update(t: f64) {
-	get_engine_mut().update(t);
+pub fn internal_create_engine() -> EngineID {
+	crate::create_engine()
 }
 
 #[no_mangle]
-pub fn internal_update_viewport(w: i32, h: i32) {
-	get_engine_mut().viewport = Vec2i::new(w,h);
+pub fn internal_update(id: EngineID, t: f64) {
+	get_engine_mut(id).update(t);
+}
+
+#[no_mangle]
+pub fn internal_update_viewport(id: EngineID, w: i32, h: i32, device_pixel_ratio: f32) {
+	get_engine_mut(id).set_viewport(Vec2i::new(w,h), device_pixel_ratio);
+}
+
+#[no_mangle]
+pub fn internal_get_render_scale(id: EngineID) -> f32 {
+	get_engine_mut(id).render_scale()
 }
 
 
@@ -33,82 +43,249 @@ pub fn internal_allocate_i32_vec(n: usize) -> *mut i32 {
 	ptr
 }
 
+#[no_mangle]
+pub fn internal_allocate_u8_vec(n: usize) -> *mut u8 {
+	let mut s = vec![0u8; n].into_boxed_slice();
+	let ptr = s.as_mut_ptr();
+	std::mem::forget(s);
+	ptr
+}
+
+// Holds the last buffer handed out to js, until the next one replaces it
+static mut EXPORT_BUFFER: Vec<u8> = Vec::new();
+
+#[no_mangle]
+pub fn internal_export_buffer_ptr() -> *const u8 {
+	unsafe { EXPORT_BUFFER.as_ptr() }
+}
+
 // input
 
 #[no_mangle]
-pub fn internal_handle_key_down(key_code: JSString) -> bool {
-	let code = KeyCode::from_js_code(key_code);
+pub fn internal_handle_key_down(id: EngineID, timestamp: f64, key_code: JSString) -> bool {
+	let code = KeyCode::from_js_code(&key_code);
 	if code.is_none() { return false }
 
 	let code = code.unwrap();
-	get_engine_mut().input_context.register_keydown(code);
-	code.always_consume()
+	let mut engine = get_engine_mut(id);
+	engine.handle_input_at(timestamp, InputEvent::KeyDown(code));
+	engine.hooks().consumes_key(code).should_consume(&engine.input_context)
 }
 
 #[no_mangle]
-pub fn internal_handle_key_up(key_code: JSString) -> bool {
-	let code = KeyCode::from_js_code(key_code);
+pub fn internal_handle_key_up(id: EngineID, timestamp: f64, key_code: JSString) -> bool {
+	let code = KeyCode::from_js_code(&key_code);
 	if code.is_none() { return false }
 
 	let code = code.unwrap();
-	get_engine_mut().input_context.register_keyup(code);
-	code.always_consume()
+	let mut engine = get_engine_mut(id);
+	engine.handle_input_at(timestamp, InputEvent::KeyUp(code));
+	engine.hooks().consumes_key(code).should_consume(&engine.input_context)
 }
 
 #[no_mangle]
-pub fn internal_handle_mouse_down(mb: MouseButton, x: i32, y: i32) -> bool {
-	get_engine_mut().input_context.register_mousedown(mb, x, y);
-	true
+#[allow(clippy::too_many_arguments)]
+pub fn internal_handle_pointer_down(id: EngineID, timestamp: f64, pointer_id: i32, pointer_type: u32, is_primary: bool, button: i32,
+	x: i32, y: i32, pressure: f32, tilt_x: f32, tilt_y: f32, width: f32, height: f32) -> bool
+{
+	let sample = pointer_sample(pointer_id, pointer_type, is_primary, x, y, pressure, tilt_x, tilt_y, width, height);
+	let button = match pointer_button(button) {
+		Some(button) => button,
+		None => return false,
+	};
+
+	let mut engine = get_engine_mut(id);
+	engine.handle_input_at(timestamp, InputEvent::PointerDown(sample, button));
+	engine.hooks().consumes_pointer().should_consume(&engine.input_context)
 }
 
 #[no_mangle]
-pub fn internal_handle_mouse_up(mb: MouseButton, x: i32, y: i32) -> bool {
-	get_engine_mut().input_context.register_mouseup(mb, x, y);
-	true
+#[allow(clippy::too_many_arguments)]
+pub fn internal_handle_pointer_up(id: EngineID, timestamp: f64, pointer_id: i32, pointer_type: u32, is_primary: bool, button: i32,
+	x: i32, y: i32, pressure: f32, tilt_x: f32, tilt_y: f32, width: f32, height: f32) -> bool
+{
+	let sample = pointer_sample(pointer_id, pointer_type, is_primary, x, y, pressure, tilt_x, tilt_y, width, height);
+	let button = match pointer_button(button) {
+		Some(button) => button,
+		None => return false,
+	};
+
+	let mut engine = get_engine_mut(id);
+	engine.handle_input_at(timestamp, InputEvent::PointerUp(sample, button));
+	engine.hooks().consumes_pointer().should_consume(&engine.input_context)
 }
 
 #[no_mangle]
-pub fn internal_handle_mouse_move(x: i32, y: i32, dx: i32, dy: i32) -> bool {
-	get_engine_mut().input_context.mouse_pos = Vec2i::new(x, y);
-	get_engine_mut().input_context.mouse_delta = Vec2i::new(dx, dy);
+#[allow(clippy::too_many_arguments)]
+pub fn internal_handle_pointer_move(id: EngineID, timestamp: f64, pointer_id: i32, pointer_type: u32, is_primary: bool,
+	x: i32, y: i32, dx: i32, dy: i32, pressure: f32, tilt_x: f32, tilt_y: f32, width: f32, height: f32) -> bool
+{
+	let sample = pointer_sample(pointer_id, pointer_type, is_primary, x, y, pressure, tilt_x, tilt_y, width, height);
+
+	get_engine_mut(id).handle_input_at(timestamp, InputEvent::PointerMove {
+		sample,
+		delta: Vec2i::new(dx, dy),
+	});
+
 	false
 }
 
 #[no_mangle]
-pub fn internal_handle_touch_down(id: i32, x: i32, y: i32) -> bool {
-	get_engine_mut().input_context.register_touchdown(id, x, y);
-	true
+pub fn internal_handle_pointer_cancel(id: EngineID, timestamp: f64, pointer_id: i32) {
+	get_engine_mut(id).handle_input_at(timestamp, InputEvent::PointerCancel(pointer_id));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pointer_sample(pointer_id: i32, pointer_type: u32, is_primary: bool, x: i32, y: i32,
+	pressure: f32, tilt_x: f32, tilt_y: f32, width: f32, height: f32) -> PointerSample
+{
+	PointerSample {
+		id: pointer_id,
+		pointer_type: PointerType::from_index(pointer_type as usize),
+		is_primary,
+		pos: Vec2i::new(x, y),
+		pressure,
+		tilt: Vec2::new(tilt_x, tilt_y),
+		contact_size: Vec2::new(width, height),
+	}
+}
+
+// Pen erasers are button 5, but are a tip as far as anything here is concerned
+fn pointer_button(button: i32) -> Option<MouseButton> {
+	match button {
+		5 => Some(MouseButton::Left),
+		b if b >= 0 => MouseButton::from_index(b as usize),
+		_ => None,
+	}
 }
 
 #[no_mangle]
-pub fn internal_handle_touch_up(id: i32, x: i32, y: i32) -> bool {
-	get_engine_mut().input_context.register_touchup(id, x, y);
-	true
+pub fn internal_handle_wheel(id: EngineID, timestamp: f64, dx: f32, dy: f32, delta_mode: u32, page_height: f32) -> bool {
+	let mut engine = get_engine_mut(id);
+	let scale = WheelDeltaMode::from_js(delta_mode).pixels_per_unit(page_height);
+
+	engine.handle_input_at(timestamp, InputEvent::Wheel(Vec2::new(dx, dy) * scale));
+	engine.hooks().consumes_wheel().should_consume(&engine.input_context)
 }
 
 #[no_mangle]
-pub fn internal_handle_touch_move(id: i32, x: i32, y: i32) -> bool {
-	get_engine_mut().input_context.register_touchmove(id, x, y);
-	false
+pub fn internal_handle_text_commit(id: EngineID, text: JSString) {
+	get_engine_mut(id).handle_input(InputEvent::Text(TextInput::Commit(text.as_str().to_owned())));
 }
 
 #[no_mangle]
-pub fn internal_handle_focus_gain() {
-	get_engine_mut().input_context.reset_inputs();
+pub fn internal_handle_text_composition(id: EngineID, text: JSString) {
+	get_engine_mut(id).handle_input(InputEvent::Text(TextInput::Composition(text.as_str().to_owned())));
 }
 
 #[no_mangle]
-pub fn internal_handle_focus_loss() {
-	get_engine_mut().input_context.reset_inputs();
+pub fn internal_handle_text_delete(id: EngineID, forward: bool) {
+	let text = if forward { TextInput::Delete } else { TextInput::Backspace };
+	get_engine_mut(id).handle_input(InputEvent::Text(text));
 }
 
 #[no_mangle]
-pub fn internal_notify_pointer_lock_change(enabled: bool) {
-	get_engine_mut().input_context.register_pointer_lock_change(enabled);
+pub fn internal_handle_focus_gain(id: EngineID) {
+	get_engine_mut(id).set_focused(true);
 }
 
+#[no_mangle]
+pub fn internal_handle_focus_loss(id: EngineID) {
+	get_engine_mut(id).set_focused(false);
+}
+
+#[no_mangle]
+pub fn internal_handle_canvas_focus_change(id: EngineID, focused: bool) {
+	get_engine_mut(id).input_context.canvas_focused = focused;
+}
+
+#[no_mangle]
+pub fn internal_handle_pointer_leave(id: EngineID) {
+	get_engine_mut(id).handle_input(InputEvent::Reset);
+}
+
+#[no_mangle]
+pub fn internal_handle_visibility_change(id: EngineID, visible: bool) {
+	get_engine_mut(id).set_visible(visible);
+}
+
+#[no_mangle]
+pub fn internal_notify_pointer_lock_change(id: EngineID, enabled: bool) {
+	get_engine_mut(id).handle_input(InputEvent::PointerLockChange(enabled));
+}
+
+
+#[no_mangle]
+pub fn engine_enable_pointer_lock(id: EngineID, e: bool) {
+	get_engine_mut(id).input_context.enable_pointer_lock(e);
+}
+
+#[no_mangle]
+pub fn engine_restart(id: EngineID) {
+	crate::restart_engine(id);
+}
+
+#[no_mangle]
+pub fn engine_has_failed(id: EngineID) -> bool {
+	crate::get_engine(id).has_failed()
+}
+
+#[no_mangle]
+pub fn engine_show_profiler(id: EngineID, show: bool) {
+	get_engine_mut(id).set_profiler_overlay(show);
+}
+
+// Timings are shared between all engines, so `id` is unused
+#[no_mangle]
+pub fn engine_dump_profile(_: EngineID) {
+	crate::profiler::dump();
+}
+
+#[no_mangle]
+pub fn engine_start_recording(id: EngineID, restart: bool) {
+	if restart {
+		crate::restart_engine(id);
+	}
+
+	get_engine_mut(id).start_recording();
+}
+
+/// Returns the size of the encoded log, which can then be read from `internal_export_buffer_ptr`
+#[no_mangle]
+pub fn engine_stop_recording(id: EngineID) -> usize {
+	let log = get_engine_mut(id).stop_recording();
+
+	unsafe {
+		EXPORT_BUFFER = log.map(|l| l.to_bytes()).unwrap_or_default();
+		EXPORT_BUFFER.len()
+	}
+}
+
+/// Takes ownership of a buffer allocated with `internal_allocate_u8_vec`
+#[no_mangle]
+pub fn engine_start_replay(id: EngineID, ptr: *mut u8, len: usize, restart: bool) -> bool {
+	let bytes = unsafe { Box::from_raw(std::slice::from_raw_parts_mut(ptr, len)) };
+
+	let log = match InputLog::from_bytes(&bytes) {
+		Ok(log) => log,
+		Err(err) => {
+			console_error!("Couldn't start replay: {}", err);
+			return false;
+		}
+	};
+
+	if restart {
+		crate::restart_engine(id);
+	}
+
+	get_engine_mut(id).start_replay(log);
+	true
+}
 
+/// Takes ownership of a buffer allocated with `internal_allocate_u8_vec`.
+/// Assets are shared between all engines, so `id` is unused
 #[no_mangle]
-pub fn engine_enable_pointer_lock(e: bool) {
-	get_engine_mut().input_context.enable_pointer_lock(e);
+pub fn engine_reload_asset(_: EngineID, name: JSString, ptr: *mut u8, len: usize) -> bool {
+	let bytes = unsafe { Box::from_raw(std::slice::from_raw_parts_mut(ptr, len)) };
+	crate::assets::reload(&name, bytes.into_vec())
 }
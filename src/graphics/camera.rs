@@ -1,5 +1,6 @@
 use common::math::*;
 use std::cell::Cell;
+use crate::graphics::ray::Ray;
 
 
 #[derive(Copy, Clone, Debug)]
@@ -144,12 +145,16 @@ impl Camera {
 
 	fn mark_projection_dirty(&mut self) {
 		self.projection_matrix.mark_dirty();
+		self.inv_projection_matrix.mark_dirty();
 		self.proj_view_matrix.mark_dirty();
+		self.inv_proj_view_matrix.mark_dirty();
 	}
 
 	fn mark_view_dirty(&mut self) {
 		self.view_matrix.mark_dirty();
+		self.inv_view_matrix.mark_dirty();
 		self.proj_view_matrix.mark_dirty();
+		self.inv_proj_view_matrix.mark_dirty();
 	}
 
 
@@ -190,5 +195,13 @@ impl Camera {
 		let v = self.inverse_projection_view() * screen.extend(1.0);
 		v.to_vec3() / v.w
 	}
+
+	/// The ray through `ndc`, which is in [-1, 1] and y up like `GestureTracker::position`.
+	/// Starts on the near plane rather than at the camera, since orthographic rays don't share an origin
+	pub fn screen_ray(&self, ndc: Vec2) -> Ray {
+		let near = self.screen_to_world(ndc.extend(-1.0));
+		let far = self.screen_to_world(ndc.extend(1.0));
+		Ray::new(near, far - near)
+	}
 }
 
@@ -1,5 +1,7 @@
+use common::math::Vec3;
 use crate::imports::gl;
 use crate::graphics::vertex::{Vertex, Descriptor};
+use crate::graphics::ray::{Ray, TriangleHit};
 
 pub struct DynamicMesh<T: Vertex> {
 	vertices: Vec<T>,
@@ -59,6 +61,20 @@ impl<T: Vertex> DynamicMesh<T> {
 		}
 	}
 
+	/// The nearest triangle hit by `ray`, treating the mesh as a triangle list. `position` gets a vertex's position,
+	/// which should be in the same space as the ray
+	pub fn intersect_ray<F>(&self, ray: &Ray, position: F) -> Option<TriangleHit> where F: Fn(&T) -> Vec3 {
+		let vertex = |idx: u16| position(&self.vertices[idx as usize]);
+
+		self.indices.chunks_exact(3)
+			.enumerate()
+			.filter_map(|(triangle, tri)| {
+				ray.intersect_triangle(vertex(tri[0]), vertex(tri[1]), vertex(tri[2]))
+					.map(|hit| TriangleHit { triangle, ..hit })
+			})
+			.min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal))
+	}
+
 	fn vert_start(&self) -> Option<u16> {
 		let start = self.vertices.len();
 		if start > 0xffff {
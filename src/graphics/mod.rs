@@ -4,12 +4,18 @@ pub mod camera;
 pub mod texture;
 pub mod shader;
 pub mod framebuffer;
+pub mod text;
+pub mod ray;
+pub mod picking;
 
 pub use self::shader::Shader;
 pub use self::texture::{Texture, TextureBuilder};
 pub use self::mesh::{BasicDynamicMesh, DynamicMesh, MeshBuilding};
 pub use self::camera::Camera;
 pub use self::framebuffer::Framebuffer;
+pub use self::text::TextRenderer;
+pub use self::ray::{Ray, Aabb, TriangleHit};
+pub use self::picking::{IdPicker, IdPass, PickQuery, PickStatus};
 
 pub use crate::imports::gl;
 
@@ -0,0 +1,237 @@
+use crate::prelude::*;
+use crate::imports::gl;
+use crate::graphics::framebuffer::FramebufferBindGuard;
+
+/// Ids are packed into the rgb of a pixel, with 0 left for the background
+pub const MAX_PICK_ID: u32 = 0xFF_FFFF;
+
+
+/// The colour `IdVertex` carries for `id`. Ids above `MAX_PICK_ID` lose their top bits
+pub fn encode_id(id: u32) -> Vec3 {
+	debug_assert!(id <= MAX_PICK_ID, "Pick id {} is too large to encode", id);
+
+	let channel = |shift: u32| ((id >> shift) & 0xFF) as f32 / 255.0;
+	Vec3::new(channel(16), channel(8), channel(0))
+}
+
+fn decode_id(pixel: [u8; 4]) -> Option<u32> {
+	let id = (pixel[0] as u32) << 16 | (pixel[1] as u32) << 8 | pixel[2] as u32;
+	if id == 0 { None } else { Some(id) }
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PickQuery(u32);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PickStatus {
+	/// Waiting for a pass to be rendered, or for that pass to be read back
+	Pending,
+	/// The id drawn under the query's position, or None if nothing was
+	Picked(Option<u32>),
+	/// Picked over a frame ago, or never requested from this picker
+	Expired,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum QueryState {
+	Requested,
+	Rendered,
+	Resolved(Option<u32>),
+}
+
+#[derive(Copy, Clone, Debug)]
+struct PendingQuery {
+	query: PickQuery,
+	position: Vec2,
+	state: QueryState,
+}
+
+
+/// GPU picking, for scenes with too much in them to test rays against on the cpu.
+/// Clients draw `IdVertex` meshes into an offscreen id pass, and ask what was drawn under a position.
+/// Reading back straight after drawing would stall on the gpu, so each query is read back at the start of the pass
+/// the frame after it was rendered:
+///
+/// ```ignore
+/// if ctx.input.tap() { self.query = Some(self.picker.request(ctx.input.position())) }
+///
+/// if self.picker.has_pending() {
+/// 	let pass = self.picker.begin_pass(ctx.viewport);
+/// 	pass.set_proj_view(proj_view);
+/// 	self.id_mesh.draw(gl::DrawMode::Triangles);
+/// }
+///
+/// if let Some(PickStatus::Picked(id)) = self.query.map(|q| self.picker.status(q)) { ... }
+/// ```
+pub struct IdPicker {
+	framebuffer: Framebuffer,
+	shader: Shader,
+
+	queries: Vec<PendingQuery>,
+	next_query: u32,
+}
+
+impl IdPicker {
+	pub fn new() -> Self {
+		let shader = Shader::from_asset(
+			embed_asset!("../shaders/id.glsl"),
+			&["position", "id"]
+		);
+
+		IdPicker {
+			framebuffer: Framebuffer::new(),
+			shader,
+
+			queries: Vec::new(),
+			next_query: 0,
+		}
+	}
+
+	/// `position` is in the same space as `GestureTracker::position` - [-1, 1], y up.
+	/// Picks from the next pass begun, so request before drawing the pass for the frame
+	pub fn request(&mut self, position: Vec2) -> PickQuery {
+		let query = PickQuery(self.next_query);
+		self.next_query = self.next_query.wrapping_add(1);
+
+		self.queries.push(PendingQuery { query, position, state: QueryState::Requested });
+		query
+	}
+
+	pub fn status(&self, query: PickQuery) -> PickStatus {
+		match self.queries.iter().find(|q| q.query == query).map(|q| q.state) {
+			Some(QueryState::Resolved(id)) => PickStatus::Picked(id),
+			Some(_) => PickStatus::Pending,
+			None => PickStatus::Expired,
+		}
+	}
+
+	/// Whether any query is still waiting on a pass. There's no need to draw the pass while this is false
+	pub fn has_pending(&self) -> bool {
+		self.queries.iter().any(|q| !matches!(q.state, QueryState::Resolved(_)))
+	}
+
+	/// Reads back the queries rendered by the previous pass, then binds and clears the id framebuffer at `viewport` size.
+	/// Everything drawn before the pass is dropped should be `IdVertex`es, and is drawn with the id shader.
+	/// Leaves the clear colour black
+	pub fn begin_pass(&mut self, viewport: Vec2i) -> IdPass<'_> {
+		self.queries.retain(|q| !matches!(q.state, QueryState::Resolved(_)));
+		self.read_back();
+
+		self.framebuffer.resize(viewport);
+
+		for query in self.queries.iter_mut() {
+			if matches!(query.state, QueryState::Requested) {
+				query.state = QueryState::Rendered;
+			}
+		}
+
+		let binding = self.framebuffer.bind();
+		let (blend, dither) = unsafe {
+			(gl::is_enabled(gl::Capability::Blend), gl::is_enabled(gl::Capability::Dither))
+		};
+
+		unsafe {
+			gl::clear_color(0.0, 0.0, 0.0, 0.0);
+			gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+			// Blended or dithered ids are different ids
+			gl::disable(gl::Capability::Blend);
+			gl::disable(gl::Capability::Dither);
+		}
+
+		self.shader.bind();
+		self.shader.set_uniform("proj_view", Mat4::ident());
+		self.shader.set_uniform("point_size", 1.0f32);
+
+		IdPass {
+			shader: &self.shader,
+			_binding: binding,
+			blend, dither,
+		}
+	}
+
+	fn read_back(&mut self) {
+		if !self.queries.iter().any(|q| matches!(q.state, QueryState::Rendered)) { return }
+
+		let size = self.framebuffer.size();
+		let _binding = self.framebuffer.bind();
+
+		for query in self.queries.iter_mut() {
+			if !matches!(query.state, QueryState::Rendered) { continue }
+
+			// Both are y up, so no flip needed
+			let pixel = (query.position * 0.5 + 0.5) * size.to_vec2();
+			let (x, y) = (pixel.x.floor() as i32, pixel.y.floor() as i32);
+
+			let id = if x >= 0 && y >= 0 && x < size.x && y < size.y {
+				let mut data = [0u8; 4];
+				unsafe { gl::read_pixels(x, y, 1, 1, data.as_mut_ptr(), data.len()) }
+				decode_id(data)
+			} else {
+				None
+			};
+
+			query.state = QueryState::Resolved(id);
+		}
+	}
+}
+
+
+/// The id framebuffer stays bound, and blending and dithering off, until this is dropped.
+/// Both are then left the way `begin_pass` found them
+pub struct IdPass<'p> {
+	shader: &'p Shader,
+	_binding: FramebufferBindGuard<'p>,
+	blend: bool,
+	dither: bool,
+}
+
+impl<'p> IdPass<'p> {
+	pub fn set_proj_view(&self, proj_view: Mat4) {
+		self.shader.set_uniform("proj_view", proj_view);
+	}
+
+	/// In framebuffer pixels, for drawing points
+	pub fn set_point_size(&self, size: f32) {
+		self.shader.set_uniform("point_size", size);
+	}
+}
+
+impl<'p> Drop for IdPass<'p> {
+	fn drop(&mut self) {
+		let restore = |cap, enabled| unsafe {
+			if enabled { gl::enable(cap) } else { gl::disable(cap) }
+		};
+
+		restore(gl::Capability::Blend, self.blend);
+		restore(gl::Capability::Dither, self.dither);
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pass_leaves(blend: bool) -> (bool, bool) {
+		gl::install_recording_backend();
+
+		unsafe {
+			if blend { gl::enable(gl::Capability::Blend) }
+			gl::disable(gl::Capability::Dither);
+		}
+
+		let mut picker = IdPicker::new();
+		picker.request(Vec2::zero());
+		drop(picker.begin_pass(Vec2i::new(4, 4)));
+
+		unsafe { (gl::is_enabled(gl::Capability::Blend), gl::is_enabled(gl::Capability::Dither)) }
+	}
+
+	#[test]
+	fn pass_restores_previous_state() {
+		assert_eq!(pass_leaves(true), (true, false));
+		assert_eq!(pass_leaves(false), (false, false));
+	}
+}
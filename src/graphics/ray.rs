@@ -0,0 +1,133 @@
+use common::math::*;
+
+// Anything closer to parallel than this is treated as missing
+const PARALLEL_EPSILON: f32 = 1e-6;
+
+
+/// A half line in world space. `direction` is always unit length, so hit distances are in world units
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+	pub origin: Vec3,
+	pub direction: Vec3,
+}
+
+/// Axis aligned bounding box
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+	pub min: Vec3,
+	pub max: Vec3,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TriangleHit {
+	pub distance: f32,
+	/// Which triangle of the mesh was hit, counting in threes through its indices
+	pub triangle: usize,
+	/// Weights of the triangle's three vertices at the hit point, summing to 1
+	pub barycentric: Vec3,
+}
+
+
+impl Ray {
+	pub fn new(origin: Vec3, direction: Vec3) -> Self {
+		Ray { origin, direction: direction.normalize() }
+	}
+
+	pub fn at(&self, distance: f32) -> Vec3 {
+		self.origin + self.direction * distance
+	}
+
+	/// Distance to where the ray enters `aabb`. Starting inside counts as a hit at 0
+	pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+		let slabs = [
+			(self.origin.x, self.direction.x, aabb.min.x, aabb.max.x),
+			(self.origin.y, self.direction.y, aabb.min.y, aabb.max.y),
+			(self.origin.z, self.direction.z, aabb.min.z, aabb.max.z),
+		];
+
+		let mut enter = 0.0f32;
+		let mut exit = std::f32::INFINITY;
+
+		for &(origin, direction, min, max) in slabs.iter() {
+			if direction.abs() < PARALLEL_EPSILON {
+				if origin < min || origin > max { return None }
+				continue;
+			}
+
+			let (t0, t1) = ((min - origin) / direction, (max - origin) / direction);
+			enter = enter.max(t0.min(t1));
+			exit = exit.min(t0.max(t1));
+
+			if enter > exit { return None }
+		}
+
+		Some(enter)
+	}
+
+	/// Distance to where the ray enters the sphere. Starting inside counts as a hit at 0
+	pub fn intersect_sphere(&self, center: Vec3, radius: f32) -> Option<f32> {
+		let to_origin = self.origin - center;
+		let b = to_origin.dot(self.direction);
+		let c = to_origin.dot(to_origin) - radius * radius;
+
+		let discriminant = b * b - c;
+		if discriminant < 0.0 { return None }
+
+		let root = discriminant.sqrt();
+		if -b + root < 0.0 { return None }
+
+		Some((-b - root).max(0.0))
+	}
+
+	/// Either side of the plane through `point` facing along `normal`
+	pub fn intersect_plane(&self, point: Vec3, normal: Vec3) -> Option<f32> {
+		let denom = normal.dot(self.direction);
+		if denom.abs() < PARALLEL_EPSILON { return None }
+
+		let distance = (point - self.origin).dot(normal) / denom;
+		if distance < 0.0 { return None }
+
+		Some(distance)
+	}
+
+	/// Möller-Trumbore. Triangles are hit from either side. `triangle` is always 0 in the result
+	pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<TriangleHit> {
+		let edge_ab = b - a;
+		let edge_ac = c - a;
+
+		let p = self.direction.cross(edge_ac);
+		let det = edge_ab.dot(p);
+		if det.abs() < PARALLEL_EPSILON { return None }
+
+		let inv_det = 1.0 / det;
+		let to_origin = self.origin - a;
+
+		let u = to_origin.dot(p) * inv_det;
+		if u < 0.0 || u > 1.0 { return None }
+
+		let q = to_origin.cross(edge_ab);
+		let v = self.direction.dot(q) * inv_det;
+		if v < 0.0 || u + v > 1.0 { return None }
+
+		let distance = edge_ac.dot(q) * inv_det;
+		if distance < 0.0 { return None }
+
+		Some(TriangleHit {
+			distance,
+			triangle: 0,
+			barycentric: Vec3::new(1.0 - u - v, u, v),
+		})
+	}
+}
+
+
+impl Aabb {
+	pub fn new(min: Vec3, max: Vec3) -> Self { Aabb { min, max } }
+
+	pub fn from_center_extents(center: Vec3, extents: Vec3) -> Self {
+		Aabb { min: center - extents, max: center + extents }
+	}
+
+	pub fn center(&self) -> Vec3 { (self.min + self.max) / 2.0 }
+	pub fn size(&self) -> Vec3 { self.max - self.min }
+}
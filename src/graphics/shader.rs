@@ -1,36 +1,58 @@
+use std::cell::Cell;
+
 use crate::imports::gl;
+use crate::assets::Asset;
 use common::*;
 
+// Combined shader sources are the vertex shader, then this, then the fragment shader
+const STAGE_SEPARATOR: &str = "/* @@@ */";
+
 pub struct Shader {
-	gl_handle: gl::ProgramID,
-	num_attribs: u32
+	gl_handle: Cell<gl::ProgramID>,
+	num_attribs: u32,
+
+	#[cfg(debug)]
+	source: Option<ShaderSource>,
+}
+
+// What a shader was built from, so it can be rebuilt when the asset is reloaded
+#[cfg(debug)]
+struct ShaderSource {
+	asset: Asset,
+	attribs: Vec<String>,
+	generation: Cell<u32>,
 }
 
 impl Shader {
 	pub fn from_combined(src: &str, attribs: &[&str]) -> Shader {
-		unsafe {
-			let mut src = src.split("/* @@@ */");
-			let (vsrc, fsrc) = (src.next().unwrap(), src.next().unwrap());
-
-			let gl_handle = gl::create_shader_program();
-
-			let vsh = gl::create_shader(gl::ShaderType::Vertex, vsrc.into());
-			let fsh = gl::create_shader(gl::ShaderType::Fragment, fsrc.into());
+		Shader {
+			gl_handle: Cell::new(link_combined(src, attribs)),
+			num_attribs: attribs.len() as u32,
 
-			for (i, &a) in attribs.iter().enumerate() {
-				gl::bind_attrib_location(gl_handle, a.into(), i as u32);
-			}
-
-			gl::link_program(gl_handle, vsh, fsh);
+			#[cfg(debug)]
+			source: None,
+		}
+	}
 
-			Shader {
-				gl_handle,
-				num_attribs: attribs.len() as u32,
-			}
-		}	
+	/// In debug builds, the shader is relinked the next time it's bound after `asset` is reloaded
+	pub fn from_asset(asset: Asset, attribs: &[&str]) -> Shader {
+		Shader {
+			gl_handle: Cell::new(link_combined(&asset.text(), attribs)),
+			num_attribs: attribs.len() as u32,
+
+			#[cfg(debug)]
+			source: Some(ShaderSource {
+				asset,
+				attribs: attribs.iter().map(|&a| a.to_owned()).collect(),
+				generation: Cell::new(asset.generation()),
+			}),
+		}
 	}
 
 	pub fn bind(&self) {
+		#[cfg(debug)]
+		self.relink_if_reloaded();
+
 		unsafe {
 			for i in 0..self.num_attribs {
 				gl::enable_attribute(i);
@@ -40,12 +62,55 @@ impl Shader {
 				gl::disable_attribute(i);
 			}
 
-			gl::use_program(self.gl_handle);
+			gl::use_program(self.gl_handle.get());
 		}
 	}
 
 	pub fn set_uniform<U: UniformType>(&self, name: &str, data: U) {
-		data.apply(self.gl_handle, name);
+		data.apply(self.gl_handle.get(), name);
+	}
+
+	// The old program is left alive, since there's no way to delete one - fine for the odd reload in a debug build
+	#[cfg(debug)]
+	fn relink_if_reloaded(&self) {
+		let source = match self.source.as_ref() {
+			Some(source) => source,
+			None => return,
+		};
+
+		let generation = source.asset.generation();
+		if generation == source.generation.get() { return }
+
+		source.generation.set(generation);
+
+		let src = source.asset.text();
+		if !src.contains(STAGE_SEPARATOR) {
+			console_error!("Reloaded shader is missing its '{}' separator - keeping the old one", STAGE_SEPARATOR);
+			return;
+		}
+
+		let attribs: Vec<&str> = source.attribs.iter().map(String::as_str).collect();
+		self.gl_handle.set(link_combined(&src, &attribs));
+	}
+}
+
+
+fn link_combined(src: &str, attribs: &[&str]) -> gl::ProgramID {
+	unsafe {
+		let mut src = src.split(STAGE_SEPARATOR);
+		let (vsrc, fsrc) = (src.next().unwrap(), src.next().unwrap());
+
+		let gl_handle = gl::create_shader_program();
+
+		let vsh = gl::create_shader(gl::ShaderType::Vertex, vsrc.into());
+		let fsh = gl::create_shader(gl::ShaderType::Fragment, fsrc.into());
+
+		for (i, &a) in attribs.iter().enumerate() {
+			gl::bind_attrib_location(gl_handle, a.into(), i as u32);
+		}
+
+		gl::link_program(gl_handle, vsh, fsh);
+		gl_handle
 	}
 }
 
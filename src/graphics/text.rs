@@ -0,0 +1,237 @@
+use common::*;
+use crate::imports::gl;
+use crate::graphics::{Shader, Texture, TextureBuilder, BasicDynamicMesh, MeshBuilding};
+use crate::graphics::vertex::{Vertex, Descriptor};
+
+// Glyphs are 5x7, padded out to 6x8 cells so that text doesn't need any extra spacing
+pub const GLYPH_WIDTH: i32 = 6;
+pub const GLYPH_HEIGHT: i32 = 8;
+
+const FIRST_GLYPH: u8 = b' ';
+const NUM_GLYPHS: usize = 96;
+const SOLID_GLYPH: usize = NUM_GLYPHS - 1;
+const ATLAS_WIDTH: i32 = NUM_GLYPHS as i32 * GLYPH_WIDTH;
+
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct TextVertex {
+	pub pos: Vec2,
+	pub uv: Vec2,
+	pub color: Vec4,
+}
+
+impl Vertex for TextVertex {
+	fn descriptor() -> Descriptor {
+		Descriptor::from(&[2, 2, 4])
+	}
+}
+
+
+/// Immediate mode text and rect drawing in pixel space, from the top left of the viewport.
+/// Only really intended for debug and error overlays - ascii only, one built in font.
+pub struct TextRenderer {
+	shader: Shader,
+	font: Texture,
+	mesh: BasicDynamicMesh<TextVertex>,
+}
+
+impl TextRenderer {
+	pub fn new() -> Self {
+		let shader = Shader::from_asset(
+			embed_asset!("../shaders/text.glsl"),
+			&["position", "uv", "color"]
+		);
+
+		let mut font = TextureBuilder::new().r8().nearest().clamp().build();
+		font.upload(Vec2i::new(ATLAS_WIDTH, GLYPH_HEIGHT), &build_font_atlas());
+
+		TextRenderer {
+			shader,
+			font,
+			mesh: BasicDynamicMesh::new(),
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.mesh.clear();
+	}
+
+	pub fn add_rect(&mut self, pos: Vec2, size: Vec2, color: Color) {
+		// Sample a single texel from the middle of the solid glyph
+		let uv = glyph_uv(SOLID_GLYPH) + Vec2::new(2.5 / ATLAS_WIDTH as f32, 0.5);
+		let color = color.to_vec4();
+
+		self.mesh.add_quad(&[
+			TextVertex { pos, uv, color },
+			TextVertex { pos: pos + Vec2::new(0.0, size.y), uv, color },
+			TextVertex { pos: pos + size, uv, color },
+			TextVertex { pos: pos + Vec2::new(size.x, 0.0), uv, color },
+		]);
+	}
+
+	/// Returns the size of the laid out text in pixels
+	pub fn add_text(&mut self, pos: Vec2, scale: f32, color: Color, text: &str) -> Vec2 {
+		let color = color.to_vec4();
+		let cell = Vec2::new(GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32) * scale;
+		let uv_size = Vec2::new(GLYPH_WIDTH as f32 / ATLAS_WIDTH as f32, 1.0);
+
+		let mut cursor = pos;
+		let mut extent = Vec2::zero();
+
+		for ch in text.chars() {
+			if ch == '\n' {
+				cursor = Vec2::new(pos.x, cursor.y + cell.y);
+				continue;
+			}
+
+			if ch != ' ' {
+				let uv = glyph_uv(glyph_index(ch));
+
+				self.mesh.add_quad(&[
+					TextVertex { pos: cursor, uv, color },
+					TextVertex { pos: cursor + Vec2::new(0.0, cell.y), uv: uv + Vec2::new(0.0, uv_size.y), color },
+					TextVertex { pos: cursor + cell, uv: uv + uv_size, color },
+					TextVertex { pos: cursor + Vec2::new(cell.x, 0.0), uv: uv + Vec2::new(uv_size.x, 0.0), color },
+				]);
+			}
+
+			cursor.x += cell.x;
+			extent = Vec2::new(extent.x.max(cursor.x - pos.x), cursor.y + cell.y - pos.y);
+		}
+
+		extent
+	}
+
+	pub fn draw(&self, viewport: Vec2i) {
+		let viewport = viewport.to_vec2();
+		let proj = Mat4::translate(Vec3::new(-1.0, 1.0, 0.0))
+			* Mat4::scale(Vec3::new(2.0 / viewport.x, -2.0 / viewport.y, 1.0));
+
+		unsafe {
+			gl::disable(gl::Capability::DepthTest);
+		}
+
+		self.font.bind(0);
+		self.shader.bind();
+		self.shader.set_uniform("proj_view", proj);
+		self.shader.set_uniform("tex", 0u32);
+		self.mesh.draw(gl::DrawMode::Triangles);
+
+		unsafe {
+			gl::enable(gl::Capability::DepthTest);
+		}
+	}
+}
+
+
+/// Size in pixels that `text` would take up if drawn with `TextRenderer::add_text`
+pub fn measure_text(text: &str, scale: f32) -> Vec2 {
+	let columns = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+	let rows = text.lines().count();
+
+	Vec2::new(columns as f32, rows as f32) * Vec2::new(GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32) * scale
+}
+
+/// Breaks lines so that none are longer than `columns` characters, preferring to break at spaces
+pub fn wrap_text(text: &str, columns: usize) -> String {
+	let columns = columns.max(1);
+	let mut wrapped = String::with_capacity(text.len());
+
+	for (idx, line) in text.lines().enumerate() {
+		if idx > 0 { wrapped.push('\n') }
+
+		let mut line_len = 0;
+
+		for word in line.split(' ') {
+			let word_len = word.chars().count();
+
+			if line_len > 0 && line_len + 1 + word_len > columns {
+				wrapped.push('\n');
+				line_len = 0;
+			} else if line_len > 0 {
+				wrapped.push(' ');
+				line_len += 1;
+			}
+
+			// Words that don't fit on a line by themselves just get chopped up
+			for (char_idx, ch) in word.chars().enumerate() {
+				if char_idx > 0 && line_len >= columns {
+					wrapped.push('\n');
+					line_len = 0;
+				}
+
+				wrapped.push(ch);
+				line_len += 1;
+			}
+		}
+	}
+
+	wrapped
+}
+
+
+fn glyph_index(ch: char) -> usize {
+	let ch = if ch == '\t' { ' ' } else { ch };
+
+	if ch.is_ascii() && (ch as u8) >= FIRST_GLYPH && ((ch as u8 - FIRST_GLYPH) as usize) < SOLID_GLYPH {
+		(ch as u8 - FIRST_GLYPH) as usize
+	} else {
+		(b'?' - FIRST_GLYPH) as usize
+	}
+}
+
+fn glyph_uv(glyph: usize) -> Vec2 {
+	Vec2::new((glyph as i32 * GLYPH_WIDTH) as f32 / ATLAS_WIDTH as f32, 0.0)
+}
+
+fn build_font_atlas() -> Vec<u8> {
+	let mut atlas = vec![0u8; (ATLAS_WIDTH * GLYPH_HEIGHT) as usize];
+
+	for (glyph, columns) in FONT_5X7.iter().enumerate() {
+		for (column, bits) in columns.iter().enumerate() {
+			for row in 0..7 {
+				if bits & (1 << row) != 0 {
+					let x = glyph * GLYPH_WIDTH as usize + column;
+					atlas[row * ATLAS_WIDTH as usize + x] = 0xFF;
+				}
+			}
+		}
+	}
+
+	atlas
+}
+
+
+// Columns of each glyph from left to right, lsb at the top. ' ' through '~', followed by a solid block
+const FONT_5X7: [[u8; 5]; NUM_GLYPHS] = [
+	[0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5F, 0x00, 0x00], [0x00, 0x07, 0x00, 0x07, 0x00], [0x14, 0x7F, 0x14, 0x7F, 0x14],
+	[0x24, 0x2A, 0x7F, 0x2A, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62], [0x36, 0x49, 0x55, 0x22, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00],
+	[0x00, 0x1C, 0x22, 0x41, 0x00], [0x00, 0x41, 0x22, 0x1C, 0x00], [0x14, 0x08, 0x3E, 0x08, 0x14], [0x08, 0x08, 0x3E, 0x08, 0x08],
+	[0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], [0x00, 0x60, 0x60, 0x00, 0x00], [0x20, 0x10, 0x08, 0x04, 0x02],
+
+	[0x3E, 0x51, 0x49, 0x45, 0x3E], [0x00, 0x42, 0x7F, 0x40, 0x00], [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4B, 0x31],
+	[0x18, 0x14, 0x12, 0x7F, 0x10], [0x27, 0x45, 0x45, 0x45, 0x39], [0x3C, 0x4A, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03],
+	[0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1E], [0x00, 0x36, 0x36, 0x00, 0x00], [0x00, 0x56, 0x36, 0x00, 0x00],
+	[0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14], [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06],
+
+	[0x32, 0x49, 0x79, 0x41, 0x3E], [0x7E, 0x11, 0x11, 0x11, 0x7E], [0x7F, 0x49, 0x49, 0x49, 0x36], [0x3E, 0x41, 0x41, 0x41, 0x22],
+	[0x7F, 0x41, 0x41, 0x22, 0x1C], [0x7F, 0x49, 0x49, 0x49, 0x41], [0x7F, 0x09, 0x09, 0x01, 0x01], [0x3E, 0x41, 0x41, 0x51, 0x32],
+	[0x7F, 0x08, 0x08, 0x08, 0x7F], [0x00, 0x41, 0x7F, 0x41, 0x00], [0x20, 0x40, 0x41, 0x3F, 0x01], [0x7F, 0x08, 0x14, 0x22, 0x41],
+	[0x7F, 0x40, 0x40, 0x40, 0x40], [0x7F, 0x02, 0x04, 0x02, 0x7F], [0x7F, 0x04, 0x08, 0x10, 0x7F], [0x3E, 0x41, 0x41, 0x41, 0x3E],
+
+	[0x7F, 0x09, 0x09, 0x09, 0x06], [0x3E, 0x41, 0x51, 0x21, 0x5E], [0x7F, 0x09, 0x19, 0x29, 0x46], [0x46, 0x49, 0x49, 0x49, 0x31],
+	[0x01, 0x01, 0x7F, 0x01, 0x01], [0x3F, 0x40, 0x40, 0x40, 0x3F], [0x1F, 0x20, 0x40, 0x20, 0x1F], [0x7F, 0x20, 0x18, 0x20, 0x7F],
+	[0x63, 0x14, 0x08, 0x14, 0x63], [0x03, 0x04, 0x78, 0x04, 0x03], [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7F, 0x41, 0x41, 0x00],
+	[0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7F, 0x00], [0x04, 0x02, 0x01, 0x02, 0x04], [0x40, 0x40, 0x40, 0x40, 0x40],
+
+	[0x00, 0x01, 0x02, 0x04, 0x00], [0x20, 0x54, 0x54, 0x54, 0x78], [0x7F, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20],
+	[0x38, 0x44, 0x44, 0x48, 0x7F], [0x38, 0x54, 0x54, 0x54, 0x18], [0x08, 0x7E, 0x09, 0x01, 0x02], [0x08, 0x54, 0x54, 0x54, 0x3C],
+	[0x7F, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7D, 0x40, 0x00], [0x20, 0x40, 0x44, 0x3D, 0x00], [0x7F, 0x10, 0x28, 0x44, 0x00],
+	[0x00, 0x41, 0x7F, 0x40, 0x00], [0x7C, 0x04, 0x18, 0x04, 0x78], [0x7C, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38],
+
+	[0x7C, 0x14, 0x14, 0x14, 0x08], [0x08, 0x14, 0x14, 0x18, 0x7C], [0x7C, 0x08, 0x04, 0x04, 0x08], [0x48, 0x54, 0x54, 0x54, 0x20],
+	[0x04, 0x3F, 0x44, 0x40, 0x20], [0x3C, 0x40, 0x40, 0x20, 0x7C], [0x1C, 0x20, 0x40, 0x20, 0x1C], [0x3C, 0x40, 0x30, 0x40, 0x3C],
+	[0x44, 0x28, 0x10, 0x28, 0x44], [0x0C, 0x50, 0x50, 0x50, 0x3C], [0x44, 0x64, 0x54, 0x4C, 0x44], [0x00, 0x08, 0x36, 0x41, 0x00],
+	[0x00, 0x00, 0x7F, 0x00, 0x00], [0x00, 0x41, 0x36, 0x08, 0x00], [0x10, 0x08, 0x08, 0x10, 0x08], [0x7F, 0x7F, 0x7F, 0x7F, 0x7F],
+];
@@ -111,3 +111,25 @@ impl Vertex for TexturedVertex {
 		Descriptor::from(&[3, 2])
 	}
 }
+
+
+
+/// For drawing into an `IdPicker` pass. `id` is the object id, encoded as a colour
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct IdVertex {
+	pub pos: Vec3,
+	pub id: Vec3,
+}
+
+impl IdVertex {
+	pub fn new(pos: Vec3, id: u32) -> Self {
+		IdVertex{pos, id: crate::graphics::picking::encode_id(id)}
+	}
+}
+
+impl Vertex for IdVertex {
+	fn descriptor() -> Descriptor {
+		Descriptor::from(&[3, 3])
+	}
+}
@@ -0,0 +1,69 @@
+use common::math::{Mat4, Vec4};
+use super::*;
+
+/// Everything the engine needs from a gl context.
+/// `WebGlBackend` forwards to the js bindings, `RecordingBackend` just logs calls so the engine can run natively.
+pub trait GlBackend {
+	fn viewport(&mut self, x: i32, y: i32, w: i32, h: i32);
+	fn scissor(&mut self, x: i32, y: i32, w: i32, h: i32);
+	fn get_viewport(&mut self) -> [i32; 4];
+
+	fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32);
+	fn clear(&mut self, bits: u32);
+	fn enable(&mut self, cap: Capability);
+	fn disable(&mut self, cap: Capability);
+	fn is_enabled(&mut self, cap: Capability) -> bool;
+
+	fn blend_func(&mut self, src: BlendFactor, dst: BlendFactor);
+
+	fn draw_arrays(&mut self, dm: DrawMode, start: usize, vert_count: usize);
+	fn draw_elements(&mut self, dm: DrawMode, el_count: usize, el_type: Type, el_offset: usize);
+
+	fn create_buffer(&mut self) -> BufferID;
+	fn bind_buffer(&mut self, target: BufferTarget, id: BufferID);
+	fn upload_buffer_data(&mut self, target: BufferTarget, data: &[u8]);
+	fn vertex_attrib_pointer(&mut self, attrib: u32, components: u32, ty: Type, normalize: bool, stride: usize, offset: usize);
+	fn enable_attribute(&mut self, attrib: u32);
+	fn disable_attribute(&mut self, attrib: u32);
+
+	fn create_texture(&mut self) -> TextureID;
+	fn bind_texture(&mut self, id: TextureID);
+	fn active_texture(&mut self, slot: i32);
+	fn upload_image_data(&mut self, w: u32, h: u32, format: Format, ty: Type, data: Option<&[u8]>);
+	fn tex_parameter(&mut self, param: TextureParam, value: TextureParamValue);
+
+	fn create_framebuffer(&mut self) -> FramebufferID;
+	fn delete_framebuffer(&mut self, id: FramebufferID);
+	fn bind_framebuffer(&mut self, id: FramebufferID);
+	fn get_bound_framebuffer(&mut self) -> FramebufferID;
+	fn framebuffer_texture_2d(&mut self, id: TextureID);
+	fn framebuffer_renderbuffer(&mut self, id: RenderbufferID);
+
+	/// Reads RGBA8 pixels from the bound framebuffer into `data`, bottom row first. `data` must hold `w * h * 4` bytes
+	fn read_pixels(&mut self, x: i32, y: i32, w: i32, h: i32, data: &mut [u8]);
+
+	fn create_renderbuffer(&mut self) -> RenderbufferID;
+	fn delete_renderbuffer(&mut self, id: RenderbufferID);
+	fn bind_renderbuffer(&mut self, id: RenderbufferID);
+	fn renderbuffer_depth_storage(&mut self, w: i32, h: i32);
+
+	fn create_shader_program(&mut self) -> ProgramID;
+	fn create_shader(&mut self, ty: ShaderType, src: &str) -> ShaderID;
+	fn bind_attrib_location(&mut self, program: ProgramID, name: &str, idx: u32);
+	fn link_program(&mut self, program: ProgramID, vert: ShaderID, frag: ShaderID);
+	fn use_program(&mut self, program: ProgramID);
+
+	fn stencil_func(&mut self, condition: StencilCondition, reference: u8, mask: u8);
+	fn stencil_op(&mut self, stencil_fail: StencilOp, depth_fail: StencilOp, pass: StencilOp);
+
+	fn color_mask(&mut self, r: bool, g: bool, b: bool, a: bool);
+	fn depth_mask(&mut self, enabled: bool);
+	fn stencil_mask(&mut self, bits: u8);
+
+	fn set_uniform_int(&mut self, program: ProgramID, name: &str, i: u32);
+	fn set_uniform_f32(&mut self, program: ProgramID, name: &str, f: f32);
+	fn set_uniform_vec4(&mut self, program: ProgramID, name: &str, v: Vec4);
+
+	/// `mat` is expected to already be transposed into gl's column-major order.
+	fn set_uniform_mat4(&mut self, program: ProgramID, name: &str, mat: &Mat4);
+}
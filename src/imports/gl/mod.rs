@@ -0,0 +1,359 @@
+use crate::imports::RawStr;
+use common::math::{Mat4, Vec4};
+use std::cell::RefCell;
+
+mod backend;
+mod recording;
+
+#[cfg(target_arch = "wasm32")]
+mod webgl;
+
+pub use self::backend::GlBackend;
+pub use self::recording::{RecordingBackend, CallLog, Call, UniformValue};
+
+#[cfg(target_arch = "wasm32")]
+pub use self::webgl::WebGlBackend;
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum ShaderType {
+	Vertex,
+	Fragment
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum BufferTarget {
+	ArrayBuffer = 34962,
+	ElementArrayBuffer = 34963,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum Type {
+	Byte = 5120,
+	Short = 5122,
+	UnsignedByte = 5121,
+	UnsignedShort = 5123,
+	Float = 5126,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum Format {
+	Luminance = 6409,
+	LuminanceAlpha = 6410,
+	RGB = 6407,
+	RGBA = 6408,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum TextureParam {
+	MagFilter = 10240,
+	MinFilter = 10241,
+	WrapS = 10242,
+	WrapT = 10243,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum TextureParamValue {
+	Nearest = 9728,
+	Linear = 9729,
+	NearestMipmapNearest = 9984,
+	LinearMipmapNearest = 9985,
+	NearestMipmapLinear = 9986,
+	LinearMipmapLinear = 9987,
+
+	Repeat = 10497,
+	ClampToEdge = 33071,
+	MirroredRepeat = 33648,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum DrawMode {
+	Points = 0,
+	Lines = 1,
+	LineLoop = 2,
+	LineStrip = 3,
+	Triangles = 4,
+	TriangleStrip = 5,
+	TriangleFan = 6,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum Capability {
+	Blend = 3042,
+	CullFace = 2884,
+	DepthTest = 2929,
+	Dither = 3024,
+	PolygonOffsetFill = 32823,
+	SampleAlphaToCoverage = 32926,
+	SampleCoverage = 32928,
+	ScissorTest = 3089,
+	StencilTest = 2960
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum StencilCondition {
+	Never = 512,
+	Always = 519,
+	Equal = 514,
+	Less = 513,
+	Greater = 516,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum StencilOp {
+	Keep = 7680,
+	Replace = 7681,
+	Incr = 7682,
+	Decr = 7683,
+	Invert = 5386,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum BlendFactor {
+	Zero = 0,
+	One = 1,
+
+	SrcColor = 768,
+	OneMinusSrcColor = 769,
+	DstColor = 774,
+	OneMinusDstColor = 775,
+
+	SrcAlpha = 770,
+	OneMinusSrcAlpha = 771,
+	DstAlpha = 772,
+	OneMinusDstAlpha = 773,
+
+	ConstantColor = 32769,
+	OneMinusConstantColor = 32770,
+	ConstantAlpha = 32771,
+	OneMinusConstantAlpha = 32772,
+}
+
+pub const COLOR_BUFFER_BIT: u32 = 1<<14;
+pub const DEPTH_BUFFER_BIT: u32 = 1<<8;
+pub const STENCIL_BUFFER_BIT: u32 = 1<<10;
+
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ShaderID(pub u32);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ProgramID(pub u32);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BufferID(pub u32);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TextureID(pub u32);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FramebufferID(pub u32);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RenderbufferID(pub u32);
+
+thread_local! {
+	static BACKEND: RefCell<Option<Box<dyn GlBackend>>> = RefCell::new(None);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_backend() -> Box<dyn GlBackend> { Box::new(WebGlBackend) }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_backend() -> Box<dyn GlBackend> { Box::new(RecordingBackend::new()) }
+
+fn with_backend<R, F>(f: F) -> R where F: FnOnce(&mut dyn GlBackend) -> R {
+	BACKEND.with(|backend| {
+		let mut backend = backend.borrow_mut();
+		let backend = backend.get_or_insert_with(default_backend);
+		f(&mut **backend)
+	})
+}
+
+/// Replaces the backend that all gl calls on this thread are routed through, returning the previous one.
+pub fn set_backend<B: GlBackend + 'static>(backend: B) -> Option<Box<dyn GlBackend>> {
+	BACKEND.with(|current| current.borrow_mut().replace(Box::new(backend)))
+}
+
+/// Routes all gl calls on this thread into a fresh `RecordingBackend` and returns its log.
+pub fn install_recording_backend() -> CallLog {
+	let backend = RecordingBackend::new();
+	let log = backend.log();
+	set_backend(backend);
+	log
+}
+
+
+unsafe fn raw_slice<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+	if ptr.is_null() {
+		None
+	} else {
+		Some(std::slice::from_raw_parts(ptr, len))
+	}
+}
+
+
+pub unsafe fn viewport(x: i32, y: i32, w: i32, h: i32) { with_backend(|b| b.viewport(x, y, w, h)) }
+pub unsafe fn scissor(x: i32, y: i32, w: i32, h: i32) { with_backend(|b| b.scissor(x, y, w, h)) }
+
+pub unsafe fn get_viewport(ptr: *mut i32, len: usize) {
+	let viewport = with_backend(|b| b.get_viewport());
+	let out = std::slice::from_raw_parts_mut(ptr, len.min(viewport.len()));
+	out.copy_from_slice(&viewport[..out.len()]);
+}
+
+pub unsafe fn clear_color(r: f32, g: f32, b: f32, a: f32) { with_backend(|be| be.clear_color(r, g, b, a)) }
+pub unsafe fn clear(bits: u32) { with_backend(|b| b.clear(bits)) }
+pub unsafe fn enable(cap: Capability) { with_backend(|b| b.enable(cap)) }
+pub unsafe fn disable(cap: Capability) { with_backend(|b| b.disable(cap)) }
+pub unsafe fn is_enabled(cap: Capability) -> bool { with_backend(|b| b.is_enabled(cap)) }
+
+pub unsafe fn blend_func(src: BlendFactor, dst: BlendFactor) { with_backend(|b| b.blend_func(src, dst)) }
+
+pub unsafe fn draw_arrays(dm: DrawMode, start: usize, vert_count: usize) {
+	with_backend(|b| b.draw_arrays(dm, start, vert_count))
+}
+
+pub unsafe fn draw_elements(dm: DrawMode, el_count: usize, el_type: Type, el_offset: usize /*bytes*/) {
+	with_backend(|b| b.draw_elements(dm, el_count, el_type, el_offset))
+}
+
+pub unsafe fn create_buffer() -> BufferID { with_backend(|b| b.create_buffer()) }
+pub unsafe fn bind_buffer(target: BufferTarget, id: BufferID) { with_backend(|b| b.bind_buffer(target, id)) }
+
+pub unsafe fn upload_buffer_data(target: BufferTarget, ptr: *const u8, len: usize) {
+	let data = raw_slice(ptr, len).unwrap_or(&[]);
+	with_backend(|b| b.upload_buffer_data(target, data))
+}
+
+pub unsafe fn vertex_attrib_pointer(attrib: u32, components: u32, ty: Type, normalize: bool, stride: usize, offset: usize) {
+	with_backend(|b| b.vertex_attrib_pointer(attrib, components, ty, normalize, stride, offset))
+}
+
+pub unsafe fn enable_attribute(attrib: u32) { with_backend(|b| b.enable_attribute(attrib)) }
+pub unsafe fn disable_attribute(attrib: u32) { with_backend(|b| b.disable_attribute(attrib)) }
+
+pub unsafe fn create_texture() -> TextureID { with_backend(|b| b.create_texture()) }
+pub unsafe fn bind_texture(id: TextureID) { with_backend(|b| b.bind_texture(id)) }
+pub unsafe fn active_texture(slot: i32) { with_backend(|b| b.active_texture(slot)) }
+
+pub unsafe fn upload_image_data(w: u32, h: u32, format: Format, ty: Type, ptr: *const u8, len: usize) {
+	let data = raw_slice(ptr, len);
+	with_backend(|b| b.upload_image_data(w, h, format, ty, data))
+}
+
+pub unsafe fn tex_parameter(param: TextureParam, value: TextureParamValue) {
+	with_backend(|b| b.tex_parameter(param, value))
+}
+
+pub unsafe fn create_framebuffer() -> FramebufferID { with_backend(|b| b.create_framebuffer()) }
+pub unsafe fn delete_framebuffer(id: FramebufferID) { with_backend(|b| b.delete_framebuffer(id)) }
+pub unsafe fn bind_framebuffer(id: FramebufferID) { with_backend(|b| b.bind_framebuffer(id)) }
+pub unsafe fn get_bound_framebuffer() -> FramebufferID { with_backend(|b| b.get_bound_framebuffer()) }
+pub unsafe fn framebuffer_texture_2d(id: TextureID) { with_backend(|b| b.framebuffer_texture_2d(id)) }
+pub unsafe fn framebuffer_renderbuffer(id: RenderbufferID) { with_backend(|b| b.framebuffer_renderbuffer(id)) }
+
+pub unsafe fn read_pixels(x: i32, y: i32, w: i32, h: i32, ptr: *mut u8, len: usize) {
+	let data = std::slice::from_raw_parts_mut(ptr, len);
+	with_backend(|b| b.read_pixels(x, y, w, h, data))
+}
+
+pub unsafe fn create_renderbuffer() -> RenderbufferID { with_backend(|b| b.create_renderbuffer()) }
+pub unsafe fn delete_renderbuffer(id: RenderbufferID) { with_backend(|b| b.delete_renderbuffer(id)) }
+pub unsafe fn bind_renderbuffer(id: RenderbufferID) { with_backend(|b| b.bind_renderbuffer(id)) }
+pub unsafe fn renderbuffer_depth_storage(w: i32, h: i32) { with_backend(|b| b.renderbuffer_depth_storage(w, h)) }
+
+pub unsafe fn create_shader_program() -> ProgramID { with_backend(|b| b.create_shader_program()) }
+
+pub unsafe fn create_shader(ty: ShaderType, src: RawStr) -> ShaderID {
+	let src = src.as_str();
+	with_backend(|b| b.create_shader(ty, src))
+}
+
+pub unsafe fn bind_attrib_location(program: ProgramID, name: RawStr, idx: u32) {
+	let name = name.as_str();
+	with_backend(|b| b.bind_attrib_location(program, name, idx))
+}
+
+pub unsafe fn link_program(program: ProgramID, vert: ShaderID, frag: ShaderID) {
+	with_backend(|b| b.link_program(program, vert, frag))
+}
+
+pub unsafe fn use_program(program: ProgramID) { with_backend(|b| b.use_program(program)) }
+
+pub unsafe fn stencil_func(condition: StencilCondition, reference: u8, mask: u8) {
+	with_backend(|b| b.stencil_func(condition, reference, mask))
+}
+
+pub unsafe fn stencil_op(stencil_fail: StencilOp, depth_fail: StencilOp, pass: StencilOp) {
+	with_backend(|b| b.stencil_op(stencil_fail, depth_fail, pass))
+}
+
+pub unsafe fn color_mask(r: bool, g: bool, b: bool, a: bool) { with_backend(|be| be.color_mask(r, g, b, a)) }
+pub unsafe fn depth_mask(enabled: bool) { with_backend(|b| b.depth_mask(enabled)) }
+pub unsafe fn stencil_mask(bits: u8) { with_backend(|b| b.stencil_mask(bits)) }
+
+pub unsafe fn set_uniform_int_raw(program: ProgramID, name: RawStr, i: u32) {
+	let name = name.as_str();
+	with_backend(|b| b.set_uniform_int(program, name, i))
+}
+
+pub unsafe fn set_uniform_f32_raw(program: ProgramID, name: RawStr, f: f32) {
+	let name = name.as_str();
+	with_backend(|b| b.set_uniform_f32(program, name, f))
+}
+
+pub unsafe fn set_uniform_vec4_raw(program: ProgramID, name: RawStr, x: f32, y: f32, z: f32, w: f32) {
+	let name = name.as_str();
+	with_backend(|b| b.set_uniform_vec4(program, name, Vec4::new(x, y, z, w)))
+}
+
+pub unsafe fn set_uniform_mat4_raw(program: ProgramID, name: RawStr, mat: *const Mat4) {
+	let name = name.as_str();
+	let mat = &*mat;
+	with_backend(|b| b.set_uniform_mat4(program, name, mat))
+}
+
+pub fn set_uniform_int(program: ProgramID, name: &str, i: u32) {
+	unsafe {
+		set_uniform_int_raw(program, name.into(), i);
+	}
+}
+
+pub fn set_uniform_f32(program: ProgramID, name: &str, f: f32) {
+	unsafe {
+		set_uniform_f32_raw(program, name.into(), f);
+	}
+}
+
+pub fn set_uniform_vec4(program: ProgramID, name: &str, v: Vec4) {
+	unsafe {
+		set_uniform_vec4_raw(program, name.into(), v.x, v.y, v.z, v.w);
+	}
+}
+
+pub fn set_uniform_mat4(program: ProgramID, name: &str, v: &Mat4) {
+	unsafe {
+		set_uniform_mat4_raw(program, name.into(), &v.transpose() as *const Mat4);
+	}
+}
+
+impl TextureID {
+	pub fn is_valid(self) -> bool { self.0 != 0 }
+}
\ No newline at end of file
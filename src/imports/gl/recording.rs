@@ -0,0 +1,291 @@
+use common::math::{Mat4, Vec4};
+use std::rc::Rc;
+use std::cell::{RefCell, Ref};
+use super::*;
+
+
+#[derive(Clone, Debug)]
+pub enum UniformValue {
+	Int(u32),
+	F32(f32),
+	Vec4(Vec4),
+	Mat4(Mat4),
+}
+
+#[derive(Clone, Debug)]
+pub enum Call {
+	Viewport(i32, i32, i32, i32),
+	Scissor(i32, i32, i32, i32),
+
+	ClearColor(f32, f32, f32, f32),
+	Clear(u32),
+	Enable(Capability),
+	Disable(Capability),
+
+	BlendFunc(BlendFactor, BlendFactor),
+
+	DrawArrays { mode: DrawMode, start: usize, count: usize },
+	DrawElements { mode: DrawMode, count: usize, el_type: Type, offset: usize },
+
+	CreateBuffer(BufferID),
+	BindBuffer(BufferTarget, BufferID),
+	UploadBufferData(BufferTarget, Vec<u8>),
+	VertexAttribPointer { attrib: u32, components: u32, ty: Type, normalize: bool, stride: usize, offset: usize },
+	EnableAttribute(u32),
+	DisableAttribute(u32),
+
+	CreateTexture(TextureID),
+	BindTexture(TextureID),
+	ActiveTexture(i32),
+	UploadImageData { w: u32, h: u32, format: Format, ty: Type, data: Option<Vec<u8>> },
+	TexParameter(TextureParam, TextureParamValue),
+
+	CreateFramebuffer(FramebufferID),
+	DeleteFramebuffer(FramebufferID),
+	BindFramebuffer(FramebufferID),
+	FramebufferTexture2D(TextureID),
+	FramebufferRenderbuffer(RenderbufferID),
+	ReadPixels(i32, i32, i32, i32),
+
+	CreateRenderbuffer(RenderbufferID),
+	DeleteRenderbuffer(RenderbufferID),
+	BindRenderbuffer(RenderbufferID),
+	RenderbufferDepthStorage(i32, i32),
+
+	CreateShaderProgram(ProgramID),
+	CreateShader(ShaderType, ShaderID, String),
+	BindAttribLocation(ProgramID, String, u32),
+	LinkProgram(ProgramID, ShaderID, ShaderID),
+	UseProgram(ProgramID),
+
+	StencilFunc(StencilCondition, u8, u8),
+	StencilOp(StencilOp, StencilOp, StencilOp),
+
+	ColorMask(bool, bool, bool, bool),
+	DepthMask(bool),
+	StencilMask(u8),
+
+	SetUniform(ProgramID, String, UniformValue),
+}
+
+
+/// Shared handle to the calls made through a `RecordingBackend`.
+/// Stays valid after the backend is replaced, so tests can inspect it once the engine is done with it.
+#[derive(Clone, Default)]
+pub struct CallLog(Rc<RefCell<Vec<Call>>>);
+
+impl CallLog {
+	pub fn calls(&self) -> Ref<'_, Vec<Call>> { self.0.borrow() }
+	pub fn take(&self) -> Vec<Call> { std::mem::take(&mut *self.0.borrow_mut()) }
+	pub fn clear(&self) { self.0.borrow_mut().clear() }
+	pub fn len(&self) -> usize { self.0.borrow().len() }
+
+	pub fn count<F>(&self, f: F) -> usize where F: Fn(&Call) -> bool {
+		self.0.borrow().iter().filter(|c| f(c)).count()
+	}
+
+	pub fn draw_calls(&self) -> usize {
+		self.count(|c| matches!(c, Call::DrawArrays{..} | Call::DrawElements{..}))
+	}
+
+	fn push(&self, call: Call) {
+		self.0.borrow_mut().push(call);
+	}
+}
+
+
+/// Headless backend that hands out ids the same way gl.js does and logs every call.
+/// Only tracks the little state that can be queried back - the bound framebuffer and the viewport.
+pub struct RecordingBackend {
+	log: CallLog,
+
+	next_id: u32,
+	bound_framebuffer: FramebufferID,
+	viewport: [i32; 4],
+	enabled: Vec<u32>,
+}
+
+impl RecordingBackend {
+	pub fn new() -> Self {
+		RecordingBackend {
+			log: CallLog::default(),
+
+			next_id: 1,
+			bound_framebuffer: FramebufferID(0),
+			viewport: [0; 4],
+			// Dither is the only capability gl starts with enabled
+			enabled: vec![Capability::Dither as u32],
+		}
+	}
+
+	pub fn log(&self) -> CallLog { self.log.clone() }
+
+	fn new_id(&mut self) -> u32 {
+		let id = self.next_id;
+		self.next_id += 1;
+		id
+	}
+}
+
+impl GlBackend for RecordingBackend {
+	fn viewport(&mut self, x: i32, y: i32, w: i32, h: i32) {
+		self.viewport = [x, y, w, h];
+		self.log.push(Call::Viewport(x, y, w, h));
+	}
+
+	fn scissor(&mut self, x: i32, y: i32, w: i32, h: i32) { self.log.push(Call::Scissor(x, y, w, h)) }
+	fn get_viewport(&mut self) -> [i32; 4] { self.viewport }
+
+	fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) { self.log.push(Call::ClearColor(r, g, b, a)) }
+	fn clear(&mut self, bits: u32) { self.log.push(Call::Clear(bits)) }
+	fn enable(&mut self, cap: Capability) {
+		if !self.is_enabled(cap) {
+			self.enabled.push(cap as u32);
+		}
+
+		self.log.push(Call::Enable(cap))
+	}
+
+	fn disable(&mut self, cap: Capability) {
+		self.enabled.retain(|&c| c != cap as u32);
+		self.log.push(Call::Disable(cap))
+	}
+
+	fn is_enabled(&mut self, cap: Capability) -> bool { self.enabled.contains(&(cap as u32)) }
+
+	fn blend_func(&mut self, src: BlendFactor, dst: BlendFactor) { self.log.push(Call::BlendFunc(src, dst)) }
+
+	fn draw_arrays(&mut self, mode: DrawMode, start: usize, count: usize) {
+		self.log.push(Call::DrawArrays { mode, start, count })
+	}
+
+	fn draw_elements(&mut self, mode: DrawMode, count: usize, el_type: Type, offset: usize) {
+		self.log.push(Call::DrawElements { mode, count, el_type, offset })
+	}
+
+	fn create_buffer(&mut self) -> BufferID {
+		let id = BufferID(self.new_id());
+		self.log.push(Call::CreateBuffer(id));
+		id
+	}
+
+	fn bind_buffer(&mut self, target: BufferTarget, id: BufferID) { self.log.push(Call::BindBuffer(target, id)) }
+
+	fn upload_buffer_data(&mut self, target: BufferTarget, data: &[u8]) {
+		self.log.push(Call::UploadBufferData(target, data.to_vec()))
+	}
+
+	fn vertex_attrib_pointer(&mut self, attrib: u32, components: u32, ty: Type, normalize: bool, stride: usize, offset: usize) {
+		self.log.push(Call::VertexAttribPointer { attrib, components, ty, normalize, stride, offset })
+	}
+
+	fn enable_attribute(&mut self, attrib: u32) { self.log.push(Call::EnableAttribute(attrib)) }
+	fn disable_attribute(&mut self, attrib: u32) { self.log.push(Call::DisableAttribute(attrib)) }
+
+	fn create_texture(&mut self) -> TextureID {
+		let id = TextureID(self.new_id());
+		self.log.push(Call::CreateTexture(id));
+		id
+	}
+
+	fn bind_texture(&mut self, id: TextureID) { self.log.push(Call::BindTexture(id)) }
+	fn active_texture(&mut self, slot: i32) { self.log.push(Call::ActiveTexture(slot)) }
+
+	fn upload_image_data(&mut self, w: u32, h: u32, format: Format, ty: Type, data: Option<&[u8]>) {
+		let data = data.map(<[u8]>::to_vec);
+		self.log.push(Call::UploadImageData { w, h, format, ty, data })
+	}
+
+	fn tex_parameter(&mut self, param: TextureParam, value: TextureParamValue) {
+		self.log.push(Call::TexParameter(param, value))
+	}
+
+	fn create_framebuffer(&mut self) -> FramebufferID {
+		let id = FramebufferID(self.new_id());
+		self.log.push(Call::CreateFramebuffer(id));
+		id
+	}
+
+	fn delete_framebuffer(&mut self, id: FramebufferID) {
+		if self.bound_framebuffer == id {
+			self.bound_framebuffer = FramebufferID(0);
+		}
+
+		self.log.push(Call::DeleteFramebuffer(id))
+	}
+
+	fn bind_framebuffer(&mut self, id: FramebufferID) {
+		self.bound_framebuffer = id;
+		self.log.push(Call::BindFramebuffer(id))
+	}
+
+	fn get_bound_framebuffer(&mut self) -> FramebufferID { self.bound_framebuffer }
+	fn framebuffer_texture_2d(&mut self, id: TextureID) { self.log.push(Call::FramebufferTexture2D(id)) }
+	fn framebuffer_renderbuffer(&mut self, id: RenderbufferID) { self.log.push(Call::FramebufferRenderbuffer(id)) }
+
+	/// There's nothing to read back, so reads always come back cleared
+	fn read_pixels(&mut self, x: i32, y: i32, w: i32, h: i32, data: &mut [u8]) {
+		data.iter_mut().for_each(|b| *b = 0);
+		self.log.push(Call::ReadPixels(x, y, w, h))
+	}
+
+	fn create_renderbuffer(&mut self) -> RenderbufferID {
+		let id = RenderbufferID(self.new_id());
+		self.log.push(Call::CreateRenderbuffer(id));
+		id
+	}
+
+	fn delete_renderbuffer(&mut self, id: RenderbufferID) { self.log.push(Call::DeleteRenderbuffer(id)) }
+	fn bind_renderbuffer(&mut self, id: RenderbufferID) { self.log.push(Call::BindRenderbuffer(id)) }
+	fn renderbuffer_depth_storage(&mut self, w: i32, h: i32) { self.log.push(Call::RenderbufferDepthStorage(w, h)) }
+
+	fn create_shader_program(&mut self) -> ProgramID {
+		let id = ProgramID(self.new_id());
+		self.log.push(Call::CreateShaderProgram(id));
+		id
+	}
+
+	fn create_shader(&mut self, ty: ShaderType, src: &str) -> ShaderID {
+		let id = ShaderID(self.new_id());
+		self.log.push(Call::CreateShader(ty, id, src.to_owned()));
+		id
+	}
+
+	fn bind_attrib_location(&mut self, program: ProgramID, name: &str, idx: u32) {
+		self.log.push(Call::BindAttribLocation(program, name.to_owned(), idx))
+	}
+
+	fn link_program(&mut self, program: ProgramID, vert: ShaderID, frag: ShaderID) {
+		self.log.push(Call::LinkProgram(program, vert, frag))
+	}
+
+	fn use_program(&mut self, program: ProgramID) { self.log.push(Call::UseProgram(program)) }
+
+	fn stencil_func(&mut self, condition: StencilCondition, reference: u8, mask: u8) {
+		self.log.push(Call::StencilFunc(condition, reference, mask))
+	}
+
+	fn stencil_op(&mut self, stencil_fail: StencilOp, depth_fail: StencilOp, pass: StencilOp) {
+		self.log.push(Call::StencilOp(stencil_fail, depth_fail, pass))
+	}
+
+	fn color_mask(&mut self, r: bool, g: bool, b: bool, a: bool) { self.log.push(Call::ColorMask(r, g, b, a)) }
+	fn depth_mask(&mut self, enabled: bool) { self.log.push(Call::DepthMask(enabled)) }
+	fn stencil_mask(&mut self, bits: u8) { self.log.push(Call::StencilMask(bits)) }
+
+	fn set_uniform_int(&mut self, program: ProgramID, name: &str, i: u32) {
+		self.log.push(Call::SetUniform(program, name.to_owned(), UniformValue::Int(i)))
+	}
+
+	fn set_uniform_f32(&mut self, program: ProgramID, name: &str, f: f32) {
+		self.log.push(Call::SetUniform(program, name.to_owned(), UniformValue::F32(f)))
+	}
+
+	fn set_uniform_vec4(&mut self, program: ProgramID, name: &str, v: Vec4) {
+		self.log.push(Call::SetUniform(program, name.to_owned(), UniformValue::Vec4(v)))
+	}
+
+	fn set_uniform_mat4(&mut self, program: ProgramID, name: &str, mat: &Mat4) {
+		self.log.push(Call::SetUniform(program, name.to_owned(), UniformValue::Mat4(*mat)))
+	}
+}
@@ -0,0 +1,185 @@
+use common::math::{Mat4, Vec4};
+use crate::imports::RawStr;
+use super::*;
+
+mod raw {
+	use super::*;
+
+	extern {
+		pub fn viewport(x: i32, y: i32, w: i32, h: i32);
+		pub fn scissor(x: i32, y: i32, w: i32, h: i32);
+		pub fn get_viewport(ptr: *mut i32, len: usize);
+
+		pub fn clear_color(r: f32, g: f32, b: f32, a: f32);
+		pub fn clear(_: u32);
+		pub fn enable(_: Capability);
+		pub fn disable(_: Capability);
+		pub fn is_enabled(_: Capability) -> bool;
+
+		pub fn blend_func(src: BlendFactor, dst: BlendFactor);
+
+		pub fn draw_arrays(_: DrawMode, start: usize, vert_count: usize);
+		pub fn draw_elements(_: DrawMode, el_count: usize, el_type: Type, el_offset: usize /*bytes*/);
+
+		pub fn create_buffer() -> BufferID;
+		pub fn bind_buffer(_: BufferTarget, _: BufferID);
+		pub fn upload_buffer_data(_: BufferTarget, _: *const u8, _: usize);
+		pub fn vertex_attrib_pointer(attrib: u32, components: u32, _: Type, normalize: bool, stride: usize, offset: usize);
+		pub fn enable_attribute(_: u32);
+		pub fn disable_attribute(_: u32);
+
+		pub fn create_texture() -> TextureID;
+		pub fn bind_texture(_: TextureID);
+		pub fn active_texture(_: i32);
+		pub fn upload_image_data(w: u32, h: u32, _: Format, _: Type, _: *const u8, _: usize);
+		pub fn tex_parameter(_: TextureParam, _: TextureParamValue);
+
+		pub fn create_framebuffer() -> FramebufferID;
+		pub fn delete_framebuffer(_: FramebufferID);
+		pub fn bind_framebuffer(_: FramebufferID);
+		pub fn get_bound_framebuffer() -> FramebufferID;
+		pub fn framebuffer_texture_2d(_: TextureID);
+		pub fn framebuffer_renderbuffer(_: RenderbufferID);
+		pub fn read_pixels(x: i32, y: i32, w: i32, h: i32, _: *mut u8, _: usize);
+
+		pub fn create_renderbuffer() -> RenderbufferID;
+		pub fn delete_renderbuffer(_: RenderbufferID);
+		pub fn bind_renderbuffer(_: RenderbufferID);
+		pub fn renderbuffer_depth_storage(w: i32, h: i32);
+
+		pub fn create_shader_program() -> ProgramID;
+		pub fn create_shader(_: ShaderType, _: RawStr) -> ShaderID;
+		pub fn bind_attrib_location(_: ProgramID, name: RawStr, idx: u32);
+		pub fn link_program(_: ProgramID, vert: ShaderID, frag: ShaderID);
+		pub fn use_program(_: ProgramID);
+
+		pub fn stencil_func(_: StencilCondition, reference: u8, mask: u8);
+		pub fn stencil_op(stencil_fail: StencilOp, depth_fail: StencilOp, pass: StencilOp);
+
+		pub fn color_mask(r: bool, g: bool, b: bool, a: bool);
+		pub fn depth_mask(enabled: bool);
+		pub fn stencil_mask(bits: u8);
+
+		pub fn set_uniform_int_raw(_: ProgramID, _: RawStr, _: u32);
+		pub fn set_uniform_f32_raw(_: ProgramID, _: RawStr, _: f32);
+		pub fn set_uniform_vec4_raw(_: ProgramID, _: RawStr, _: f32, _: f32, _: f32, _: f32);
+		pub fn set_uniform_mat4_raw(_: ProgramID, _: RawStr, _: *const Mat4);
+	}
+}
+
+
+/// Forwards every call straight through to the WebGL context set up by gl.js.
+pub struct WebGlBackend;
+
+impl GlBackend for WebGlBackend {
+	fn viewport(&mut self, x: i32, y: i32, w: i32, h: i32) { unsafe { raw::viewport(x, y, w, h) } }
+	fn scissor(&mut self, x: i32, y: i32, w: i32, h: i32) { unsafe { raw::scissor(x, y, w, h) } }
+
+	fn get_viewport(&mut self) -> [i32; 4] {
+		let mut viewport = [0; 4];
+		unsafe { raw::get_viewport(viewport.as_mut_ptr(), viewport.len()) }
+		viewport
+	}
+
+	fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) { unsafe { raw::clear_color(r, g, b, a) } }
+	fn clear(&mut self, bits: u32) { unsafe { raw::clear(bits) } }
+	fn enable(&mut self, cap: Capability) { unsafe { raw::enable(cap) } }
+	fn disable(&mut self, cap: Capability) { unsafe { raw::disable(cap) } }
+	fn is_enabled(&mut self, cap: Capability) -> bool { unsafe { raw::is_enabled(cap) } }
+
+	fn blend_func(&mut self, src: BlendFactor, dst: BlendFactor) { unsafe { raw::blend_func(src, dst) } }
+
+	fn draw_arrays(&mut self, dm: DrawMode, start: usize, vert_count: usize) {
+		unsafe { raw::draw_arrays(dm, start, vert_count) }
+	}
+
+	fn draw_elements(&mut self, dm: DrawMode, el_count: usize, el_type: Type, el_offset: usize) {
+		unsafe { raw::draw_elements(dm, el_count, el_type, el_offset) }
+	}
+
+	fn create_buffer(&mut self) -> BufferID { unsafe { raw::create_buffer() } }
+	fn bind_buffer(&mut self, target: BufferTarget, id: BufferID) { unsafe { raw::bind_buffer(target, id) } }
+
+	fn upload_buffer_data(&mut self, target: BufferTarget, data: &[u8]) {
+		unsafe { raw::upload_buffer_data(target, data.as_ptr(), data.len()) }
+	}
+
+	fn vertex_attrib_pointer(&mut self, attrib: u32, components: u32, ty: Type, normalize: bool, stride: usize, offset: usize) {
+		unsafe { raw::vertex_attrib_pointer(attrib, components, ty, normalize, stride, offset) }
+	}
+
+	fn enable_attribute(&mut self, attrib: u32) { unsafe { raw::enable_attribute(attrib) } }
+	fn disable_attribute(&mut self, attrib: u32) { unsafe { raw::disable_attribute(attrib) } }
+
+	fn create_texture(&mut self) -> TextureID { unsafe { raw::create_texture() } }
+	fn bind_texture(&mut self, id: TextureID) { unsafe { raw::bind_texture(id) } }
+	fn active_texture(&mut self, slot: i32) { unsafe { raw::active_texture(slot) } }
+
+	fn upload_image_data(&mut self, w: u32, h: u32, format: Format, ty: Type, data: Option<&[u8]>) {
+		let (ptr, len) = data.map(|d| (d.as_ptr(), d.len()))
+			.unwrap_or((std::ptr::null(), 0));
+
+		unsafe { raw::upload_image_data(w, h, format, ty, ptr, len) }
+	}
+
+	fn tex_parameter(&mut self, param: TextureParam, value: TextureParamValue) {
+		unsafe { raw::tex_parameter(param, value) }
+	}
+
+	fn create_framebuffer(&mut self) -> FramebufferID { unsafe { raw::create_framebuffer() } }
+	fn delete_framebuffer(&mut self, id: FramebufferID) { unsafe { raw::delete_framebuffer(id) } }
+	fn bind_framebuffer(&mut self, id: FramebufferID) { unsafe { raw::bind_framebuffer(id) } }
+	fn get_bound_framebuffer(&mut self) -> FramebufferID { unsafe { raw::get_bound_framebuffer() } }
+	fn framebuffer_texture_2d(&mut self, id: TextureID) { unsafe { raw::framebuffer_texture_2d(id) } }
+	fn framebuffer_renderbuffer(&mut self, id: RenderbufferID) { unsafe { raw::framebuffer_renderbuffer(id) } }
+
+	fn read_pixels(&mut self, x: i32, y: i32, w: i32, h: i32, data: &mut [u8]) {
+		unsafe { raw::read_pixels(x, y, w, h, data.as_mut_ptr(), data.len()) }
+	}
+
+	fn create_renderbuffer(&mut self) -> RenderbufferID { unsafe { raw::create_renderbuffer() } }
+	fn delete_renderbuffer(&mut self, id: RenderbufferID) { unsafe { raw::delete_renderbuffer(id) } }
+	fn bind_renderbuffer(&mut self, id: RenderbufferID) { unsafe { raw::bind_renderbuffer(id) } }
+	fn renderbuffer_depth_storage(&mut self, w: i32, h: i32) { unsafe { raw::renderbuffer_depth_storage(w, h) } }
+
+	fn create_shader_program(&mut self) -> ProgramID { unsafe { raw::create_shader_program() } }
+	fn create_shader(&mut self, ty: ShaderType, src: &str) -> ShaderID { unsafe { raw::create_shader(ty, src.into()) } }
+
+	fn bind_attrib_location(&mut self, program: ProgramID, name: &str, idx: u32) {
+		unsafe { raw::bind_attrib_location(program, name.into(), idx) }
+	}
+
+	fn link_program(&mut self, program: ProgramID, vert: ShaderID, frag: ShaderID) {
+		unsafe { raw::link_program(program, vert, frag) }
+	}
+
+	fn use_program(&mut self, program: ProgramID) { unsafe { raw::use_program(program) } }
+
+	fn stencil_func(&mut self, condition: StencilCondition, reference: u8, mask: u8) {
+		unsafe { raw::stencil_func(condition, reference, mask) }
+	}
+
+	fn stencil_op(&mut self, stencil_fail: StencilOp, depth_fail: StencilOp, pass: StencilOp) {
+		unsafe { raw::stencil_op(stencil_fail, depth_fail, pass) }
+	}
+
+	fn color_mask(&mut self, r: bool, g: bool, b: bool, a: bool) { unsafe { raw::color_mask(r, g, b, a) } }
+	fn depth_mask(&mut self, enabled: bool) { unsafe { raw::depth_mask(enabled) } }
+	fn stencil_mask(&mut self, bits: u8) { unsafe { raw::stencil_mask(bits) } }
+
+	fn set_uniform_int(&mut self, program: ProgramID, name: &str, i: u32) {
+		unsafe { raw::set_uniform_int_raw(program, name.into(), i) }
+	}
+
+	fn set_uniform_f32(&mut self, program: ProgramID, name: &str, f: f32) {
+		unsafe { raw::set_uniform_f32_raw(program, name.into(), f) }
+	}
+
+	fn set_uniform_vec4(&mut self, program: ProgramID, name: &str, v: Vec4) {
+		unsafe { raw::set_uniform_vec4_raw(program, name.into(), v.x, v.y, v.z, v.w) }
+	}
+
+	fn set_uniform_mat4(&mut self, program: ProgramID, name: &str, mat: &Mat4) {
+		unsafe { raw::set_uniform_mat4_raw(program, name.into(), mat as *const Mat4) }
+	}
+}
@@ -1,8 +1,29 @@
-
-
+#[cfg(target_arch = "wasm32")]
 extern {
 	pub fn init_input_listeners(passive: bool);
 
 	pub fn request_pointer_lock();
 	pub fn exit_pointer_lock();
-}
\ No newline at end of file
+
+	pub fn set_text_input_enabled(enabled: bool);
+
+	/// Fills `buttons` with 0 or 1 per standard mapping button, and `axes` with LeftX, LeftY, RightX, RightY,
+	/// then the trigger values - sticks positive up. Returns whether a pad is connected at `index`
+	pub fn poll_gamepad(index: u32, buttons: *mut u8, num_buttons: usize, axes: *mut f32, num_axes: usize) -> bool;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::native::*;
+
+// There's no page to listen to outside of a browser - input is fed through the exports directly
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+	pub unsafe fn init_input_listeners(_passive: bool) {}
+
+	pub unsafe fn request_pointer_lock() {}
+	pub unsafe fn exit_pointer_lock() {}
+
+	pub unsafe fn set_text_input_enabled(_enabled: bool) {}
+
+	pub unsafe fn poll_gamepad(_index: u32, _buttons: *mut u8, _num_buttons: usize, _axes: *mut f32, _num_axes: usize) -> bool { false }
+}
@@ -2,10 +2,20 @@ pub mod gl;
 pub mod util;
 pub mod input;
 
+#[cfg(target_arch = "wasm32")]
+pub mod storage;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct RawStr (pub *const u8, pub usize);
 
+impl RawStr {
+	pub unsafe fn as_str<'a>(&self) -> &'a str {
+		let RawStr(ptr, len) = *self;
+		std::str::from_utf8_unchecked(std::slice::from_raw_parts(ptr, len))
+	}
+}
+
 impl<'a> Into<RawStr> for &'a str {
 	fn into(self) -> RawStr {
 		RawStr(self.as_ptr(), self.len())
@@ -0,0 +1,11 @@
+use crate::imports::RawStr;
+use crate::string_arena::JSString;
+
+// Only used by storage::LocalStorage - there's no page to store anything in natively
+extern {
+	/// Returns a null JSString if `key` isn't set
+	pub fn storage_get(key: RawStr) -> JSString;
+	pub fn storage_set(key: RawStr, value: RawStr) -> bool;
+	pub fn storage_remove(key: RawStr);
+	pub fn storage_clear();
+}
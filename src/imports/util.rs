@@ -1,4 +1,4 @@
-
+#[cfg(target_arch = "wasm32")]
 extern {
 	pub fn console_log_raw(_: *const u8, _: usize);
 	pub fn console_warn_raw(_: *const u8, _: usize);
@@ -8,4 +8,62 @@ extern {
 	pub fn canvas_height() -> i32;
 
 	pub fn math_random() -> f32;
+	pub fn performance_now() -> f64;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::native::*;
+
+// Stand-ins so the engine can be built and driven outside of a browser
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+	use std::cell::Cell;
+	use std::time::Instant;
+	use crate::imports::RawStr;
+
+	thread_local! {
+		static CANVAS_SIZE: Cell<(i32, i32)> = Cell::new((0, 0));
+		static RANDOM_STATE: Cell<u32> = Cell::new(0x9E37_79B9);
+		static START_TIME: Instant = Instant::now();
+	}
+
+	pub fn set_canvas_size(w: i32, h: i32) {
+		CANVAS_SIZE.with(|s| s.set((w, h)));
+	}
+
+	pub fn seed_math_random(seed: u32) {
+		RANDOM_STATE.with(|s| s.set(seed.max(1)));
+	}
+
+	pub unsafe extern fn console_log_raw(ptr: *const u8, len: usize) {
+		println!("{}", RawStr(ptr, len).as_str());
+	}
+
+	pub unsafe extern fn console_warn_raw(ptr: *const u8, len: usize) {
+		eprintln!("{}", RawStr(ptr, len).as_str());
+	}
+
+	pub unsafe extern fn console_error_raw(ptr: *const u8, len: usize) {
+		eprintln!("{}", RawStr(ptr, len).as_str());
+	}
+
+	pub unsafe fn canvas_width() -> i32 { CANVAS_SIZE.with(|s| s.get().0) }
+	pub unsafe fn canvas_height() -> i32 { CANVAS_SIZE.with(|s| s.get().1) }
+
+	// xorshift32 - deterministic so native runs are reproducible
+	pub unsafe fn math_random() -> f32 {
+		RANDOM_STATE.with(|s| {
+			let mut x = s.get();
+			x ^= x << 13;
+			x ^= x >> 17;
+			x ^= x << 5;
+			s.set(x);
+
+			(x >> 8) as f32 / (1u32 << 24) as f32
+		})
+	}
+
+	pub unsafe fn performance_now() -> f64 {
+		START_TIME.with(|start| start.elapsed().as_secs_f64() * 1000.0)
+	}
 }
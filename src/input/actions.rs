@@ -0,0 +1,354 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::prelude::*;
+use crate::input::core::*;
+use crate::input::gesture_tracker::GestureTracker;
+use crate::input::gamepad::*;
+use crate::input::virtual_controls::VirtualControls;
+
+// Digital bindings are worth 1 while held. An action is down while the magnitude of its value is at least this
+const PRESS_THRESHOLD: f32 = 0.5;
+
+
+/// Gestures from the primary pointer, as tracked by `GestureTracker`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Gesture {
+	Tap, Hold, Drag,
+
+	/// Pointer movement this frame while dragging, in the same units as `GestureTracker::frame_delta`
+	DragX, DragY,
+}
+
+
+/// A physical input that can drive an action
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Binding {
+	Key(KeyCode),
+	Mouse(MouseButton),
+	Gesture(Gesture),
+
+	/// Pressed on any connected pad
+	GamepadButton(GamepadButton),
+	/// From whichever connected pad is pushing it furthest, with dead zones applied
+	GamepadAxis(GamepadAxis),
+
+	/// The on-screen stick, positive right and up
+	VirtualStickX,
+	VirtualStickY,
+	/// An on-screen button, by its index in the client's `VirtualControlsLayout`
+	VirtualButton(usize),
+}
+
+impl Binding {
+	fn value(&self, input: &InputContext, gestures: &GestureTracker, controls: &VirtualControls) -> f32 {
+		let digital = |down: bool| if down { 1.0 } else { 0.0 };
+
+		match *self {
+			Binding::Key(key) => digital(input.button_state(key).is_down()),
+			Binding::Mouse(mb) => digital(input.button_state(mb).is_down()),
+
+			Binding::Gesture(Gesture::Tap) => digital(gestures.tap()),
+			Binding::Gesture(Gesture::Hold) => digital(gestures.holding()),
+			Binding::Gesture(Gesture::Drag) => digital(gestures.dragging()),
+
+			Binding::Gesture(Gesture::DragX) => if gestures.dragging() { gestures.frame_delta().x } else { 0.0 },
+			Binding::Gesture(Gesture::DragY) => if gestures.dragging() { gestures.frame_delta().y } else { 0.0 },
+
+			Binding::GamepadButton(button) => digital(input.gamepad_button_down(button)),
+			Binding::GamepadAxis(axis) => input.gamepad_axis(axis),
+
+			Binding::VirtualStickX => controls.stick().x,
+			Binding::VirtualStickY => controls.stick().y,
+			Binding::VirtualButton(button) => digital(controls.button_state(button).is_down()),
+		}
+	}
+}
+
+
+/// Named actions, and the inputs bound to each of them.
+/// Every binding has a scale, which is how axes are built - e.g. "move_forward" bound to W with 1 and S with -1.
+///
+/// Serializes to one line per action, like `move_forward = Key:KeyW, Key:KeyS*-1, GamepadAxis:LeftY`, so a set of bindings can be
+/// kept with `storage::set` and `storage::get`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bindings {
+	actions: Vec<(String, Vec<(Binding, f32)>)>,
+}
+
+impl Bindings {
+	pub fn new() -> Self { Bindings::default() }
+
+	pub fn bind(&mut self, action: &str, binding: Binding) -> &mut Self {
+		self.bind_axis(action, binding, 1.0)
+	}
+
+	/// Binding the same input to an action twice replaces its scale
+	pub fn bind_axis(&mut self, action: &str, binding: Binding, scale: f32) -> &mut Self {
+		let bindings = self.action_mut(action);
+
+		match bindings.iter_mut().find(|(b, _)| *b == binding) {
+			Some(existing) => existing.1 = scale,
+			None => bindings.push((binding, scale)),
+		}
+
+		self
+	}
+
+	pub fn unbind(&mut self, action: &str, binding: Binding) {
+		if let Some((_, bindings)) = self.actions.iter_mut().find(|(name, _)| name == action) {
+			bindings.retain(|(b, _)| *b != binding);
+		}
+	}
+
+	/// Swaps `old` for `new`, keeping its scale. Binds `new` with a scale of 1 if `old` wasn't bound
+	pub fn rebind(&mut self, action: &str, old: Binding, new: Binding) {
+		let bindings = self.action_mut(action);
+		bindings.retain(|(b, _)| *b != new);
+
+		match bindings.iter_mut().find(|(b, _)| *b == old) {
+			Some(existing) => existing.0 = new,
+			None => bindings.push((new, 1.0)),
+		}
+	}
+
+	/// Removes every binding for `action`, but keeps the action itself
+	pub fn clear(&mut self, action: &str) {
+		self.action_mut(action).clear();
+	}
+
+	pub fn actions(&self) -> impl Iterator<Item=&str> {
+		self.actions.iter().map(|(name, _)| name.as_str())
+	}
+
+	pub fn bindings(&self, action: &str) -> &[(Binding, f32)] {
+		self.actions.iter()
+			.find(|(name, _)| name == action)
+			.map_or(&[], |(_, bindings)| bindings.as_slice())
+	}
+
+	fn action_mut(&mut self, action: &str) -> &mut Vec<(Binding, f32)> {
+		let idx = match self.actions.iter().position(|(name, _)| name == action) {
+			Some(idx) => idx,
+			None => {
+				self.actions.push((action.to_owned(), Vec::new()));
+				self.actions.len() - 1
+			}
+		};
+
+		&mut self.actions[idx].1
+	}
+}
+
+
+#[derive(Clone, Debug)]
+struct ActionState {
+	name: String,
+	value: f32,
+	state: ButtonState,
+}
+
+/// The state of every action in the client's `Bindings`, updated once per animation frame.
+/// Edges behave like `ButtonState`'s. Unknown actions are never down, and have a value of 0
+#[derive(Clone, Debug, Default)]
+pub struct Actions {
+	states: Vec<ActionState>,
+}
+
+impl Actions {
+	pub(crate) fn new() -> Self { Actions::default() }
+
+	pub(crate) fn update(&mut self, bindings: Option<&Bindings>, input: &InputContext, gestures: &GestureTracker, controls: &VirtualControls) {
+		let bindings = match bindings {
+			Some(bindings) => bindings,
+			None => {
+				self.states.clear();
+				return;
+			}
+		};
+
+		let prev_states = std::mem::take(&mut self.states);
+
+		for (name, action_bindings) in bindings.actions.iter() {
+			let value: f32 = action_bindings.iter()
+				.map(|(binding, scale)| binding.value(input, gestures, controls) * scale)
+				.sum();
+
+			let value = value.max(-1.0).min(1.0);
+			let was_down = prev_states.iter()
+				.find(|s| s.name == *name)
+				.map_or(false, |s| s.state.is_down());
+
+			let state = match (was_down, value.abs() >= PRESS_THRESHOLD) {
+				(false, true) => ButtonState::DownRecent,
+				(true, true) => ButtonState::Down,
+				(true, false) => ButtonState::UpRecent,
+				(false, false) => ButtonState::Up,
+			};
+
+			self.states.push(ActionState { name: name.clone(), value, state });
+		}
+	}
+
+	pub fn state(&self, action: &str) -> ButtonState {
+		self.find(action).map_or(ButtonState::Up, |s| s.state)
+	}
+
+	pub fn down(&self, action: &str) -> bool { self.state(action).is_down() }
+	pub fn pressed(&self, action: &str) -> bool { self.state(action).is_pressed() }
+	pub fn released(&self, action: &str) -> bool { self.state(action).is_released() }
+
+	/// Sum of every binding's value times its scale, clamped to [-1, 1]
+	pub fn axis(&self, action: &str) -> f32 {
+		self.find(action).map_or(0.0, |s| s.value)
+	}
+
+	fn find(&self, action: &str) -> Option<&ActionState> {
+		self.states.iter().find(|s| s.name == action)
+	}
+}
+
+
+
+impl fmt::Display for Gesture {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Debug::fmt(self, f)
+	}
+}
+
+impl FromStr for Gesture {
+	type Err = failure::Error;
+
+	fn from_str(s: &str) -> EngineResult<Gesture> {
+		match s {
+			"Tap" => Ok(Gesture::Tap),
+			"Hold" => Ok(Gesture::Hold),
+			"Drag" => Ok(Gesture::Drag),
+			"DragX" => Ok(Gesture::DragX),
+			"DragY" => Ok(Gesture::DragY),
+			_ => bail!("Unknown gesture '{}'", s),
+		}
+	}
+}
+
+impl fmt::Display for Binding {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			Binding::Key(key) => write!(f, "Key:{}", key.js_code()),
+			Binding::Mouse(mb) => write!(f, "Mouse:{:?}", mb),
+			Binding::Gesture(gesture) => write!(f, "Gesture:{}", gesture),
+			Binding::GamepadButton(button) => write!(f, "Gamepad:{:?}", button),
+			Binding::GamepadAxis(axis) => write!(f, "GamepadAxis:{:?}", axis),
+			Binding::VirtualStickX => write!(f, "VirtualStick:X"),
+			Binding::VirtualStickY => write!(f, "VirtualStick:Y"),
+			Binding::VirtualButton(button) => write!(f, "VirtualButton:{}", button),
+		}
+	}
+}
+
+impl FromStr for Binding {
+	type Err = failure::Error;
+
+	fn from_str(s: &str) -> EngineResult<Binding> {
+		let mut parts = s.splitn(2, ':');
+		let (kind, name) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+		match kind {
+			"Key" => KeyCode::from_js_code(name)
+				.map(Binding::Key)
+				.ok_or_else(|| format_err!("Unknown key '{}'", name)),
+
+			"Mouse" => match name {
+				"Left" => Ok(Binding::Mouse(MouseButton::Left)),
+				"Middle" => Ok(Binding::Mouse(MouseButton::Middle)),
+				"Right" => Ok(Binding::Mouse(MouseButton::Right)),
+				_ => bail!("Unknown mouse button '{}'", name),
+			},
+
+			"Gesture" => Ok(Binding::Gesture(name.parse()?)),
+
+			"Gamepad" => (0..GamepadButton::Count as usize)
+				.filter_map(GamepadButton::from_index)
+				.find(|button| format!("{:?}", button) == name)
+				.map(Binding::GamepadButton)
+				.ok_or_else(|| format_err!("Unknown gamepad button '{}'", name)),
+
+			"GamepadAxis" => (0..GamepadAxis::Count as usize)
+				.filter_map(GamepadAxis::from_index)
+				.find(|axis| format!("{:?}", axis) == name)
+				.map(Binding::GamepadAxis)
+				.ok_or_else(|| format_err!("Unknown gamepad axis '{}'", name)),
+
+			"VirtualStick" => match name {
+				"X" => Ok(Binding::VirtualStickX),
+				"Y" => Ok(Binding::VirtualStickY),
+				_ => bail!("Unknown virtual stick axis '{}'", name),
+			},
+
+			"VirtualButton" => name.parse()
+				.map(Binding::VirtualButton)
+				.map_err(|_| format_err!("Invalid virtual button '{}'", name)),
+
+			_ => bail!("Unknown binding '{}'", s),
+		}
+	}
+}
+
+impl fmt::Display for Bindings {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (name, bindings) in self.actions.iter() {
+			write!(f, "{} =", name)?;
+
+			for (i, (binding, scale)) in bindings.iter().enumerate() {
+				let separator = if i == 0 { " " } else { ", " };
+
+				if *scale == 1.0 {
+					write!(f, "{}{}", separator, binding)?;
+				} else {
+					write!(f, "{}{}*{}", separator, binding, scale)?;
+				}
+			}
+
+			writeln!(f)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl FromStr for Bindings {
+	type Err = failure::Error;
+
+	fn from_str(s: &str) -> EngineResult<Bindings> {
+		let mut result = Bindings::new();
+
+		for (line_no, line) in s.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() { continue }
+
+			let mut parts = line.splitn(2, '=');
+			let action = parts.next().unwrap_or("").trim();
+			let bindings = parts.next()
+				.ok_or_else(|| format_err!("Expected '=' on line {} of bindings", line_no + 1))?;
+
+			ensure!(!action.is_empty(), "Missing action name on line {} of bindings", line_no + 1);
+
+			result.action_mut(action);
+
+			for binding in bindings.split(',').map(str::trim).filter(|b| !b.is_empty()) {
+				let mut parts = binding.splitn(2, '*');
+				let binding_str = parts.next().unwrap_or("").trim();
+
+				let scale = match parts.next() {
+					Some(scale) => scale.trim().parse::<f32>()
+						.map_err(|_| format_err!("Invalid scale '{}' on line {} of bindings", scale, line_no + 1))?,
+					None => 1.0,
+				};
+
+				result.bind_axis(action, binding_str.parse()?, scale);
+			}
+		}
+
+		Ok(result)
+	}
+}
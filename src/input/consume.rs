@@ -0,0 +1,47 @@
+use crate::input::core::{InputContext, KeyCode};
+
+/// When an input event should be kept from the page - i.e. whether its default action (scrolling, context menus,
+/// browser shortcuts) is prevented. The engine sees the event either way.
+/// Toys filling the page want most things consumed, toys embedded in a page that scrolls want as little as possible
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Consume {
+	Never,
+	Always,
+	/// While the canvas has keyboard focus. It takes focus when it's pressed, and can be tabbed to
+	WhileFocused,
+	WhilePointerLocked,
+	/// Pointer lock can take focus away from the canvas, so clients that capture input want this rather than `WhileFocused`
+	WhileFocusedOrPointerLocked,
+}
+
+impl Consume {
+	/// What's consumed for clients that don't say otherwise - anything that would scroll the page, and F1
+	pub fn default_for_key(key: KeyCode) -> Consume {
+		match key {
+			KeyCode::Left
+			| KeyCode::Right
+			| KeyCode::Up
+			| KeyCode::Down
+			| KeyCode::Space
+			| KeyCode::PageUp
+			| KeyCode::PageDown
+			| KeyCode::Home
+			| KeyCode::End => Consume::Always,
+
+			// No help for you
+			KeyCode::F1 => Consume::Always,
+
+			_ => Consume::Never
+		}
+	}
+
+	pub fn should_consume(self, input: &InputContext) -> bool {
+		match self {
+			Consume::Never => false,
+			Consume::Always => true,
+			Consume::WhileFocused => input.is_canvas_focused(),
+			Consume::WhilePointerLocked => input.is_pointer_locked(),
+			Consume::WhileFocusedOrPointerLocked => input.is_canvas_focused() || input.is_pointer_locked(),
+		}
+	}
+}
@@ -1,27 +1,114 @@
-use common::math::Vec2i;
-use crate::string_arena::JSString;
+use common::math::{Vec2, Vec2i};
 use crate::imports::input;
+use crate::input::recording::{InputEvent, TimedInputEvent};
+use crate::input::gamepad::*;
+use crate::input::pointer::*;
 
 
+/// Every key named by `KeyboardEvent.code`. Keys with no code of their own, like "Unidentified", are dropped
 #[repr(usize)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum KeyCode {
 	Left, Right, Up, Down,
-	W, A, S, D,
-	Q, E, F,
 
-	Space, Enter, Escape,
-	Shift, Ctrl, Alt,
+	A, B, C, D, E, F, G, H, I, J, K, L, M,
+	N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+
+	Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+
+	F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+	F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+
+	Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+
+	NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadDecimal, NumpadEnter, NumpadEqual, NumLock,
+	NumpadComma, NumpadBackspace, NumpadClear, NumpadClearEntry, NumpadHash, NumpadStar, NumpadParenLeft, NumpadParenRight,
+	NumpadMemoryAdd, NumpadMemoryClear, NumpadMemoryRecall, NumpadMemoryStore, NumpadMemorySubtract,
 
-	F1,
+	Backquote, Minus, Equal, BracketLeft, BracketRight, Backslash, Semicolon, Quote, Comma, Period, Slash,
+	IntlBackslash, IntlRo, IntlYen,
+
+	Convert, NonConvert, KanaMode, Hiragana, Katakana,
+	Lang1, Lang2, Lang3, Lang4, Lang5,
+
+	Space, Enter, Escape, Tab, Backspace, Delete, Insert,
+	Home, End, PageUp, PageDown, CapsLock,
+	ContextMenu, PrintScreen, ScrollLock, Pause, Help, Fn, FnLock,
+
+	MediaPlayPause, MediaStop, MediaTrackNext, MediaTrackPrevious, MediaSelect,
+	AudioVolumeDown, AudioVolumeMute, AudioVolumeUp,
+	BrowserBack, BrowserForward, BrowserRefresh, BrowserStop, BrowserSearch, BrowserFavorites, BrowserHome,
+	LaunchApp1, LaunchApp2, LaunchMail, Eject, Power, Sleep, WakeUp,
+
+	// Only found on old or unusual keyboards
+	Again, Copy, Cut, Find, Open, Paste, Props, Select, Undo,
+	Abort, Resume, Suspend, Hyper, Super, Turbo,
+
+	ShiftLeft, ShiftRight, CtrlLeft, CtrlRight, AltLeft, AltRight, MetaLeft, MetaRight,
 
 	Count
 }
 
+// KeyboardEvent.code for each KeyCode, in the same order
+const JS_CODES: [&str; KeyCode::Count as usize] = [
+	"ArrowLeft", "ArrowRight", "ArrowUp", "ArrowDown",
+
+	"KeyA", "KeyB", "KeyC", "KeyD", "KeyE", "KeyF", "KeyG", "KeyH",
+	"KeyI", "KeyJ", "KeyK", "KeyL", "KeyM", "KeyN", "KeyO", "KeyP",
+	"KeyQ", "KeyR", "KeyS", "KeyT", "KeyU", "KeyV", "KeyW", "KeyX",
+	"KeyY", "KeyZ",
+
+	"Digit0", "Digit1", "Digit2", "Digit3", "Digit4", "Digit5", "Digit6", "Digit7",
+	"Digit8", "Digit9",
+
+	"F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8",
+	"F9", "F10", "F11", "F12",
+	"F13", "F14", "F15", "F16", "F17", "F18", "F19", "F20",
+	"F21", "F22", "F23", "F24",
+
+	"Numpad0", "Numpad1", "Numpad2", "Numpad3", "Numpad4", "Numpad5", "Numpad6", "Numpad7",
+	"Numpad8", "Numpad9",
+
+	"NumpadAdd", "NumpadSubtract", "NumpadMultiply", "NumpadDivide", "NumpadDecimal", "NumpadEnter", "NumpadEqual", "NumLock",
+	"NumpadComma", "NumpadBackspace", "NumpadClear", "NumpadClearEntry", "NumpadHash", "NumpadStar", "NumpadParenLeft", "NumpadParenRight",
+	"NumpadMemoryAdd", "NumpadMemoryClear", "NumpadMemoryRecall", "NumpadMemoryStore", "NumpadMemorySubtract",
+
+	"Backquote", "Minus", "Equal", "BracketLeft", "BracketRight", "Backslash", "Semicolon", "Quote",
+	"Comma", "Period", "Slash", "IntlBackslash", "IntlRo", "IntlYen",
+
+	"Convert", "NonConvert", "KanaMode", "Hiragana", "Katakana",
+	"Lang1", "Lang2", "Lang3", "Lang4", "Lang5",
+
+	"Space", "Enter", "Escape", "Tab", "Backspace", "Delete", "Insert",
+	"Home", "End", "PageUp", "PageDown", "CapsLock",
+	"ContextMenu", "PrintScreen", "ScrollLock", "Pause", "Help", "Fn", "FnLock",
+
+	"MediaPlayPause", "MediaStop", "MediaTrackNext", "MediaTrackPrevious", "MediaSelect",
+	"AudioVolumeDown", "AudioVolumeMute", "AudioVolumeUp",
+	"BrowserBack", "BrowserForward", "BrowserRefresh", "BrowserStop", "BrowserSearch", "BrowserFavorites", "BrowserHome",
+	"LaunchApp1", "LaunchApp2", "LaunchMail", "Eject", "Power", "Sleep", "WakeUp",
+
+	"Again", "Copy", "Cut", "Find", "Open", "Paste", "Props", "Select", "Undo",
+	"Abort", "Resume", "Suspend", "Hyper", "Super", "Turbo",
+
+	"ShiftLeft", "ShiftRight", "ControlLeft", "ControlRight", "AltLeft", "AltRight", "MetaLeft", "MetaRight",
+];
+
 #[repr(usize)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum MouseButton { Left, Middle, Right,  Count }
 
+impl MouseButton {
+	pub fn from_index(idx: usize) -> Option<MouseButton> {
+		match idx {
+			0 => Some(MouseButton::Left),
+			1 => Some(MouseButton::Middle),
+			2 => Some(MouseButton::Right),
+			_ => None
+		}
+	}
+}
+
 
 #[derive(Copy, Clone, Debug)]
 pub enum Button {
@@ -44,30 +131,131 @@ pub enum ButtonState {
 }
 
 
-#[derive(Copy, Clone)]
-pub struct TouchInstance {
-	pub(crate) id: i32,
-	pub(crate) index: u32,
-	pub(crate) pos: Vec2i,
-	pub(crate) frame_delta: Vec2i,
-	pub(crate) state: ButtonState,
+// A button pressed since the last frame stays pressed for the frame even if it's released again before then,
+// with the release held back to the following frame - otherwise a quick tap would go straight from Up to UpRecent
+// and `is_pressed` would never see it
+#[derive(Copy, Clone, Debug)]
+struct TrackedButton {
+	state: ButtonState,
+	release_pending: bool,
+	presses: u32,
+}
+
+impl TrackedButton {
+	const UP: TrackedButton = TrackedButton { state: ButtonState::Up, release_pending: false, presses: 0 };
+
+	fn press(&mut self) {
+		if self.state.is_up() {
+			self.state = ButtonState::DownRecent;
+			self.presses += 1;
+		} else if self.release_pending {
+			self.release_pending = false;
+			self.presses += 1;
+		}
+	}
+
+	fn release(&mut self) {
+		match self.state {
+			ButtonState::DownRecent => self.release_pending = true,
+			ButtonState::Down => self.state = ButtonState::UpRecent,
+			_ => {}
+		}
+	}
+
+	fn clear_frame_state(&mut self) {
+		self.state = if self.release_pending { ButtonState::UpRecent } else { self.state.recent_flag_cleared() };
+		self.release_pending = false;
+		self.presses = 0;
+	}
+}
+
+
+/// Whether either of each pair of modifier keys is held
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+	pub shift: bool,
+	pub ctrl: bool,
+	pub alt: bool,
+	pub meta: bool,
+}
+
+
+/// Units of a wheel event's delta, as in `WheelEvent.deltaMode`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WheelDeltaMode {
+	Pixel, Line, Page,
+}
+
+impl WheelDeltaMode {
+	// Browsers don't expose the line height they scroll by, but this is close to all of them
+	const LINE_HEIGHT: f32 = 16.0;
+
+	pub fn from_js(mode: u32) -> WheelDeltaMode {
+		match mode {
+			1 => WheelDeltaMode::Line,
+			2 => WheelDeltaMode::Page,
+			_ => WheelDeltaMode::Pixel,
+		}
+	}
+
+	/// `page_height` is the height of the canvas in CSS pixels
+	pub fn pixels_per_unit(self, page_height: f32) -> f32 {
+		match self {
+			WheelDeltaMode::Pixel => 1.0,
+			WheelDeltaMode::Line => WheelDeltaMode::LINE_HEIGHT,
+			WheelDeltaMode::Page => page_height,
+		}
+	}
+}
+
+
+/// Typed text, as opposed to key presses - with the keyboard layout, dead keys and any IME already applied.
+/// Only produced while text input is enabled with `InputContext::enable_text_input`
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextInput {
+	/// Characters to insert at the cursor - typed, pasted or the result of an IME composition. Enter commits "\n"
+	Commit(String),
+
+	/// The text an IME is in the middle of composing, to be shown at the cursor but not yet inserted.
+	/// Replaces any previous composition, and is empty once composition ends
+	Composition(String),
+
+	/// Delete the character before the cursor
+	Backspace,
+	/// Delete the character after the cursor
+	Delete,
 }
 
 
 pub struct InputContext {
-	key_states: [ButtonState; KeyCode::Count as usize],
-	mb_states: [ButtonState; MouseButton::Count as usize],
-	pub(crate) touch_states: Vec<TouchInstance>,
+	key_states: [TrackedButton; KeyCode::Count as usize],
+	mb_states: [TrackedButton; MouseButton::Count as usize],
+	pub(crate) pointers: Vec<Pointer>,
+
+	frame_events: Vec<TimedInputEvent>,
 
 	// TODO: probably improve this
 	pub(crate) mouse_pos: Vec2i,
 	pub(crate) mouse_delta: Vec2i,
+	wheel_delta: Vec2,
+
+	pub(crate) pixel_ratio: f32,
 
 	is_pointer_locked: bool,
 	should_pointer_lock: bool,
 	pub(crate) pointer_lock_allowed: bool,
 
-	pub(crate) touch_mode: bool,
+	pub(crate) canvas_focused: bool,
+
+	/// Whichever kind of pointer was last pressed. Its primary pointer drives gestures
+	pub(crate) active_pointer_type: PointerType,
+
+	text_input_enabled: bool,
+	text_input: Vec<TextInput>,
+	composition: String,
+
+	pub(crate) gamepads: [Gamepad; MAX_GAMEPADS],
+	gamepad_connection_changes: Vec<(usize, bool)>,
 }
 
 
@@ -78,50 +266,71 @@ impl InputContext {
 		}
 
 		InputContext {
-			key_states: [ButtonState::Up; KeyCode::Count as usize],
-			mb_states: [ButtonState::Up; MouseButton::Count as usize],
-			touch_states: Vec::new(),
+			key_states: [TrackedButton::UP; KeyCode::Count as usize],
+			mb_states: [TrackedButton::UP; MouseButton::Count as usize],
+			pointers: Vec::new(),
+
+			frame_events: Vec::new(),
 
 			mouse_pos: Vec2i::zero(),
 			mouse_delta: Vec2i::zero(),
+			wheel_delta: Vec2::zero(),
+
+			pixel_ratio: 1.0,
 
 			is_pointer_locked: false,
 			should_pointer_lock: false,
 			pointer_lock_allowed: true, // TODO: actually get this info
 
-			touch_mode: false,
+			canvas_focused: false,
+
+			active_pointer_type: PointerType::Mouse,
+
+			text_input_enabled: false,
+			text_input: Vec::new(),
+			composition: String::new(),
+
+			gamepads: [Gamepad::new(); MAX_GAMEPADS],
+			gamepad_connection_changes: Vec::new(),
 		}
 	}
 
 	pub fn clear_frame_state(&mut self) {
 		self.mouse_delta = Vec2i::zero();
+		self.wheel_delta = Vec2::zero();
 
-		for state in self.key_states.iter_mut() {
-			*state = state.recent_flag_cleared();
+		for button in self.key_states.iter_mut().chain(self.mb_states.iter_mut()) {
+			button.clear_frame_state();
 		}
 
-		for state in self.mb_states.iter_mut() {
-			*state = state.recent_flag_cleared();
+		for pointer in self.pointers.iter_mut() {
+			pointer.frame_delta = Vec2i::zero();
+			pointer.state = if pointer.release_pending { ButtonState::UpRecent } else { pointer.state.recent_flag_cleared() };
+			pointer.release_pending = false;
 		}
 
-		for state in self.touch_states.iter_mut() {
-			state.frame_delta = Vec2i::zero();
-			state.state = state.state.recent_flag_cleared();
+		self.pointers.retain(|p| p.in_range || p.state != ButtonState::Up);
+		self.text_input.clear();
+		self.frame_events.clear();
+
+		for pad in self.gamepads.iter_mut() {
+			pad.clear_frame_state();
 		}
 
-		self.touch_states.retain(|s| s.state != ButtonState::Up);
+		self.gamepad_connection_changes.clear();
 	}
 
 	pub fn reset_inputs(&mut self) {
-		for state in self.key_states.iter_mut() {
-			*state = ButtonState::Up;
+		for button in self.key_states.iter_mut().chain(self.mb_states.iter_mut()) {
+			*button = TrackedButton::UP;
 		}
 
-		for state in self.mb_states.iter_mut() {
-			*state = ButtonState::Up;
-		}
+		self.pointers.clear();
+		self.composition.clear();
 
-		self.touch_states.clear();
+		for pad in self.gamepads.iter_mut() {
+			pad.reset();
+		}
 	}
 
 	pub fn button_state<B: Into<Button>>(&self, b: B) -> ButtonState {
@@ -132,7 +341,7 @@ impl InputContext {
 					return ButtonState::Up;
 				}
 				
-				self.key_states[k as usize]
+				self.key_states[k as usize].state
 			}
 
 			Button::Mouse(m) => {
@@ -141,31 +350,142 @@ impl InputContext {
 					return ButtonState::Up;
 				}
 
-				self.mb_states[m as usize]
+				self.mb_states[m as usize].state
 			}
 		}
 	}
 
+	/// How many times `b` went down since the last frame. Can be more than one for fast enough taps, which
+	/// `button_state` coalesces into a single press
+	pub fn press_count<B: Into<Button>>(&self, b: B) -> u32 {
+		match b.into() {
+			Button::Key(k) => self.key_states.get(k as usize).map_or(0, |b| b.presses),
+			Button::Mouse(m) => self.mb_states.get(m as usize).map_or(0, |b| b.presses),
+		}
+	}
+
+	/// Every event applied this frame, in the order they arrived, stamped with the browser's event time in milliseconds
+	/// (the same clock as `Engine::update`'s timestamp). Replayed events are stamped with the frame they're replayed on
+	pub fn events(&self) -> &[TimedInputEvent] { &self.frame_events }
+
+	pub fn modifiers(&self) -> Modifiers {
+		let either = |a: KeyCode, b: KeyCode| self.key_states[a as usize].state.is_down() || self.key_states[b as usize].state.is_down();
+
+		Modifiers {
+			shift: either(KeyCode::ShiftLeft, KeyCode::ShiftRight),
+			ctrl: either(KeyCode::CtrlLeft, KeyCode::CtrlRight),
+			alt: either(KeyCode::AltLeft, KeyCode::AltRight),
+			meta: either(KeyCode::MetaLeft, KeyCode::MetaRight),
+		}
+	}
+
+	/// Starts or stops producing `TextInput`. While enabled, the page focuses a hidden text field so that
+	/// IMEs and on-screen keyboards work - key states are still tracked, but the page's own shortcuts may not be
+	pub fn enable_text_input(&mut self, e: bool) {
+		if e == self.text_input_enabled { return }
+
+		self.text_input_enabled = e;
+		self.composition.clear();
+
+		unsafe { input::set_text_input_enabled(e); }
+	}
+
+	pub fn is_text_input_enabled(&self) -> bool { self.text_input_enabled }
+
+	/// Text typed since the last frame, in order
+	pub fn text_input(&self) -> &[TextInput] { &self.text_input }
+
+	/// The IME composition in progress, if any
+	pub fn composition(&self) -> &str { &self.composition }
+
+	/// None if there's no pad connected at `index`
+	pub fn gamepad(&self, index: usize) -> Option<&Gamepad> {
+		self.gamepads.get(index).filter(|pad| pad.is_connected())
+	}
+
+	/// Every connected pad, and its index
+	pub fn gamepads(&self) -> impl Iterator<Item=(usize, &Gamepad)> + '_ {
+		self.gamepads.iter().enumerate()
+			.filter(|(_, pad)| pad.is_connected())
+	}
+
+	/// Whether `button` is down on any connected pad
+	pub fn gamepad_button_down(&self, button: GamepadButton) -> bool {
+		self.gamepads().any(|(_, pad)| pad.button_state(button).is_down())
+	}
+
+	/// Whichever connected pad is pushing `axis` the furthest, so that it doesn't matter which pad is picked up
+	pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+		self.gamepads()
+			.map(|(_, pad)| pad.axis(axis))
+			.fold(0.0, |a: f32, b: f32| if b.abs() > a.abs() { b } else { a })
+	}
+
+	/// Pads that were connected (true) or disconnected (false) since the last frame
+	pub fn gamepad_connection_changes(&self) -> &[(usize, bool)] { &self.gamepad_connection_changes }
+
+	pub fn set_gamepad_dead_zones(&mut self, stick: f32, trigger: f32) {
+		for pad in self.gamepads.iter_mut() {
+			pad.set_dead_zones(stick, trigger);
+		}
+	}
+
+	/// Framebuffer pixels per CSS pixel. Positions and deltas here are all in CSS pixels, as the browser reports them
+	pub fn pixel_ratio(&self) -> f32 { self.pixel_ratio }
+
+	/// Last known mouse position, in CSS pixels
+	pub fn mouse_position(&self) -> Vec2i { self.mouse_pos }
+
+	/// CSS pixels to framebuffer pixels, e.g. to compare a pointer position against something rendered
+	pub fn to_physical(&self, css: Vec2i) -> Vec2 { css.to_vec2() * self.pixel_ratio }
+
+	pub fn to_css(&self, physical: Vec2) -> Vec2 { physical / self.pixel_ratio }
+
+	/// Every mouse, pen and finger the page can currently see
+	pub fn pointers(&self) -> impl Iterator<Item=&Pointer> + '_ { self.pointers.iter() }
+
+	pub fn active_pointer_type(&self) -> PointerType { self.active_pointer_type }
+
+	/// Every finger on the screen, plus any lifted since the last frame
+	pub fn touches(&self) -> impl Iterator<Item=&Pointer> + '_ {
+		self.pointers.iter().filter(|p| p.pointer_type() == PointerType::Touch)
+	}
+
+	pub fn touch(&self, slot: u32) -> Option<&Pointer> {
+		self.touches().find(|p| p.slot() == slot)
+	}
+
+	/// The primary pointer of whichever kind was last pressed - so on a touchscreen laptop,
+	/// touching the screen hands control from the mouse to the first finger, and clicking hands it back.
+	/// For touches it's whichever finger is in the lowest slot, so lifting the first finger hands control to the next.
+	/// Fingers on the virtual controls are skipped
+	pub fn primary_pointer(&self) -> Option<&Pointer> {
+		if self.active_pointer_type == PointerType::Touch {
+			return self.touches()
+				.filter(|p| !p.is_claimed())
+				.min_by_key(|p| p.slot());
+		}
+
+		self.pointers.iter()
+			.find(|p| p.pointer_type() == self.active_pointer_type && p.is_primary())
+	}
+
 	// TODO: Use ButtonState here
 	pub fn primary_down(&self) -> bool {
-		if self.touch_mode {
-			self.touch_states.first()
-				.map(|s| s.index == 0)
-				.unwrap_or(false)
-		} else {
-			self.button_state(MouseButton::Left)
-				.is_down()
+		match self.active_pointer_type {
+			PointerType::Mouse => self.button_state(MouseButton::Left).is_down(),
+			_ => self.primary_pointer().map_or(false, |p| p.state.is_down()),
 		}
 	}
 
+	/// Scrolling since the last frame, in CSS pixels whatever units the browser reported it in.
+	/// Positive y is rolling the wheel towards you, which would scroll a page down
+	pub fn wheel_delta(&self) -> Vec2 { self.wheel_delta }
+
 	pub fn primary_delta(&self) -> Vec2i {
-		if self.touch_mode {
-			self.touch_states.first()
-				.filter(|s| s.index == 0)
-				.map(|s| s.frame_delta)
-				.unwrap_or(Vec2i::zero())
-		} else {
-			self.mouse_delta
+		match self.active_pointer_type {
+			PointerType::Mouse => self.mouse_delta,
+			_ => self.primary_pointer().map_or(Vec2i::zero(), |p| p.frame_delta),
 		}
 	}
 
@@ -181,73 +501,197 @@ impl InputContext {
 
 	pub fn is_pointer_locked(&self) -> bool { self.is_pointer_locked }
 
-	pub(crate) fn register_keydown(&mut self, code: KeyCode) {
-		let s = &mut self.key_states[code as usize];
-		if s.is_up() { *s = ButtonState::DownRecent }
+	/// Whether the canvas has keyboard focus. Unlike the engine's focus, which follows the whole window
+	pub fn is_canvas_focused(&self) -> bool { self.canvas_focused }
+
+	/// Pointer lock can only be requested while the browser is handling user input, so unlike everything else
+	/// this can't wait until the event is taken off the queue
+	pub(crate) fn handle_user_gesture(&self, event: &InputEvent) {
+		let is_gesture = match event {
+			InputEvent::KeyDown(code) | InputEvent::KeyUp(code) => !code.is_modifier(),
+			InputEvent::PointerDown(sample, _) | InputEvent::PointerUp(sample, _) => sample.pointer_type == PointerType::Mouse,
+			_ => false,
+		};
 
-		if !code.is_modifier() {
+		if is_gesture {
 			self.try_set_pointer_lock();
 		}
 	}
 
-	pub(crate) fn register_keyup(&mut self, code: KeyCode) {
-		let s = &mut self.key_states[code as usize];
-		if s.is_down() { *s = ButtonState::UpRecent }
-		
-		if !code.is_modifier() {
-			self.try_set_pointer_lock();
+	/// Applies `event`, and adds it to this frame's `events`
+	pub(crate) fn process_event(&mut self, timestamp: f64, event: InputEvent) {
+		self.frame_events.push(TimedInputEvent { timestamp, event: event.clone() });
+		self.handle_event(event);
+	}
+
+	pub fn handle_event(&mut self, event: InputEvent) {
+		match event {
+			InputEvent::KeyDown(code) => self.register_keydown(code),
+			InputEvent::KeyUp(code) => self.register_keyup(code),
+
+			InputEvent::PointerDown(sample, button) => self.register_pointer_down(sample, button),
+			InputEvent::PointerUp(sample, button) => self.register_pointer_up(sample, button),
+			InputEvent::PointerMove { sample, delta } => self.register_pointer_move(sample, delta),
+			InputEvent::PointerCancel(id) => self.register_pointer_cancel(id),
+
+			InputEvent::Wheel(delta) => self.register_wheel(delta),
+			InputEvent::Text(text) => self.register_text_input(text),
+
+			InputEvent::GamepadConnection(pad, connected) => self.register_gamepad_connection(pad, connected),
+			InputEvent::GamepadButton(pad, button, down) => self.register_gamepad_button(pad, button, down),
+			InputEvent::GamepadAxis(pad, axis, value) => self.register_gamepad_axis(pad, axis, value),
+
+			InputEvent::Reset => self.reset_inputs(),
+			InputEvent::PointerLockChange(enabled) => self.register_pointer_lock_change(enabled),
 		}
 	}
 
-	pub(crate) fn register_mousedown(&mut self, mb: MouseButton, x: i32, y: i32) {
-		self.touch_mode = false;
+	pub(crate) fn register_keydown(&mut self, code: KeyCode) {
+		self.key_states[code as usize].press();
+	}
+
+	pub(crate) fn register_keyup(&mut self, code: KeyCode) {
+		self.key_states[code as usize].release();
+	}
 
-		let s = &mut self.mb_states[mb as usize];
-		if s.is_up() { *s = ButtonState::DownRecent }
+	pub(crate) fn register_mousedown(&mut self, mb: MouseButton, x: i32, y: i32) {
+		self.mb_states[mb as usize].press();
 		self.mouse_pos = Vec2i::new(x, y);
-		self.try_set_pointer_lock();
 	}
 
 	pub(crate) fn register_mouseup(&mut self, mb: MouseButton, x: i32, y: i32) {
-		let s = &mut self.mb_states[mb as usize];
-		if s.is_down() { *s = ButtonState::UpRecent }
+		self.mb_states[mb as usize].release();
 		self.mouse_pos = Vec2i::new(x, y);
-		self.try_set_pointer_lock();
 	}
 
-	pub(crate) fn register_touchdown(&mut self, id: i32, x: i32, y: i32) {
-		self.touch_mode = true;
+	pub(crate) fn register_mousemove(&mut self, pos: Vec2i, delta: Vec2i) {
+		self.mouse_pos = pos;
+		self.mouse_delta += delta;
+	}
+
+	pub(crate) fn register_wheel(&mut self, delta: Vec2) {
+		self.wheel_delta += delta;
+	}
 
-		// TODO: check if id already exists and move to end
+	/// `button` is the mouse button pressed. Touches and pen tips come through as the left button
+	pub(crate) fn register_pointer_down(&mut self, sample: PointerSample, button: MouseButton) {
+		self.active_pointer_type = sample.pointer_type;
 
-		let index = self.touch_states.len() as u32;
-		self.touch_states.push(TouchInstance {
-			id,
-			pos: Vec2i::new(x, y),
-			frame_delta: Vec2i::zero(),
-			index,
-			state: ButtonState::DownRecent,
-		});
+		// A finger can't go down twice - if it has, its up was never seen, so start it over
+		if sample.pointer_type == PointerType::Touch {
+			self.pointers.retain(|p| p.id() != sample.id || !p.state.is_down() || p.release_pending);
+		}
+
+		let pointer = self.pointer_mut(sample);
+		if button == MouseButton::Left && (pointer.state.is_up() || pointer.release_pending) {
+			pointer.state = ButtonState::DownRecent;
+			pointer.release_pending = false;
+			pointer.start_pos = sample.pos;
+			pointer.cancelled = false;
+		}
+
+		if sample.pointer_type == PointerType::Mouse {
+			self.register_mousedown(button, sample.pos.x, sample.pos.y);
+		}
+	}
+
+	pub(crate) fn register_pointer_up(&mut self, sample: PointerSample, button: MouseButton) {
+		let pointer = self.pointer_mut(sample);
+		if button == MouseButton::Left {
+			pointer.release();
+		}
+
+		// Fingers only exist while they're touching
+		if sample.pointer_type == PointerType::Touch {
+			pointer.in_range = false;
+		}
+
+		if sample.pointer_type == PointerType::Mouse {
+			self.register_mouseup(button, sample.pos.x, sample.pos.y);
+		}
+	}
+
+	pub(crate) fn register_pointer_move(&mut self, sample: PointerSample, delta: Vec2i) {
+		// A finger that isn't down is one that's already been lifted
+		if sample.pointer_type == PointerType::Touch && !self.pointers.iter().any(|p| p.id() == sample.id) {
+			return;
+		}
+
+		self.pointer_mut(sample);
+
+		if sample.pointer_type == PointerType::Mouse {
+			self.register_mousemove(sample.pos, delta);
+		}
 	}
 
-	pub(crate) fn register_touchup(&mut self, id: i32, x: i32, y: i32) {
-		if let Some(state) = self.touch_states.iter_mut().find(|s| s.id == id) {
-			let new_pos = Vec2i::new(x, y);
-			let diff = new_pos - state.pos;
+	/// The browser has stopped tracking a pointer - a touch turned into a scroll, a pen left the canvas, etc
+	pub(crate) fn register_pointer_cancel(&mut self, id: i32) {
+		if let Some(pointer) = self.pointers.iter_mut().find(|p| p.id() == id) {
+			// Touches get a pointerleave straight after their pointerup, which can land in the same frame.
+			// A release that's already pending is a normal one, and shouldn't be turned into a cancel
+			if pointer.state.is_down() && !pointer.release_pending {
+				pointer.release();
+				pointer.cancelled = true;
+			}
 
-			state.pos = new_pos;
-			state.frame_delta += diff;
-			state.state = ButtonState::UpRecent;
+			pointer.in_range = false;
 		}
 	}
 
-	pub(crate) fn register_touchmove(&mut self, id: i32, x: i32, y: i32) {
-		if let Some(state) = self.touch_states.iter_mut().find(|s| s.id == id) {
-			let new_pos = Vec2i::new(x, y);
-			let diff = new_pos - state.pos;
+	fn pointer_mut(&mut self, sample: PointerSample) -> &mut Pointer {
+		let idx = match self.pointers.iter().position(|p| p.id() == sample.id) {
+			Some(idx) => idx,
+			None => {
+				let slot = match sample.pointer_type {
+					PointerType::Touch => (0..).find(|&slot| self.touch(slot).is_none()).unwrap(),
+					_ => 0,
+				};
+
+				self.pointers.push(Pointer::new(sample, slot));
+				self.pointers.len() - 1
+			}
+		};
+
+		let pointer = &mut self.pointers[idx];
+		pointer.update(sample);
+		pointer
+	}
+
+	pub(crate) fn register_text_input(&mut self, text: TextInput) {
+		// Anything in flight when text input was disabled is dropped
+		if !self.text_input_enabled { return }
 
-			state.pos = new_pos;
-			state.frame_delta += diff;
+		if let TextInput::Composition(composition) = &text {
+			self.composition.clone_from(composition);
+		}
+
+		self.text_input.push(text);
+	}
+
+	pub(crate) fn register_gamepad_connection(&mut self, index: usize, connected: bool) {
+		let pad = match self.gamepads.get_mut(index) {
+			Some(pad) => pad,
+			None => return,
+		};
+
+		if pad.connected == connected { return }
+
+		pad.connected = connected;
+		pad.reset();
+
+		self.gamepad_connection_changes.push((index, connected));
+	}
+
+	pub(crate) fn register_gamepad_button(&mut self, index: usize, button: GamepadButton, down: bool) {
+		if let Some(s) = self.gamepads.get_mut(index).and_then(|pad| pad.buttons.get_mut(button as usize)) {
+			if down && s.is_up() { *s = ButtonState::DownRecent }
+			if !down && s.is_down() { *s = ButtonState::UpRecent }
+		}
+	}
+
+	pub(crate) fn register_gamepad_axis(&mut self, index: usize, axis: GamepadAxis, value: f32) {
+		if let Some(v) = self.gamepads.get_mut(index).and_then(|pad| pad.axes.get_mut(axis as usize)) {
+			*v = value;
 		}
 	}
 
@@ -292,59 +736,54 @@ impl ButtonState {
 
 
 impl KeyCode {
-	pub fn from_js_code(s: JSString) -> Option<KeyCode> {
-		match s.as_str() {
-			"ArrowLeft" => Some(KeyCode::Left),
-			"ArrowRight" => Some(KeyCode::Right),
-			"ArrowUp" => Some(KeyCode::Up),
-			"ArrowDown" => Some(KeyCode::Down),
-
-			"KeyW" => Some(KeyCode::W),
-			"KeyA" => Some(KeyCode::A),
-			"KeyS" => Some(KeyCode::S),
-			"KeyD" => Some(KeyCode::D),
-
-			"KeyQ" => Some(KeyCode::Q),
-			"KeyE" => Some(KeyCode::E),
-			"KeyF" => Some(KeyCode::F),
-
-			"Enter" => Some(KeyCode::Enter),
-			"Space" => Some(KeyCode::Space),
-			"Escape" => Some(KeyCode::Escape),
-
-			"ControlLeft" | "ControlRight" => Some(KeyCode::Ctrl),
-			"ShiftLeft" | "ShiftRight" => Some(KeyCode::Shift),
-			"AltLeft" | "AltRight" => Some(KeyCode::Alt),
+	pub fn from_index(idx: usize) -> Option<KeyCode> {
+		if idx < KeyCode::Count as usize {
+			// KeyCode is repr(usize) with no explicit discriminants, so every index below Count is a valid variant
+			Some(unsafe { std::mem::transmute::<usize, KeyCode>(idx) })
+		} else {
+			None
+		}
+	}
 
-			"F1" => Some(KeyCode::F1),
+	/// `s` is a `KeyboardEvent.code`, which names a physical key regardless of layout
+	pub fn from_js_code(s: &str) -> Option<KeyCode> {
+		JS_CODES.iter()
+			.position(|&code| code == s)
+			.and_then(KeyCode::from_index)
+	}
 
-			_ => None
-		}
+	pub fn js_code(&self) -> &'static str {
+		JS_CODES[*self as usize]
 	}
 
 	pub fn is_modifier(&self) -> bool {
 		match *self {
-			KeyCode::Ctrl
-			| KeyCode::Shift
-			| KeyCode::Alt => true,
+			KeyCode::ShiftLeft | KeyCode::ShiftRight
+			| KeyCode::CtrlLeft | KeyCode::CtrlRight
+			| KeyCode::AltLeft | KeyCode::AltRight
+			| KeyCode::MetaLeft | KeyCode::MetaRight => true,
 
 			_ => false
 		}
 	}
+}
 
-	pub fn always_consume(&self) -> bool {
-		match *self {
-			// We don't want to scroll
-			KeyCode::Left
-			| KeyCode::Right
-			| KeyCode::Up
-			| KeyCode::Down
-			| KeyCode::Space => true,
 
-			// No help for you
-			KeyCode::F1 => true,
+#[cfg(test)]
+mod tests {
+	use super::*;
 
-			_ => false
+	#[test]
+	fn js_codes_round_trip() {
+		// Input logs store key codes as a single byte
+		assert!((KeyCode::Count as usize) <= 256);
+
+		for idx in 0..KeyCode::Count as usize {
+			let key = KeyCode::from_index(idx).unwrap();
+			assert_eq!(KeyCode::from_js_code(key.js_code()), Some(key), "{} isn't unique", key.js_code());
 		}
+
+		assert_eq!(KeyCode::from_js_code("IntlYen"), Some(KeyCode::IntlYen));
+		assert_eq!(KeyCode::from_js_code("Unidentified"), None);
 	}
 }
@@ -0,0 +1,152 @@
+use common::math::Vec2;
+use crate::input::core::ButtonState;
+
+/// The browser exposes at most four pads, and so do we
+pub const MAX_GAMEPADS: usize = 4;
+
+pub const DEFAULT_STICK_DEAD_ZONE: f32 = 0.15;
+pub const DEFAULT_TRIGGER_DEAD_ZONE: f32 = 0.05;
+
+
+/// Buttons of the W3C "standard" gamepad mapping, in mapping order.
+/// Named by position, since the labels differ between controllers
+#[repr(usize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GamepadButton {
+	South, East, West, North,
+	LeftBumper, RightBumper,
+	LeftTrigger, RightTrigger,
+	Select, Start,
+	LeftStick, RightStick,
+	DPadUp, DPadDown, DPadLeft, DPadRight,
+	Home,
+
+	Count
+}
+
+/// Stick axes are in [-1, 1] and positive right and up. Triggers are in [0, 1]
+#[repr(usize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GamepadAxis {
+	LeftX, LeftY,
+	RightX, RightY,
+	LeftTrigger, RightTrigger,
+
+	Count
+}
+
+
+#[derive(Copy, Clone, Debug)]
+pub struct Gamepad {
+	pub(crate) connected: bool,
+	pub(crate) buttons: [ButtonState; GamepadButton::Count as usize],
+	pub(crate) axes: [f32; GamepadAxis::Count as usize],
+
+	stick_dead_zone: f32,
+	trigger_dead_zone: f32,
+}
+
+impl Gamepad {
+	pub(crate) fn new() -> Self {
+		Gamepad {
+			connected: false,
+			buttons: [ButtonState::Up; GamepadButton::Count as usize],
+			axes: [0.0; GamepadAxis::Count as usize],
+
+			stick_dead_zone: DEFAULT_STICK_DEAD_ZONE,
+			trigger_dead_zone: DEFAULT_TRIGGER_DEAD_ZONE,
+		}
+	}
+
+	pub fn is_connected(&self) -> bool { self.connected }
+
+	pub fn button_state(&self, button: GamepadButton) -> ButtonState {
+		self.buttons.get(button as usize).cloned().unwrap_or(ButtonState::Up)
+	}
+
+	/// With dead zones applied
+	pub fn axis(&self, axis: GamepadAxis) -> f32 {
+		match axis {
+			GamepadAxis::LeftX => self.left_stick().x,
+			GamepadAxis::LeftY => self.left_stick().y,
+			GamepadAxis::RightX => self.right_stick().x,
+			GamepadAxis::RightY => self.right_stick().y,
+
+			GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => {
+				apply_dead_zone(self.raw_axis(axis), self.trigger_dead_zone)
+			}
+
+			GamepadAxis::Count => 0.0,
+		}
+	}
+
+	/// As reported by the browser, but with Y flipped to be positive up
+	pub fn raw_axis(&self, axis: GamepadAxis) -> f32 {
+		self.axes.get(axis as usize).cloned().unwrap_or(0.0)
+	}
+
+	pub fn left_stick(&self) -> Vec2 {
+		self.stick(GamepadAxis::LeftX, GamepadAxis::LeftY)
+	}
+
+	pub fn right_stick(&self) -> Vec2 {
+		self.stick(GamepadAxis::RightX, GamepadAxis::RightY)
+	}
+
+	/// Stick dead zones are radial, and rescaled so that values still start from zero at the edge of the dead zone
+	pub fn set_dead_zones(&mut self, stick: f32, trigger: f32) {
+		self.stick_dead_zone = stick.max(0.0).min(0.99);
+		self.trigger_dead_zone = trigger.max(0.0).min(0.99);
+	}
+
+	fn stick(&self, x: GamepadAxis, y: GamepadAxis) -> Vec2 {
+		let raw = Vec2::new(self.raw_axis(x), self.raw_axis(y));
+		let length = raw.length();
+		if length <= self.stick_dead_zone { return Vec2::zero() }
+
+		let scaled = apply_dead_zone(length.min(1.0), self.stick_dead_zone);
+		raw * (scaled / length)
+	}
+
+	pub(crate) fn clear_frame_state(&mut self) {
+		for state in self.buttons.iter_mut() {
+			*state = state.recent_flag_cleared();
+		}
+	}
+
+	pub(crate) fn reset(&mut self) {
+		self.buttons = [ButtonState::Up; GamepadButton::Count as usize];
+		self.axes = [0.0; GamepadAxis::Count as usize];
+	}
+}
+
+
+impl GamepadButton {
+	pub fn from_index(idx: usize) -> Option<GamepadButton> {
+		if idx < GamepadButton::Count as usize {
+			// repr(usize) with no explicit discriminants, so every index below Count is a valid variant
+			Some(unsafe { std::mem::transmute::<usize, GamepadButton>(idx) })
+		} else {
+			None
+		}
+	}
+}
+
+impl GamepadAxis {
+	pub fn from_index(idx: usize) -> Option<GamepadAxis> {
+		if idx < GamepadAxis::Count as usize {
+			Some(unsafe { std::mem::transmute::<usize, GamepadAxis>(idx) })
+		} else {
+			None
+		}
+	}
+}
+
+
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+	if value.abs() <= dead_zone {
+		0.0
+	} else {
+		value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone)
+	}
+}
@@ -1,66 +1,217 @@
+use std::collections::VecDeque;
+
 use crate::prelude::*;
 use crate::input::core::*;
+use crate::input::pointer::*;
+use crate::input::keyboard_gestures::KeyboardCursor;
+
+/// CSS pixels are nominally 1/96th of an inch, and gestures are tracked in them.
+/// Browsers pick a device pixel ratio that keeps them about the same physical size everywhere, so thresholds are
+/// given in millimetres and converted to CSS pixels with this once, when they're set
+pub const DP_PER_MM: f32 = 96.0 / 25.4;
+
+// Mouse equivalents of the two finger gestures, per pixel dragged
+const MOUSE_PINCH_RATE: f32 = 0.01;
+const MOUSE_TWIST_RATE: f32 = PI / 360.0;
+
+// Same again for the wheel, per pixel scrolled. A typical wheel notch is about 100 pixels
+const WHEEL_PINCH_RATE: f32 = 0.002;
+const WHEEL_TWIST_RATE: f32 = PI / 720.0;
+
+// Velocity is measured over roughly the last tenth of a second, so a pause before releasing kills a flick
+const VELOCITY_WINDOW: Ticks = 6;
+
+// How far apart, in millimetres, the two taps of a double tap can be
+const DOUBLE_TAP_SLOP: f32 = 8.0;
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SwipeDirection {
+	Left, Right, Up, Down,
+}
 
 #[derive(Debug)]
 pub struct GestureTracker {
+	// In CSS pixels, like everything from the input context
 	client_size: Vec2,
+	pixel_ratio: f32,
 	state: ButtonState,
 
+	primary_pointer: Option<Pointer>,
 	current_position: Vec2,
 	initial_position: Vec2,
 	delta: Vec2,
-	distance_travelled: u32,
+	distance_travelled: f32,
 
 	hold_begin: Ticks,
 	current_time: Ticks,
 
-	drag_threshold: u32,
+	transforming: bool,
+	transformed_since_press: bool,
+	centroid: Vec2,
+	pinch_delta: f32,
+	twist_delta: f32,
+	pan_delta: Vec2,
+
+	position_history: VecDeque<(Ticks, Vec2)>,
+	velocity: Vec2,
+	release_velocity: Option<Vec2>,
+	swipe: Option<SwipeDirection>,
+
+	last_tap: Option<(Ticks, Vec2)>,
+	double_tap: bool,
+
+	held_since_press: bool,
+
+	// In CSS pixels, and CSS pixels per second
+	drag_threshold: f32,
 	hold_threshold: u32,
+
+	double_tap_interval: Option<Ticks>,
+	swipe_threshold: Option<f32>,
+	long_press_drag: bool,
+
+	keyboard_cursor: Option<KeyboardCursor>,
 }
 
 
 impl GestureTracker {
-	pub fn new(drag_threshold: u32, hold_threshold: u32) -> Self {
+	/// `drag_threshold` is in millimetres, `hold_threshold` in ticks
+	pub fn new(drag_threshold: f32, hold_threshold: u32) -> Self {
 		Self {
 			client_size: Vec2::zero(),
+			pixel_ratio: 1.0,
 
 			state: ButtonState::Up,
+			primary_pointer: None,
 			current_position: Vec2::zero(),
 			initial_position: Vec2::zero(),
 			delta: Vec2::zero(),
-			distance_travelled: 0,
+			distance_travelled: 0.0,
 
 			hold_begin: 0,
 			current_time: 0,
 
-			// TODO: deal with dpi?
-			drag_threshold,
+			transforming: false,
+			transformed_since_press: false,
+			centroid: Vec2::zero(),
+			pinch_delta: 1.0,
+			twist_delta: 0.0,
+			pan_delta: Vec2::zero(),
+
+			position_history: VecDeque::new(),
+			velocity: Vec2::zero(),
+			release_velocity: None,
+			swipe: None,
+
+			last_tap: None,
+			double_tap: false,
+
+			held_since_press: false,
+
+			drag_threshold: drag_threshold * DP_PER_MM,
 			hold_threshold,
+
+			double_tap_interval: None,
+			swipe_threshold: None,
+			long_press_drag: false,
+
+			keyboard_cursor: None,
+		}
+	}
+
+	/// The most ticks there can be between two taps for the second to count as a double tap. None disables double taps
+	pub fn set_double_tap_interval(&mut self, interval: Option<Ticks>) {
+		self.double_tap_interval = interval;
+	}
+
+	/// How fast a drag must be moving when released to count as a swipe, in millimetres per second.
+	/// None disables swipes
+	pub fn set_swipe_threshold(&mut self, threshold: Option<f32>) {
+		self.swipe_threshold = threshold.map(|threshold| threshold * DP_PER_MM);
+	}
+
+	/// Whether dragging after holding is reported by `long_press_dragging`. Needs a hold threshold
+	pub fn set_long_press_drag(&mut self, enabled: bool) {
+		self.long_press_drag = enabled;
+	}
+
+	/// Drive gestures from the keyboard as well as pointers - see `EngineClient::keyboard_gestures`
+	pub fn set_keyboard_gestures(&mut self, enabled: bool) {
+		if enabled != self.keyboard_cursor.is_some() {
+			self.keyboard_cursor = if enabled { Some(KeyboardCursor::new()) } else { None };
 		}
 	}
 
 	pub fn update(&mut self, input: &InputContext, viewport: Vec2i, time: Ticks) {
-		self.client_size = viewport.to_vec2();
+		self.pixel_ratio = input.pixel_ratio();
+		self.client_size = viewport.to_vec2() / self.pixel_ratio;
 		self.current_time = time;
 
-		let (new_state, position, delta) = get_primary_state(input);
+		let keyboard_state = self.keyboard_cursor.as_mut()
+			.and_then(|cursor| cursor.update(input, self.client_size, time));
+
+		let (new_state, position, delta) = match keyboard_state {
+			Some(keyboard_state) => {
+				self.primary_pointer = None;
+				keyboard_state
+			}
+
+			None => {
+				let (state, position, delta) = get_primary_state(input);
+				self.primary_pointer = input.primary_pointer().cloned();
+				(state, position.to_vec2(), delta.to_vec2())
+			}
+		};
+
 		self.state = new_state;
-		self.delta = delta.to_vec2();
+		self.delta = delta;
 
 		if self.state != ButtonState::Up {
-			self.current_position = position.to_vec2();
+			self.current_position = position;
 		} else {
-			self.distance_travelled = 0;
+			self.distance_travelled = 0.0;
 		}
 
 		if self.state.is_pressed() {
-			self.initial_position = position.to_vec2();
-			self.distance_travelled = 0;
+			self.initial_position = position;
+			self.distance_travelled = 0.0;
 			self.hold_begin = time;
 
 		} else if self.state.is_down() {
-			self.distance_travelled = self.distance_travelled.saturating_add((delta.x.abs() + delta.y.abs()) as u32);
+			self.distance_travelled += delta.x.abs() + delta.y.abs();
+		}
+
+		if self.state.is_pressed() {
+			self.transformed_since_press = false;
+			self.held_since_press = false;
+			self.position_history.clear();
+		}
+
+		match get_transform_state(input) {
+			Some(transform) => {
+				self.transforming = true;
+				self.transformed_since_press = true;
+				self.centroid = transform.centroid;
+				self.pinch_delta = transform.scale;
+				self.twist_delta = transform.twist;
+				self.pan_delta = transform.pan;
+			}
+
+			None => {
+				self.transforming = false;
+				self.pinch_delta = 1.0;
+				self.twist_delta = 0.0;
+				self.pan_delta = Vec2::zero();
+			}
+		}
+
+		if self.holding() {
+			self.held_since_press = true;
 		}
+
+		self.update_velocity();
+		self.update_double_tap();
 	}
 
 	pub fn press(&self) -> bool { self.state == ButtonState::DownRecent }
@@ -71,22 +222,101 @@ impl GestureTracker {
 		self.state == ButtonState::UpRecent
 			&& (self.current_time - self.hold_begin) < self.hold_threshold
 			&& !self.dragging()
+			&& !self.transformed_since_press
+			&& !self.primary_pointer.map_or(false, |p| p.was_cancelled())
 	}
 
 	pub fn holding(&self) -> bool {
 		self.state.is_down()
 			&& (self.current_time - self.hold_begin) >= self.hold_threshold
 			&& !self.dragging()
+			&& !self.transforming
 	}
 
+	/// Never true while a two finger gesture is in progress, so the first finger doesn't also spin things around
 	pub fn dragging(&self) -> bool {
-		self.state != ButtonState::Up && self.distance_travelled >= self.drag_threshold
+		self.state != ButtonState::Up
+			&& self.distance_travelled >= self.drag_threshold
+			&& !self.transforming
+	}
+
+	/// The second of two taps within the double tap interval. The first tap is still reported by `tap` as usual
+	pub fn double_tap(&self) -> bool { self.double_tap }
+
+	/// A drag that began after holding past the hold threshold. Also reported by `dragging`.
+	/// Only with long press drag enabled
+	pub fn long_press_dragging(&self) -> bool {
+		self.long_press_drag && self.held_since_press && self.dragging()
+	}
+
+	/// Recent velocity of the pointer while down, in `position` units per second
+	pub fn velocity(&self) -> Vec2 { self.velocity }
+
+	/// Velocity at the moment a drag was released, for flicking things with inertia. Only on the frame of release
+	pub fn release_velocity(&self) -> Option<Vec2> { self.release_velocity }
+
+	/// A drag released faster than the swipe threshold, in whichever direction it was mostly moving. Only on the frame of release
+	pub fn swipe(&self) -> Option<SwipeDirection> { self.swipe }
+
+	/// Whether a pinch, twist or pan is in progress - either two touches are down, or the mouse equivalents are in use:
+	/// ctrl + drag or the wheel to pinch, shift + drag or shift + wheel to twist, and middle button drag to pan.
+	/// The mouse equivalents are unavailable while the pointer is locked
+	pub fn transforming(&self) -> bool { self.transforming }
+
+	/// Midpoint of the two touches, or the mouse position, in the same space as `position`
+	pub fn centroid(&self) -> Vec2 {
+		(self.centroid / self.client_size * 2.0 - 1.0) * Vec2::new(1.0, -1.0)
+	}
+
+	/// Ratio of the distance between the two touches this frame to the last. Greater than 1 when spreading.
+	/// Multiply a zoom level by this. Always 1 when not transforming
+	pub fn pinch_delta(&self) -> f32 { self.pinch_delta }
+
+	/// Change in angle between the two touches this frame, in radians, counter-clockwise positive
+	pub fn twist_delta(&self) -> f32 { self.twist_delta }
+
+	/// Movement of the centroid this frame, in the same units as `frame_delta`
+	pub fn pan_delta(&self) -> Vec2 {
+		self.pan_delta / self.client_size * Vec2::new(1.0, -1.0)
 	}
 
 	pub fn position(&self) -> Vec2 {
 		(self.current_position / self.client_size * 2.0 - 1.0) * Vec2::new(1.0, -1.0)
 	}
 
+	/// Which kind of pointer is driving gestures
+	pub fn pointer_type(&self) -> PointerType {
+		self.primary_pointer.map_or(PointerType::Mouse, |p| p.pointer_type())
+	}
+
+	/// Pressure of the pointer driving gestures, in [0, 1]. Devices without pressure report 0.5 while pressed
+	pub fn pressure(&self) -> f32 {
+		match self.primary_pointer {
+			Some(pointer) => pointer.pressure(),
+			None => if self.down() { 0.5 } else { 0.0 },
+		}
+	}
+
+	/// Pen tilt in degrees from vertical in the x and y planes. Zero for everything else
+	pub fn tilt(&self) -> Vec2 {
+		self.primary_pointer.map_or(Vec2::zero(), |p| p.tilt())
+	}
+
+	/// Where the keyboard cursor is, in the same space as `position`. Only while keyboard gestures are enabled and the
+	/// cursor is in use - clients don't need to draw it, but may want to highlight whatever is under it
+	pub fn keyboard_cursor(&self) -> Option<Vec2> {
+		let position = self.keyboard_cursor.as_ref()?.position()?;
+		Some((position / self.client_size * 2.0 - 1.0) * Vec2::new(1.0, -1.0))
+	}
+
+	pub(crate) fn keyboard_cursor_state(&self) -> Option<&KeyboardCursor> { self.keyboard_cursor.as_ref() }
+
+	/// Position in CSS pixels from the top left of the page
+	pub fn css_position(&self) -> Vec2 { self.current_position }
+
+	/// Position in framebuffer pixels from the top left of the page
+	pub fn pixel_position(&self) -> Vec2 { self.current_position * self.pixel_ratio }
+
 	pub fn initial_position(&self) -> Vec2 {
 		(self.initial_position / self.client_size * 2.0 - 1.0) * Vec2::new(1.0, -1.0)
 	}
@@ -98,6 +328,79 @@ impl GestureTracker {
 	pub fn drag_delta(&self) -> Vec2 {
 		self.position() - self.initial_position()
 	}
+
+	fn update_velocity(&mut self) {
+		self.release_velocity = None;
+		self.swipe = None;
+
+		if self.state == ButtonState::Up {
+			self.velocity = Vec2::zero();
+			return;
+		}
+
+		let time = self.current_time;
+		self.position_history.push_back((time, self.current_position));
+
+		while let Some(&(sample_time, _)) = self.position_history.front() {
+			if time.wrapping_sub(sample_time) <= VELOCITY_WINDOW { break }
+			self.position_history.pop_front();
+		}
+
+		let (oldest_time, oldest_position) = self.position_history.front().cloned().unwrap();
+		let elapsed = time.wrapping_sub(oldest_time) as f32 * DT;
+
+		// In CSS pixels per second, for comparing against the swipe threshold
+		let mut css_velocity = Vec2::zero();
+
+		if elapsed > 0.0 {
+			css_velocity = (self.current_position - oldest_position) / elapsed;
+			self.velocity = css_velocity / self.client_size * Vec2::new(2.0, -2.0);
+		}
+
+		if !self.state.is_released() || !self.dragging() { return }
+
+		self.release_velocity = Some(self.velocity);
+
+		let threshold = match self.swipe_threshold {
+			Some(threshold) => threshold,
+			None => return,
+		};
+
+		if css_velocity.length() >= threshold {
+			let Vec2{x, y} = self.velocity;
+
+			self.swipe = Some(match (x.abs() > y.abs(), x > 0.0, y > 0.0) {
+				(true, true, _) => SwipeDirection::Right,
+				(true, false, _) => SwipeDirection::Left,
+				(false, _, true) => SwipeDirection::Up,
+				(false, _, false) => SwipeDirection::Down,
+			});
+		}
+	}
+
+	fn update_double_tap(&mut self) {
+		self.double_tap = false;
+
+		let interval = match self.double_tap_interval {
+			Some(interval) => interval,
+			None => return,
+		};
+
+		if !self.tap() { return }
+
+		let is_double = self.last_tap
+			.filter(|&(tap_time, _)| self.current_time.wrapping_sub(tap_time) <= interval)
+			.filter(|&(_, tap_position)| (self.current_position - tap_position).length() <= DOUBLE_TAP_SLOP * DP_PER_MM)
+			.is_some();
+
+		if is_double {
+			// A third tap starts a new double tap rather than completing another
+			self.double_tap = true;
+			self.last_tap = None;
+		} else {
+			self.last_tap = Some((self.current_time, self.current_position));
+		}
+	}
 }
 
 
@@ -106,14 +409,11 @@ fn get_primary_state(ctx: &InputContext) -> (ButtonState, Vec2i, Vec2i) {
 	let mut pos = Vec2i::zero();
 	let mut delta = Vec2i::zero();
 
-	if ctx.touch_mode {
-		let primary_touch = ctx.touch_states.first()
-			.filter(|s| s.index == 0);
-
-		if let Some(state) = primary_touch {
-			button_state = state.state;
-			pos = state.pos;
-			delta = state.frame_delta;
+	if ctx.active_pointer_type != PointerType::Mouse {
+		if let Some(pointer) = ctx.primary_pointer() {
+			button_state = pointer.state;
+			pos = pointer.position();
+			delta = pointer.frame_delta;
 		}
 	} else {
 		let mouse_state = ctx.button_state(MouseButton::Left);
@@ -126,3 +426,204 @@ fn get_primary_state(ctx: &InputContext) -> (ButtonState, Vec2i, Vec2i) {
 
 	(button_state, pos, delta)
 }
+
+
+// Pixel space, like the input context
+struct TransformState {
+	centroid: Vec2,
+	scale: f32,
+	twist: f32,
+	pan: Vec2,
+}
+
+fn get_transform_state(ctx: &InputContext) -> Option<TransformState> {
+	if ctx.active_pointer_type == PointerType::Touch {
+		let mut touches = ctx.touches().filter(|p| p.state.is_down() && !p.is_claimed());
+
+		let (a, b) = (touches.next()?, touches.next()?);
+
+		let (a_pos, b_pos) = (a.position().to_vec2(), b.position().to_vec2());
+		let (a_prev, b_prev) = (a_pos - a.frame_delta.to_vec2(), b_pos - b.frame_delta.to_vec2());
+
+		let span = b_pos - a_pos;
+		let prev_span = b_prev - a_prev;
+
+		let centroid = (a_pos + b_pos) / 2.0;
+		let prev_centroid = (a_prev + b_prev) / 2.0;
+
+		let scale = if prev_span.length() > 0.0 { span.length() / prev_span.length() } else { 1.0 };
+
+		// Pixel space is y down, so flip to get counter-clockwise positive
+		let twist = (-span.y).atan2(span.x) - (-prev_span.y).atan2(prev_span.x);
+		let twist = (twist + PI).rem_euclid(2.0 * PI) - PI;
+
+		return Some(TransformState { centroid, scale, twist, pan: centroid - prev_centroid });
+	}
+
+	if ctx.active_pointer_type != PointerType::Mouse || ctx.is_pointer_locked() { return None }
+
+	let centroid = ctx.mouse_pos.to_vec2();
+	let delta = ctx.mouse_delta.to_vec2();
+	let wheel = ctx.wheel_delta();
+	let modifiers = ctx.modifiers();
+
+	let mut transform = TransformState { centroid, scale: 1.0, twist: 0.0, pan: Vec2::zero() };
+
+	if ctx.button_state(MouseButton::Middle).is_down() {
+		transform.pan = delta;
+
+	} else if ctx.button_state(MouseButton::Left).is_down() && modifiers.ctrl {
+		// Dragging up spreads
+		transform.scale = (-delta.y * MOUSE_PINCH_RATE).exp();
+
+	} else if ctx.button_state(MouseButton::Left).is_down() && modifiers.shift {
+		transform.twist = -delta.x * MOUSE_TWIST_RATE;
+
+	} else if wheel.y != 0.0 || wheel.x != 0.0 {
+		if modifiers.shift {
+			// Some platforms turn shift + wheel into horizontal scrolling
+			let amount = if wheel.y != 0.0 { wheel.y } else { wheel.x };
+			transform.twist = -amount * WHEEL_TWIST_RATE;
+		} else {
+			// Scrolling down zooms out. Trackpad pinches arrive as wheel events too
+			transform.scale = (-wheel.y * WHEEL_PINCH_RATE).exp();
+		}
+
+	} else {
+		return None;
+	}
+
+	Some(transform)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::input::recording::InputEvent;
+
+	fn touch(x: f32, y: f32) -> PointerSample {
+		PointerSample {
+			id: 1,
+			pointer_type: PointerType::Touch,
+			is_primary: true,
+			pos: Vec2i::new(x as i32, y as i32),
+			pressure: 0.5,
+			tilt: Vec2::zero(),
+			contact_size: Vec2::splat(1.0),
+		}
+	}
+
+	struct Harness {
+		input: InputContext,
+		tracker: GestureTracker,
+		time: Ticks,
+	}
+
+	impl Harness {
+		fn new(tracker: GestureTracker, pixel_ratio: f32) -> Self {
+			let mut input = InputContext::new(true);
+			input.pixel_ratio = pixel_ratio;
+			Harness { input, tracker, time: 0 }
+		}
+
+		fn frame(&mut self, events: Vec<InputEvent>) {
+			for event in events {
+				self.input.handle_event(event);
+			}
+
+			let ratio = self.input.pixel_ratio;
+			let viewport = Vec2i::new((800.0 * ratio) as i32, (600.0 * ratio) as i32);
+			self.tracker.update(&self.input, viewport, self.time);
+			self.input.clear_frame_state();
+			self.time += 1;
+		}
+
+		fn tap_at(&mut self, x: f32, y: f32) {
+			self.frame(vec![InputEvent::PointerDown(touch(x, y), MouseButton::Left)]);
+			self.frame(vec![InputEvent::PointerUp(touch(x, y), MouseButton::Left)]);
+		}
+	}
+
+	#[test]
+	fn drag_threshold_is_in_millimetres() {
+		for &pixel_ratio in &[1.0, 3.0] {
+			let mut h = Harness::new(GestureTracker::new(2.0, std::u32::MAX), pixel_ratio);
+
+			h.frame(vec![InputEvent::PointerDown(touch(100.0, 100.0), MouseButton::Left)]);
+
+			let short = 100.0 + 1.5 * DP_PER_MM;
+			h.frame(vec![InputEvent::PointerMove { sample: touch(short, 100.0), delta: Vec2i::zero() }]);
+			assert!(!h.tracker.dragging(), "dragging after 1.5mm at pixel ratio {}", pixel_ratio);
+
+			let long = 100.0 + 2.5 * DP_PER_MM;
+			h.frame(vec![InputEvent::PointerMove { sample: touch(long, 100.0), delta: Vec2i::zero() }]);
+			assert!(h.tracker.dragging(), "not dragging after 2.5mm at pixel ratio {}", pixel_ratio);
+		}
+	}
+
+	#[test]
+	fn leaving_after_release_is_still_a_tap() {
+		let mut h = Harness::new(GestureTracker::new(2.0, std::u32::MAX), 1.0);
+
+		h.frame(vec![
+			InputEvent::PointerDown(touch(100.0, 100.0), MouseButton::Left),
+			InputEvent::PointerUp(touch(100.0, 100.0), MouseButton::Left),
+			InputEvent::PointerCancel(1),
+		]);
+		assert!(h.tracker.press());
+
+		h.frame(Vec::new());
+		assert!(h.tracker.tap());
+	}
+
+	#[test]
+	fn cancelled_touches_are_not_taps() {
+		let mut h = Harness::new(GestureTracker::new(2.0, std::u32::MAX), 1.0);
+
+		h.frame(vec![InputEvent::PointerDown(touch(100.0, 100.0), MouseButton::Left)]);
+		h.frame(vec![InputEvent::PointerCancel(1)]);
+		assert!(h.tracker.release());
+		assert!(!h.tracker.tap());
+	}
+
+	#[test]
+	fn double_tap_slop_is_in_millimetres() {
+		let mut tracker = GestureTracker::new(100.0, std::u32::MAX);
+		tracker.set_double_tap_interval(Some(30));
+
+		let mut h = Harness::new(tracker, 2.0);
+
+		h.tap_at(100.0, 100.0);
+		h.tap_at(100.0 + (DOUBLE_TAP_SLOP - 1.0) * DP_PER_MM, 100.0);
+		assert!(h.tracker.double_tap());
+
+		h.tap_at(100.0, 100.0);
+		h.tap_at(100.0 + (DOUBLE_TAP_SLOP + 1.0) * DP_PER_MM, 100.0);
+		assert!(!h.tracker.double_tap());
+	}
+
+	#[test]
+	fn swipe_threshold_is_in_millimetres_per_second() {
+		let swipe = |threshold: f32| {
+			let mut tracker = GestureTracker::new(0.0, std::u32::MAX);
+			tracker.set_swipe_threshold(Some(threshold));
+
+			let mut h = Harness::new(tracker, 2.0);
+			h.frame(vec![InputEvent::PointerDown(touch(100.0, 100.0), MouseButton::Left)]);
+
+			let mut x = 100.0;
+			for _ in 0..4 {
+				x += 10.0 * DP_PER_MM;
+				h.frame(vec![InputEvent::PointerMove { sample: touch(x, 100.0), delta: Vec2i::zero() }]);
+			}
+
+			h.frame(vec![InputEvent::PointerUp(touch(x, 100.0), MouseButton::Left)]);
+			h.tracker.swipe()
+		};
+
+		// 40mm over the 5 ticks between the press and the release is 480mm/s, give or take rounding to whole pixels
+		assert_eq!(swipe(400.0), Some(SwipeDirection::Right));
+		assert_eq!(swipe(560.0), None);
+	}
+}
@@ -0,0 +1,192 @@
+use common::math::*;
+use crate::DT;
+use crate::Ticks;
+use crate::imports::gl;
+use crate::graphics::{Shader, DynamicMesh, MeshBuilding};
+use crate::graphics::vertex::{Vertex, Descriptor};
+use crate::input::core::*;
+
+// In CSS pixels per second. Holding a modifier other than shift moves it slower, for lining things up
+const CURSOR_SPEED: f32 = 300.0;
+const CURSOR_PRECISE_SPEED: f32 = 60.0;
+const CURSOR_RADIUS: f32 = 10.0;
+const CURSOR_SEGMENTS: usize = 32;
+
+
+/// A cursor moved with the arrow keys, for using gestures without a pointer.
+/// Space or Enter presses at the cursor, so holding either is a hold and tapping is a tap.
+/// Shift and the arrows drags - the press begins with the first arrow and ends when shift is released.
+/// The cursor shows up the first time any of those keys are pressed, and hides again when a pointer is used
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct KeyboardCursor {
+	// In CSS pixels, like the input context. None until the cursor is first shown
+	position: Option<Vec2>,
+	visible: bool,
+
+	state: ButtonState,
+	dragging: bool,
+	prev_time: Ticks,
+}
+
+impl KeyboardCursor {
+	pub(crate) fn new() -> Self {
+		KeyboardCursor {
+			position: None,
+			visible: false,
+
+			state: ButtonState::Up,
+			dragging: false,
+			prev_time: 0,
+		}
+	}
+
+	/// In CSS pixels. None while hidden
+	pub(crate) fn position(&self) -> Option<Vec2> { self.position.filter(|_| self.visible) }
+	pub(crate) fn state(&self) -> ButtonState { self.state }
+
+	/// The same state, position and delta that would otherwise come from the primary pointer,
+	/// or None if the cursor isn't being pressed
+	pub(crate) fn update(&mut self, input: &InputContext, client_size: Vec2, time: Ticks) -> Option<(ButtonState, Vec2, Vec2)> {
+		let elapsed = time.wrapping_sub(self.prev_time) as f32 * DT;
+		self.prev_time = time;
+
+		let key_down = |key: KeyCode| input.button_state(key).is_down();
+		let key_pressed = |key: KeyCode| input.button_state(key).is_pressed();
+
+		let activate = key_down(KeyCode::Space) || key_down(KeyCode::Enter) || key_down(KeyCode::NumpadEnter);
+
+		let arrows = [KeyCode::Left, KeyCode::Right, KeyCode::Up, KeyCode::Down];
+		let used = arrows.iter().chain(&[KeyCode::Space, KeyCode::Enter, KeyCode::NumpadEnter]).any(|&key| key_pressed(key));
+
+		let pointer_used = input.mouse_delta != Vec2i::zero()
+			|| input.pointers().any(|p| p.state().is_pressed())
+			|| input.button_state(MouseButton::Left).is_pressed();
+
+		let was_down = self.state.is_down();
+
+		// Not while pressed, so a press always gets its release
+		if pointer_used && !was_down {
+			self.visible = false;
+		} else if used {
+			self.visible = true;
+		}
+
+		if !self.visible {
+			self.dragging = false;
+			self.state = ButtonState::Up;
+			return None;
+		}
+
+		// Pixel space is y down
+		let direction = Vec2::new(
+			key_down(KeyCode::Right) as i32 as f32 - key_down(KeyCode::Left) as i32 as f32,
+			key_down(KeyCode::Down) as i32 as f32 - key_down(KeyCode::Up) as i32 as f32,
+		);
+
+		let modifiers = input.modifiers();
+		let speed = if modifiers.ctrl || modifiers.alt { CURSOR_PRECISE_SPEED } else { CURSOR_SPEED };
+
+		let prev_position = self.position.unwrap_or(client_size / 2.0);
+		let mut position = prev_position;
+
+		if direction != Vec2::zero() {
+			position += direction.normalize() * speed * elapsed;
+		}
+
+		position = Vec2::new(position.x.max(0.0).min(client_size.x), position.y.max(0.0).min(client_size.y));
+		self.position = Some(position);
+
+		self.dragging = modifiers.shift && (self.dragging || direction != Vec2::zero());
+
+		let down = activate || self.dragging;
+		self.state = match (was_down, down) {
+			(false, true) => ButtonState::DownRecent,
+			(true, true) => ButtonState::Down,
+			(true, false) => ButtonState::UpRecent,
+			(false, false) => ButtonState::Up,
+		};
+
+		if self.state == ButtonState::Up { return None }
+
+		Some((self.state, position, position - prev_position))
+	}
+}
+
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct CursorVertex {
+	pos: Vec2,
+	color: Vec4,
+}
+
+impl Vertex for CursorVertex {
+	fn descriptor() -> Descriptor {
+		Descriptor::from(&[2, 4])
+	}
+}
+
+/// Draws the `KeyboardCursor` over everything else, in framebuffer pixels
+pub(crate) struct KeyboardCursorRenderer {
+	shader: Shader,
+	mesh: DynamicMesh<CursorVertex>,
+}
+
+impl KeyboardCursorRenderer {
+	pub(crate) fn new() -> Self {
+		let shader = Shader::from_asset(
+			embed_asset!("../shaders/virtual_controls.glsl"),
+			&["position", "color"]
+		);
+
+		KeyboardCursorRenderer {
+			shader,
+			mesh: DynamicMesh::new(),
+		}
+	}
+
+	pub(crate) fn draw(&mut self, cursor: &KeyboardCursor, viewport: Vec2i, pixel_ratio: f32) {
+		let position = match cursor.position() {
+			Some(position) => position,
+			None => return,
+		};
+
+		let center = position * pixel_ratio;
+		let radius = CURSOR_RADIUS * pixel_ratio;
+		let fill = if cursor.state().is_down() { 0.9 } else { 0.6 };
+
+		self.mesh.clear();
+
+		// Dark rim so it shows up on light backgrounds too
+		self.add_circle(center, radius * 1.25, Vec4::new(0.1, 0.1, 0.1, 0.8));
+		self.add_circle(center, radius, Vec4::new(0.9, 0.9, 0.9, fill));
+
+		let viewport_f = viewport.to_vec2();
+		let proj = Mat4::translate(Vec3::new(-1.0, 1.0, 0.0))
+			* Mat4::scale(Vec3::new(2.0 / viewport_f.x, -2.0 / viewport_f.y, 1.0));
+
+		unsafe {
+			gl::disable(gl::Capability::DepthTest);
+		}
+
+		self.shader.bind();
+		self.shader.set_uniform("proj_view", proj);
+		self.mesh.draw(gl::DrawMode::Triangles);
+
+		unsafe {
+			gl::enable(gl::Capability::DepthTest);
+		}
+	}
+
+	fn add_circle(&mut self, center: Vec2, radius: f32, color: Vec4) {
+		let verts: Vec<_> = (0..CURSOR_SEGMENTS)
+			.map(|i| {
+				let angle = i as f32 / CURSOR_SEGMENTS as f32 * 2.0 * PI;
+				let pos = center + Vec2::new(angle.cos(), angle.sin()) * radius;
+				CursorVertex { pos, color }
+			})
+			.collect();
+
+		self.mesh.add_tri_fan(&verts);
+	}
+}
@@ -1,5 +1,18 @@
 pub mod core;
 pub mod gesture_tracker;
+pub mod recording;
+pub mod actions;
+pub mod gamepad;
+pub mod pointer;
+pub mod virtual_controls;
+pub mod consume;
+pub(crate) mod keyboard_gestures;
 
 pub use crate::input::core::*;
-pub use crate::input::gesture_tracker::*;
\ No newline at end of file
+pub use crate::input::gesture_tracker::*;
+pub use crate::input::recording::*;
+pub use crate::input::actions::*;
+pub use crate::input::gamepad::*;
+pub use crate::input::pointer::*;
+pub use crate::input::virtual_controls::*;
+pub use crate::input::consume::*;
\ No newline at end of file
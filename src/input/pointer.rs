@@ -0,0 +1,118 @@
+use common::math::{Vec2, Vec2i};
+use crate::input::core::ButtonState;
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PointerType {
+	Mouse, Pen, Touch,
+}
+
+impl PointerType {
+	/// 0, 1 and 2, as passed from the page. Anything unrecognised is treated as a mouse
+	pub fn from_index(idx: usize) -> PointerType {
+		match idx {
+			1 => PointerType::Pen,
+			2 => PointerType::Touch,
+			_ => PointerType::Mouse,
+		}
+	}
+}
+
+
+/// Everything a pointer event reports about a pointer. Positions and sizes are in CSS pixels
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PointerSample {
+	pub id: i32,
+	pub pointer_type: PointerType,
+	/// The browser's primary pointer of its type - the mouse, or the first finger down
+	pub is_primary: bool,
+	pub pos: Vec2i,
+
+	/// In [0, 1]. Devices without pressure report 0.5 while pressed, and 0 otherwise
+	pub pressure: f32,
+	/// Degrees from vertical in the x and y planes, in [-90, 90]
+	pub tilt: Vec2,
+	/// Width and height of the contact geometry. 1x1 for devices that don't report it
+	pub contact_size: Vec2,
+}
+
+
+/// A mouse, pen or finger. Mice and pens are tracked while hovering, fingers only while in contact
+#[derive(Copy, Clone, Debug)]
+pub struct Pointer {
+	pub(crate) sample: PointerSample,
+	pub(crate) frame_delta: Vec2i,
+	pub(crate) start_pos: Vec2i,
+	pub(crate) slot: u32,
+	pub(crate) cancelled: bool,
+	pub(crate) claimed: bool,
+	pub(crate) release_pending: bool,
+
+	/// Contact for touches and pens, and the left button for mice
+	pub(crate) state: ButtonState,
+
+	/// Cleared once the pointer can't be seen by the page anymore. It's dropped at the end of the frame
+	pub(crate) in_range: bool,
+}
+
+impl Pointer {
+	pub(crate) fn new(sample: PointerSample, slot: u32) -> Pointer {
+		Pointer {
+			sample,
+			frame_delta: Vec2i::zero(),
+			start_pos: sample.pos,
+			slot,
+			cancelled: false,
+			claimed: false,
+			release_pending: false,
+			state: ButtonState::Up,
+			in_range: true,
+		}
+	}
+
+	pub fn id(&self) -> i32 { self.sample.id }
+	pub fn pointer_type(&self) -> PointerType { self.sample.pointer_type }
+	pub fn is_primary(&self) -> bool { self.sample.is_primary }
+
+	/// In CSS pixels
+	pub fn position(&self) -> Vec2i { self.sample.pos }
+	pub fn frame_delta(&self) -> Vec2i { self.frame_delta }
+
+	/// Where the pointer was last pressed, in CSS pixels
+	pub fn start_position(&self) -> Vec2i { self.start_pos }
+
+	/// Press and release edges are per pointer, so every finger can be handled independently
+	pub fn state(&self) -> ButtonState { self.state }
+
+	/// Touches get the lowest slot not taken by another touch when they go down, and keep it until they're lifted.
+	/// So with two fingers down, lifting the first leaves the second in slot 1, and the next finger down takes slot 0.
+	/// Always 0 for mice and pens
+	pub fn slot(&self) -> u32 { self.slot }
+
+	/// Whether the browser took the pointer away rather than it being released - e.g. a touch turning into a page scroll.
+	/// A cancelled pointer is still released, but shouldn't be treated as e.g. a tap
+	pub fn was_cancelled(&self) -> bool { self.cancelled }
+
+	/// Whether the pointer went down on one of the on-screen `VirtualControls`. Claimed pointers stay claimed until they're
+	/// lifted, and are never the primary pointer or part of a gesture
+	pub fn is_claimed(&self) -> bool { self.claimed }
+
+	pub fn pressure(&self) -> f32 { self.sample.pressure }
+	pub fn tilt(&self) -> Vec2 { self.sample.tilt }
+	pub fn contact_size(&self) -> Vec2 { self.sample.contact_size }
+
+	/// Like keys, a pointer pressed and released within one frame is released on the next
+	pub(crate) fn release(&mut self) {
+		match self.state {
+			ButtonState::DownRecent => self.release_pending = true,
+			ButtonState::Down => self.state = ButtonState::UpRecent,
+			_ => {}
+		}
+	}
+
+	pub(crate) fn update(&mut self, sample: PointerSample) {
+		self.frame_delta += sample.pos - self.sample.pos;
+		self.sample = sample;
+		self.in_range = true;
+	}
+}
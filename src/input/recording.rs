@@ -0,0 +1,551 @@
+use crate::prelude::*;
+use crate::input::core::*;
+use crate::input::gamepad::*;
+use crate::input::pointer::*;
+
+// Log layout: MAGIC, VERSION, then one record after another until the end of the buffer.
+// Each record is a tag byte, the number of ticks since the previous record as a varint, then the tag's payload.
+// Integers are zigzag varints, frame times and axis values are little endian f64s and f32s, strings are a varint length then utf-8.
+const MAGIC: &[u8; 4] = b"WTIR";
+const VERSION: u8 = 7;
+
+const TAG_FRAME: u8 = 0;
+const TAG_KEY_DOWN: u8 = 1;
+const TAG_KEY_UP: u8 = 2;
+const TAG_POINTER_DOWN: u8 = 3;
+const TAG_POINTER_UP: u8 = 4;
+const TAG_POINTER_MOVE: u8 = 5;
+const TAG_POINTER_CANCEL: u8 = 6;
+const TAG_WHEEL: u8 = 7;
+const TAG_RESET: u8 = 8;
+const TAG_POINTER_LOCK_CHANGE: u8 = 9;
+const TAG_TEXT_COMMIT: u8 = 10;
+const TAG_TEXT_COMPOSITION: u8 = 11;
+const TAG_TEXT_BACKSPACE: u8 = 12;
+const TAG_TEXT_DELETE: u8 = 13;
+const TAG_GAMEPAD_CONNECT: u8 = 14;
+const TAG_GAMEPAD_DISCONNECT: u8 = 15;
+const TAG_GAMEPAD_BUTTON: u8 = 16;
+const TAG_GAMEPAD_AXIS: u8 = 17;
+
+
+/// Everything that can change the state of an `InputContext`
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent {
+	KeyDown(KeyCode),
+	KeyUp(KeyCode),
+
+	/// Touches and pen tips come through as the left button
+	PointerDown(PointerSample, MouseButton),
+	PointerUp(PointerSample, MouseButton),
+	/// `delta` is the mouse's movement, which is still reported while the pointer is locked
+	PointerMove { sample: PointerSample, delta: Vec2i },
+	PointerCancel(i32),
+
+	/// In CSS pixels
+	Wheel(Vec2),
+
+	Text(TextInput),
+
+	/// Pad index, and whether it's now connected
+	GamepadConnection(usize, bool),
+	/// Pad index, button, and whether it's now down
+	GamepadButton(usize, GamepadButton, bool),
+	/// Pad index, axis, and its new raw value
+	GamepadAxis(usize, GamepadAxis, f32),
+
+	/// All buttons and touches released - focus loss, the pointer leaving the canvas, etc
+	Reset,
+	PointerLockChange(bool),
+}
+
+
+/// An event as it arrived from the page. `timestamp` is in milliseconds, on the same clock as requestAnimationFrame
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedInputEvent {
+	pub timestamp: f64,
+	pub event: InputEvent,
+}
+
+
+#[derive(Clone, Debug)]
+enum Record {
+	/// Start of an animation frame, and the real time that had passed since the previous one
+	Frame(f64),
+	Event(InputEvent),
+}
+
+
+/// Input events stamped with the tick they arrived on, interleaved with the start of each animation frame.
+/// Replaying the frames as well as the events means simulate steps and gesture edges line up exactly
+#[derive(Clone, Debug, Default)]
+pub struct InputLog {
+	entries: Vec<(Ticks, Record)>,
+}
+
+impl InputLog {
+	pub fn new() -> Self { InputLog::default() }
+
+	pub fn len(&self) -> usize { self.entries.len() }
+	pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+	pub fn events(&self) -> impl Iterator<Item=(Ticks, &InputEvent)> + '_ {
+		self.entries.iter()
+			.filter_map(|(tick, record)| match record {
+				Record::Event(event) => Some((*tick, event)),
+				Record::Frame(_) => None,
+			})
+	}
+
+	pub fn frame_count(&self) -> usize {
+		self.entries.iter().filter(|(_, r)| matches!(r, Record::Frame(_))).count()
+	}
+
+	pub(crate) fn push_event(&mut self, tick: Ticks, event: InputEvent) {
+		self.entries.push((tick, Record::Event(event)));
+	}
+
+	pub(crate) fn push_frame(&mut self, tick: Ticks, frame_time: f64) {
+		self.entries.push((tick, Record::Frame(frame_time)));
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = Vec::with_capacity(MAGIC.len() + 1 + self.entries.len() * 4);
+		buf.extend_from_slice(MAGIC);
+		buf.push(VERSION);
+
+		let mut prev_tick = self.entries.first().map_or(0, |&(t, _)| t);
+		write_varint(&mut buf, prev_tick as u64);
+
+		for &(tick, ref record) in self.entries.iter() {
+			let (tag, payload) = encode_record(record);
+			buf.push(tag);
+			write_varint(&mut buf, tick.wrapping_sub(prev_tick) as u64);
+			buf.extend_from_slice(&payload);
+			prev_tick = tick;
+		}
+
+		buf
+	}
+
+	pub fn from_bytes(bytes: &[u8]) -> EngineResult<InputLog> {
+		ensure!(bytes.len() > MAGIC.len() && &bytes[..MAGIC.len()] == MAGIC, "Not an input log");
+		ensure!(bytes[MAGIC.len()] == VERSION, "Unsupported input log version {}", bytes[MAGIC.len()]);
+
+		let mut reader = Reader { bytes, pos: MAGIC.len() + 1 };
+		let mut tick = reader.varint()? as Ticks;
+		let mut entries = Vec::new();
+
+		while !reader.is_empty() {
+			let tag = reader.byte()?;
+			tick = tick.wrapping_add(reader.varint()? as Ticks);
+
+			let record = decode_record(tag, &mut reader)?;
+			entries.push((tick, record));
+		}
+
+		Ok(InputLog { entries })
+	}
+}
+
+
+/// Feeds an `InputLog` back into an `InputContext` one frame at a time
+pub struct InputReplay {
+	log: InputLog,
+	cursor: usize,
+}
+
+impl InputReplay {
+	pub fn new(log: InputLog) -> Self {
+		InputReplay { log, cursor: 0 }
+	}
+
+	pub fn is_finished(&self) -> bool { self.cursor >= self.log.entries.len() }
+
+	/// Applies every event up to the start of the next recorded frame, and returns that frame's frame time.
+	/// Returns None once the log runs out of frames
+	pub fn next_frame(&mut self, input: &mut InputContext, timestamp: f64) -> Option<f64> {
+		while let Some((_, record)) = self.log.entries.get(self.cursor) {
+			self.cursor += 1;
+
+			match record {
+				Record::Event(event) => input.process_event(timestamp, event.clone()),
+				Record::Frame(frame_time) => return Some(*frame_time),
+			}
+		}
+
+		None
+	}
+}
+
+
+fn encode_record(record: &Record) -> (u8, Vec<u8>) {
+	let mut payload = Vec::new();
+
+	let tag = match *record {
+		Record::Frame(frame_time) => {
+			payload.extend_from_slice(&frame_time.to_le_bytes());
+			TAG_FRAME
+		}
+
+		Record::Event(ref event) => match *event {
+			InputEvent::KeyDown(code) => { payload.push(code as u8); TAG_KEY_DOWN }
+			InputEvent::KeyUp(code) => { payload.push(code as u8); TAG_KEY_UP }
+
+			InputEvent::PointerDown(ref sample, mb) => { write_pointer_sample(&mut payload, sample); payload.push(mb as u8); TAG_POINTER_DOWN }
+			InputEvent::PointerUp(ref sample, mb) => { write_pointer_sample(&mut payload, sample); payload.push(mb as u8); TAG_POINTER_UP }
+
+			InputEvent::PointerMove { ref sample, delta } => {
+				write_pointer_sample(&mut payload, sample);
+				write_vec2i(&mut payload, delta);
+				TAG_POINTER_MOVE
+			}
+
+			InputEvent::PointerCancel(id) => { write_zigzag(&mut payload, id); TAG_POINTER_CANCEL }
+
+			InputEvent::Wheel(delta) => {
+				write_f32(&mut payload, delta.x);
+				write_f32(&mut payload, delta.y);
+				TAG_WHEEL
+			}
+
+			InputEvent::Text(ref text) => match text {
+				TextInput::Commit(text) => { write_str(&mut payload, text); TAG_TEXT_COMMIT }
+				TextInput::Composition(text) => { write_str(&mut payload, text); TAG_TEXT_COMPOSITION }
+				TextInput::Backspace => TAG_TEXT_BACKSPACE,
+				TextInput::Delete => TAG_TEXT_DELETE,
+			}
+
+			InputEvent::GamepadConnection(pad, connected) => {
+				payload.push(pad as u8);
+				if connected { TAG_GAMEPAD_CONNECT } else { TAG_GAMEPAD_DISCONNECT }
+			}
+
+			InputEvent::GamepadButton(pad, button, down) => {
+				payload.push(pad as u8);
+				payload.push(button as u8);
+				payload.push(down as u8);
+				TAG_GAMEPAD_BUTTON
+			}
+
+			InputEvent::GamepadAxis(pad, axis, value) => {
+				payload.push(pad as u8);
+				payload.push(axis as u8);
+				write_f32(&mut payload, value);
+				TAG_GAMEPAD_AXIS
+			}
+
+			InputEvent::Reset => TAG_RESET,
+			InputEvent::PointerLockChange(enabled) => { payload.push(enabled as u8); TAG_POINTER_LOCK_CHANGE }
+		}
+	};
+
+	(tag, payload)
+}
+
+fn decode_record(tag: u8, reader: &mut Reader<'_>) -> EngineResult<Record> {
+	let event = match tag {
+		TAG_FRAME => return Ok(Record::Frame(reader.f64()?)),
+
+		TAG_KEY_DOWN => InputEvent::KeyDown(reader.key_code()?),
+		TAG_KEY_UP => InputEvent::KeyUp(reader.key_code()?),
+
+		TAG_POINTER_DOWN => InputEvent::PointerDown(reader.pointer_sample()?, reader.mouse_button()?),
+		TAG_POINTER_UP => InputEvent::PointerUp(reader.pointer_sample()?, reader.mouse_button()?),
+		TAG_POINTER_MOVE => InputEvent::PointerMove { sample: reader.pointer_sample()?, delta: reader.vec2i()? },
+		TAG_POINTER_CANCEL => InputEvent::PointerCancel(reader.zigzag()?),
+		TAG_WHEEL => InputEvent::Wheel(Vec2::new(reader.f32()?, reader.f32()?)),
+
+		TAG_TEXT_COMMIT => InputEvent::Text(TextInput::Commit(reader.string()?)),
+		TAG_TEXT_COMPOSITION => InputEvent::Text(TextInput::Composition(reader.string()?)),
+		TAG_TEXT_BACKSPACE => InputEvent::Text(TextInput::Backspace),
+		TAG_TEXT_DELETE => InputEvent::Text(TextInput::Delete),
+
+		TAG_GAMEPAD_CONNECT => InputEvent::GamepadConnection(reader.byte()? as usize, true),
+		TAG_GAMEPAD_DISCONNECT => InputEvent::GamepadConnection(reader.byte()? as usize, false),
+		TAG_GAMEPAD_BUTTON => InputEvent::GamepadButton(reader.byte()? as usize, reader.gamepad_button()?, reader.byte()? != 0),
+		TAG_GAMEPAD_AXIS => InputEvent::GamepadAxis(reader.byte()? as usize, reader.gamepad_axis()?, reader.f32()?),
+
+		TAG_RESET => InputEvent::Reset,
+		TAG_POINTER_LOCK_CHANGE => InputEvent::PointerLockChange(reader.byte()? != 0),
+
+		_ => bail!("Unknown input log record tag {} at byte {}", tag, reader.pos),
+	};
+
+	Ok(Record::Event(event))
+}
+
+
+fn write_varint(buf: &mut Vec<u8>, mut v: u64) {
+	while v >= 0x80 {
+		buf.push((v as u8) | 0x80);
+		v >>= 7;
+	}
+
+	buf.push(v as u8);
+}
+
+fn write_zigzag(buf: &mut Vec<u8>, v: i32) {
+	write_varint(buf, ((v << 1) ^ (v >> 31)) as u32 as u64);
+}
+
+fn write_vec2i(buf: &mut Vec<u8>, v: Vec2i) {
+	write_zigzag(buf, v.x);
+	write_zigzag(buf, v.y);
+}
+
+fn write_f32(buf: &mut Vec<u8>, v: f32) {
+	buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn write_pointer_sample(buf: &mut Vec<u8>, sample: &PointerSample) {
+	write_zigzag(buf, sample.id);
+	buf.push(sample.pointer_type as u8);
+	buf.push(sample.is_primary as u8);
+	write_vec2i(buf, sample.pos);
+	write_f32(buf, sample.pressure);
+	write_f32(buf, sample.tilt.x);
+	write_f32(buf, sample.tilt.y);
+	write_f32(buf, sample.contact_size.x);
+	write_f32(buf, sample.contact_size.y);
+}
+
+fn write_str(buf: &mut Vec<u8>, s: &str) {
+	write_varint(buf, s.len() as u64);
+	buf.extend_from_slice(s.as_bytes());
+}
+
+
+struct Reader<'b> {
+	bytes: &'b [u8],
+	pos: usize,
+}
+
+impl<'b> Reader<'b> {
+	fn is_empty(&self) -> bool { self.pos >= self.bytes.len() }
+
+	fn byte(&mut self) -> EngineResult<u8> {
+		let b = *self.bytes.get(self.pos)
+			.ok_or_else(|| format_err!("Input log ended unexpectedly"))?;
+
+		self.pos += 1;
+		Ok(b)
+	}
+
+	fn varint(&mut self) -> EngineResult<u64> {
+		let mut v = 0u64;
+
+		for shift in (0..64).step_by(7) {
+			let b = self.byte()?;
+			v |= ((b & 0x7F) as u64) << shift;
+			if b & 0x80 == 0 { return Ok(v) }
+		}
+
+		bail!("Malformed varint in input log at byte {}", self.pos)
+	}
+
+	fn zigzag(&mut self) -> EngineResult<i32> {
+		let v = self.varint()? as u32;
+		Ok(((v >> 1) as i32) ^ -((v & 1) as i32))
+	}
+
+	fn vec2i(&mut self) -> EngineResult<Vec2i> {
+		Ok(Vec2i::new(self.zigzag()?, self.zigzag()?))
+	}
+
+	fn f64(&mut self) -> EngineResult<f64> {
+		let mut raw = [0u8; 8];
+		for b in raw.iter_mut() {
+			*b = self.byte()?;
+		}
+
+		Ok(f64::from_le_bytes(raw))
+	}
+
+	fn f32(&mut self) -> EngineResult<f32> {
+		let mut raw = [0u8; 4];
+		for b in raw.iter_mut() {
+			*b = self.byte()?;
+		}
+
+		Ok(f32::from_le_bytes(raw))
+	}
+
+	fn string(&mut self) -> EngineResult<String> {
+		let len = self.varint()? as usize;
+		let end = self.pos.checked_add(len)
+			.filter(|&end| end <= self.bytes.len())
+			.ok_or_else(|| format_err!("Input log ended unexpectedly"))?;
+
+		let s = std::str::from_utf8(&self.bytes[self.pos..end])
+			.map_err(|_| format_err!("Invalid utf-8 in input log at byte {}", self.pos))?;
+
+		self.pos = end;
+		Ok(s.to_owned())
+	}
+
+	fn key_code(&mut self) -> EngineResult<KeyCode> {
+		let idx = self.byte()?;
+		KeyCode::from_index(idx as usize)
+			.ok_or_else(|| format_err!("Invalid KeyCode {} in input log", idx))
+	}
+
+	fn mouse_button(&mut self) -> EngineResult<MouseButton> {
+		let idx = self.byte()?;
+		MouseButton::from_index(idx as usize)
+			.ok_or_else(|| format_err!("Invalid MouseButton {} in input log", idx))
+	}
+
+	fn pointer_sample(&mut self) -> EngineResult<PointerSample> {
+		Ok(PointerSample {
+			id: self.zigzag()?,
+			pointer_type: PointerType::from_index(self.byte()? as usize),
+			is_primary: self.byte()? != 0,
+			pos: self.vec2i()?,
+			pressure: self.f32()?,
+			tilt: Vec2::new(self.f32()?, self.f32()?),
+			contact_size: Vec2::new(self.f32()?, self.f32()?),
+		})
+	}
+
+	fn gamepad_button(&mut self) -> EngineResult<GamepadButton> {
+		let idx = self.byte()?;
+		GamepadButton::from_index(idx as usize)
+			.ok_or_else(|| format_err!("Invalid GamepadButton {} in input log", idx))
+	}
+
+	fn gamepad_axis(&mut self) -> EngineResult<GamepadAxis> {
+		let idx = self.byte()?;
+		GamepadAxis::from_index(idx as usize)
+			.ok_or_else(|| format_err!("Invalid GamepadAxis {} in input log", idx))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sample(id: i32, x: i32, y: i32) -> PointerSample {
+		PointerSample {
+			id,
+			pointer_type: PointerType::Touch,
+			is_primary: id == 0,
+			pos: Vec2i::new(x, y),
+			pressure: 0.5,
+			tilt: Vec2::zero(),
+			contact_size: Vec2::splat(1.0),
+		}
+	}
+
+	fn test_frames() -> Vec<(f64, Vec<InputEvent>)> {
+		vec![
+			(1.0 / 60.0, vec![
+				InputEvent::KeyDown(KeyCode::A),
+				InputEvent::PointerDown(sample(0, 10, 20), MouseButton::Left),
+			]),
+			(1.0 / 30.0, vec![
+				InputEvent::PointerMove { sample: sample(0, 15, 18), delta: Vec2i::new(5, -2) },
+				InputEvent::Wheel(Vec2::new(0.0, 120.0)),
+				InputEvent::GamepadConnection(0, true),
+				InputEvent::GamepadAxis(0, GamepadAxis::LeftX, -0.75),
+			]),
+			(0.0, Vec::new()),
+			(1.0 / 60.0, vec![
+				InputEvent::KeyUp(KeyCode::A),
+				InputEvent::PointerUp(sample(0, 15, 18), MouseButton::Left),
+				InputEvent::Text(TextInput::Commit("héllo".to_owned())),
+				InputEvent::GamepadButton(0, GamepadButton::South, true),
+			]),
+		]
+	}
+
+	fn snapshot(input: &InputContext) -> String {
+		let pointers: Vec<_> = input.pointers()
+			.map(|p| (p.id(), p.state(), p.position(), p.frame_delta()))
+			.collect();
+
+		let pad = &input.gamepads[0];
+
+		format!("{:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+			input.button_state(KeyCode::A), input.button_state(MouseButton::Left),
+			input.mouse_position(), input.mouse_delta, input.wheel_delta(), input.text_input(), pointers,
+			pad.raw_axis(GamepadAxis::LeftX), pad.button_state(GamepadButton::South))
+	}
+
+	/// Applies each frame's events the way the engine does, logging them along the way
+	fn record(frames: &[(f64, Vec<InputEvent>)]) -> (InputLog, Vec<String>) {
+		let mut input = InputContext::new(true);
+		let mut log = InputLog::new();
+		let mut snapshots = Vec::new();
+
+		for (tick, (frame_time, events)) in frames.iter().enumerate() {
+			for (idx, event) in events.iter().enumerate() {
+				log.push_event(tick as Ticks, event.clone());
+				input.process_event(tick as f64 * 16.0 + idx as f64, event.clone());
+			}
+
+			log.push_frame(tick as Ticks, *frame_time);
+			snapshots.push(snapshot(&input));
+			input.clear_frame_state();
+		}
+
+		(log, snapshots)
+	}
+
+	#[test]
+	fn replay_reproduces_recorded_input() {
+		let frames = test_frames();
+		let (log, recorded) = record(&frames);
+
+		let bytes = log.to_bytes();
+		let decoded = InputLog::from_bytes(&bytes).unwrap();
+
+		assert_eq!(decoded.len(), log.len());
+		assert_eq!(decoded.frame_count(), frames.len());
+		assert_eq!(decoded.to_bytes(), bytes);
+
+		let mut input = InputContext::new(true);
+		let mut replay = InputReplay::new(decoded);
+
+		for (frame, (frame_time, _)) in frames.iter().enumerate() {
+			assert_eq!(replay.next_frame(&mut input, 0.0), Some(*frame_time), "frame {}", frame);
+			assert_eq!(snapshot(&input), recorded[frame], "frame {}", frame);
+			input.clear_frame_state();
+		}
+
+		assert!(replay.is_finished());
+		assert_eq!(replay.next_frame(&mut input, 0.0), None);
+	}
+
+	#[test]
+	fn events_keep_their_ticks() {
+		let (log, _) = record(&test_frames());
+		let decoded = InputLog::from_bytes(&log.to_bytes()).unwrap();
+
+		let ticks: Vec<_> = decoded.events().map(|(tick, _)| tick).collect();
+		assert_eq!(ticks, [0, 0, 1, 1, 1, 1, 3, 3, 3, 3]);
+
+		let events: Vec<_> = decoded.events().map(|(_, e)| e.clone()).collect();
+		let expected: Vec<_> = log.events().map(|(_, e)| e.clone()).collect();
+		assert_eq!(events, expected);
+	}
+
+	#[test]
+	fn bad_logs_are_rejected() {
+		let (log, _) = record(&test_frames());
+		let bytes = log.to_bytes();
+
+		let mut wrong_version = bytes.clone();
+		wrong_version[MAGIC.len()] = VERSION + 1;
+		assert!(InputLog::from_bytes(&wrong_version).is_err());
+
+		let mut wrong_magic = bytes.clone();
+		wrong_magic[0] = b'X';
+		assert!(InputLog::from_bytes(&wrong_magic).is_err());
+
+		// The last record is a frame, so this cuts its frame time short
+		assert!(InputLog::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+		assert!(InputLog::from_bytes(&bytes[..MAGIC.len()]).is_err());
+	}
+}
@@ -0,0 +1,294 @@
+use common::*;
+use crate::imports::gl;
+use crate::graphics::{Shader, DynamicMesh, MeshBuilding, TextRenderer};
+use crate::graphics::vertex::{Vertex, Descriptor};
+use crate::graphics::text::measure_text;
+use crate::input::core::{InputContext, ButtonState};
+use crate::input::pointer::PointerType;
+
+// A touch that lands a little outside the stick still grabs it - thumbs aren't precise
+const STICK_GRAB_SCALE: f32 = 1.5;
+const THUMB_SCALE: f32 = 0.45;
+const CIRCLE_SEGMENTS: usize = 32;
+const LABEL_SCALE: f32 = 2.0;
+
+
+/// The corner of the canvas that a control is positioned from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Anchor {
+	TopLeft, TopRight,
+	BottomLeft, BottomRight,
+}
+
+/// Controls are circles, `offset` CSS pixels in from their anchor corner to their centre
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VirtualStickLayout {
+	pub anchor: Anchor,
+	pub offset: Vec2,
+	pub radius: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VirtualButtonLayout {
+	pub anchor: Anchor,
+	pub offset: Vec2,
+	pub radius: f32,
+	pub label: String,
+}
+
+/// What a client wants on screen for touch input. Buttons are referred to by the order they were added in,
+/// e.g. `Binding::VirtualButton(0)` for the first
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VirtualControlsLayout {
+	stick: Option<VirtualStickLayout>,
+	buttons: Vec<VirtualButtonLayout>,
+}
+
+impl VirtualControlsLayout {
+	pub fn new() -> Self { VirtualControlsLayout::default() }
+
+	pub fn set_stick(&mut self, anchor: Anchor, offset: Vec2, radius: f32) -> &mut Self {
+		self.stick = Some(VirtualStickLayout { anchor, offset, radius });
+		self
+	}
+
+	pub fn add_button(&mut self, anchor: Anchor, offset: Vec2, radius: f32, label: &str) -> &mut Self {
+		self.buttons.push(VirtualButtonLayout { anchor, offset, radius, label: label.to_owned() });
+		self
+	}
+
+	pub fn stick(&self) -> Option<&VirtualStickLayout> { self.stick.as_ref() }
+	pub fn buttons(&self) -> &[VirtualButtonLayout] { &self.buttons }
+}
+
+
+/// On-screen stick and buttons, driven by touches. Only shown and active while touch is the active pointer type,
+/// and only for clients that return a layout from `EngineClient::virtual_controls`.
+/// Feeds `Actions` through `Binding::VirtualStickX`, `VirtualStickY` and `VirtualButton`, so the same actions can be
+/// bound to keys, pads and these
+pub struct VirtualControls {
+	visible: bool,
+
+	stick_owner: Option<i32>,
+	stick_value: Vec2,
+
+	button_owners: Vec<Option<i32>>,
+	button_states: Vec<ButtonState>,
+
+	renderer: Option<VirtualControlsRenderer>,
+}
+
+impl VirtualControls {
+	pub(crate) fn new() -> Self {
+		VirtualControls {
+			visible: false,
+
+			stick_owner: None,
+			stick_value: Vec2::zero(),
+
+			button_owners: Vec::new(),
+			button_states: Vec::new(),
+
+			renderer: None,
+		}
+	}
+
+	pub fn is_visible(&self) -> bool { self.visible }
+
+	/// In [-1, 1] on each axis, positive right and up. Zero while the stick isn't held
+	pub fn stick(&self) -> Vec2 { self.stick_value }
+
+	pub fn button_state(&self, button: usize) -> ButtonState {
+		self.button_states.get(button).cloned().unwrap_or(ButtonState::Up)
+	}
+
+	/// Claims any new touches that land on a control, and updates the controls from the touches they hold.
+	/// Has to run before the `GestureTracker`, so that it doesn't see claimed touches
+	pub(crate) fn update(&mut self, layout: Option<&VirtualControlsLayout>, input: &mut InputContext, css_size: Vec2) {
+		self.visible = layout.is_some() && input.active_pointer_type() == PointerType::Touch;
+
+		let empty_layout = VirtualControlsLayout::new();
+		let layout = if self.visible { layout.unwrap() } else { &empty_layout };
+
+		// Owners stay until their touch is lifted, or the control goes away
+		let held = |owner: Option<i32>| owner.filter(|&id| input.touches().any(|p| p.id() == id && p.state().is_down()));
+
+		self.stick_owner = layout.stick.and(held(self.stick_owner));
+		self.button_owners.resize(layout.buttons.len(), None);
+		for owner in self.button_owners.iter_mut() {
+			*owner = held(*owner);
+		}
+
+		for pointer in input.pointers.iter_mut() {
+			if pointer.pointer_type() != PointerType::Touch || !pointer.state.is_pressed() || pointer.claimed { continue }
+
+			let pos = pointer.position().to_vec2();
+
+			if let Some(stick) = layout.stick.filter(|_| self.stick_owner.is_none()) {
+				if (pos - control_center(stick.anchor, stick.offset, css_size)).length() <= stick.radius * STICK_GRAB_SCALE {
+					self.stick_owner = Some(pointer.id());
+					pointer.claimed = true;
+					continue;
+				}
+			}
+
+			let hit_button = layout.buttons.iter()
+				.zip(self.button_owners.iter_mut())
+				.find(|(button, owner)| {
+					owner.is_none() && (pos - control_center(button.anchor, button.offset, css_size)).length() <= button.radius
+				});
+
+			if let Some((_, owner)) = hit_button {
+				*owner = Some(pointer.id());
+				pointer.claimed = true;
+			}
+		}
+
+		self.stick_value = match (layout.stick, self.stick_owner) {
+			(Some(stick), Some(owner)) => {
+				let pos = input.touches().find(|p| p.id() == owner).unwrap().position().to_vec2();
+				let offset = (pos - control_center(stick.anchor, stick.offset, css_size)) / stick.radius.max(1.0);
+				let offset = if offset.length() > 1.0 { offset / offset.length() } else { offset };
+
+				// Pixel space is y down
+				Vec2::new(offset.x, -offset.y)
+			}
+
+			_ => Vec2::zero(),
+		};
+
+		let prev_states = std::mem::take(&mut self.button_states);
+
+		self.button_states = self.button_owners.iter().enumerate()
+			.map(|(idx, owner)| {
+				let was_down = prev_states.get(idx).map_or(false, |s| s.is_down());
+
+				match (was_down, owner.is_some()) {
+					(false, true) => ButtonState::DownRecent,
+					(true, true) => ButtonState::Down,
+					(true, false) => ButtonState::UpRecent,
+					(false, false) => ButtonState::Up,
+				}
+			})
+			.collect();
+	}
+
+	/// Draws over whatever the client rendered, in framebuffer pixels
+	pub(crate) fn draw(&mut self, layout: Option<&VirtualControlsLayout>, viewport: Vec2i, pixel_ratio: f32) {
+		let layout = match layout {
+			Some(layout) if self.visible => layout,
+			_ => return,
+		};
+
+		let css_size = viewport.to_vec2() / pixel_ratio;
+		let renderer = self.renderer.get_or_insert_with(VirtualControlsRenderer::new);
+		renderer.mesh.clear();
+		renderer.text.clear();
+
+		if let Some(stick) = layout.stick {
+			let center = control_center(stick.anchor, stick.offset, css_size) * pixel_ratio;
+			let radius = stick.radius * pixel_ratio;
+			let alpha = if self.stick_owner.is_some() { 0.5 } else { 0.3 };
+
+			let thumb_center = center + Vec2::new(self.stick_value.x, -self.stick_value.y) * radius;
+
+			renderer.add_circle(center, radius, grey(0.1, alpha));
+			renderer.add_circle(thumb_center, radius * THUMB_SCALE, grey(0.9, alpha));
+		}
+
+		for (idx, button) in layout.buttons.iter().enumerate() {
+			let center = control_center(button.anchor, button.offset, css_size) * pixel_ratio;
+			let radius = button.radius * pixel_ratio;
+			let alpha = if self.button_states.get(idx).map_or(false, |s| s.is_down()) { 0.6 } else { 0.3 };
+
+			renderer.add_circle(center, radius, grey(0.9, alpha));
+
+			let text_scale = LABEL_SCALE * pixel_ratio;
+			let label_size = measure_text(&button.label, text_scale);
+			renderer.text.add_text(center - label_size / 2.0, text_scale, Color::grey(0.1), &button.label);
+		}
+
+		renderer.draw(viewport);
+	}
+}
+
+fn grey(value: f32, alpha: f32) -> Vec4 {
+	Vec4::new(value, value, value, alpha)
+}
+
+fn control_center(anchor: Anchor, offset: Vec2, css_size: Vec2) -> Vec2 {
+	match anchor {
+		Anchor::TopLeft => offset,
+		Anchor::TopRight => Vec2::new(css_size.x - offset.x, offset.y),
+		Anchor::BottomLeft => Vec2::new(offset.x, css_size.y - offset.y),
+		Anchor::BottomRight => css_size - offset,
+	}
+}
+
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct ControlVertex {
+	pos: Vec2,
+	color: Vec4,
+}
+
+impl Vertex for ControlVertex {
+	fn descriptor() -> Descriptor {
+		Descriptor::from(&[2, 4])
+	}
+}
+
+struct VirtualControlsRenderer {
+	shader: Shader,
+	mesh: DynamicMesh<ControlVertex>,
+	text: TextRenderer,
+}
+
+impl VirtualControlsRenderer {
+	fn new() -> Self {
+		let shader = Shader::from_asset(
+			embed_asset!("../shaders/virtual_controls.glsl"),
+			&["position", "color"]
+		);
+
+		VirtualControlsRenderer {
+			shader,
+			mesh: DynamicMesh::new(),
+			text: TextRenderer::new(),
+		}
+	}
+
+	fn add_circle(&mut self, center: Vec2, radius: f32, color: Vec4) {
+
+		let verts: Vec<_> = (0..CIRCLE_SEGMENTS)
+			.map(|i| {
+				let angle = i as f32 / CIRCLE_SEGMENTS as f32 * 2.0 * PI;
+				let pos = center + Vec2::new(angle.cos(), angle.sin()) * radius;
+				ControlVertex { pos, color }
+			})
+			.collect();
+
+		self.mesh.add_tri_fan(&verts);
+	}
+
+	fn draw(&self, viewport: Vec2i) {
+		let viewport_f = viewport.to_vec2();
+		let proj = Mat4::translate(Vec3::new(-1.0, 1.0, 0.0))
+			* Mat4::scale(Vec3::new(2.0 / viewport_f.x, -2.0 / viewport_f.y, 1.0));
+
+		unsafe {
+			gl::disable(gl::Capability::DepthTest);
+		}
+
+		self.shader.bind();
+		self.shader.set_uniform("proj_view", proj);
+		self.mesh.draw(gl::DrawMode::Triangles);
+
+		unsafe {
+			gl::enable(gl::Capability::DepthTest);
+		}
+
+		self.text.draw(viewport);
+	}
+}
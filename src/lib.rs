@@ -7,23 +7,33 @@ pub mod imports;
 
 #[macro_use]
 pub mod io;
+#[macro_use]
+pub mod profiler;
+#[macro_use]
+pub mod assets;
 pub mod string_arena;
 
 pub mod exports;
 
 pub mod engine;
+pub mod state;
+pub mod storage;
 pub mod input;
 pub mod graphics;
 
-pub use engine::{EngineClient, UpdateContext, Ticks, EngineResult};
+pub use engine::{EngineClient, EngineID, UpdateContext, Ticks, EngineResult};
 pub const DT: f32 = 1.0/60.0;
 
 use std::cell::{RefCell, Ref, RefMut};
 
-// RefCell is an attempt at guarding against mutable ref aliasing across the js/wasm boundary
-static mut ENGINE: Option<RefCell<engine::Engine>> = None;
+// RefCell is an attempt at guarding against mutable ref aliasing across the js/wasm boundary.
+// Engines are boxed so that refs stay valid while new engines are being created
+static mut ENGINES: Vec<Box<RefCell<engine::Engine>>> = Vec::new();
+static mut CLIENT_FACTORY: Option<Box<dyn Fn() -> Box<dyn EngineClient>>> = None;
 
-pub fn init_engine<F: FnOnce() -> C, C: EngineClient + 'static>(client: F) {
+/// Registers the client that every engine instance will be created with.
+/// The page creates one engine per canvas, so `client` may be called more than once
+pub fn init_engine<F, C>(client: F) where F: Fn() -> C + 'static, C: EngineClient + 'static {
 	std::panic::set_hook(Box::new(|panic_info| {
 		if let Some(loc) = panic_info.location() {
 			console_error!("panic at {}:{}!", loc.file(), loc.line());
@@ -35,20 +45,53 @@ pub fn init_engine<F: FnOnce() -> C, C: EngineClient + 'static>(client: F) {
 	exports::force_linkage();
 
 	unsafe {
-		ENGINE = Some(RefCell::new(engine::Engine::new(client())));
+		CLIENT_FACTORY = Some(Box::new(move || Box::new(client())));
 	}
+}
+
+pub fn init_engine_with_states<F, I, C>(client: F, initial: I)
+	where F: Fn() -> C + 'static, I: Fn() -> state::Transition<C> + 'static, C: EngineClient + 'static
+{
+	init_engine(move || state::StatefulClient::new(client(), initial()));
+}
+
+/// Creates a new engine instance with the client registered with `init_engine`.
+/// Any gl calls made by the client's `init` go to whichever context is currently active
+pub fn create_engine() -> EngineID {
+	let engine = engine::Engine::new(create_client());
+
+	let id = unsafe {
+		ENGINES.push(Box::new(RefCell::new(engine)));
+		EngineID(ENGINES.len() as u32 - 1)
+	};
 
-	get_engine_mut().client.init();
+	get_engine_mut(id).init();
+	id
+}
+
+/// Replaces an engine's client with a fresh one, e.g. to recover from a failure
+pub fn restart_engine(id: EngineID) {
+	let client = create_client();
+	get_engine_mut(id).restart(client);
+}
+
+fn create_client() -> Box<dyn EngineClient> {
+	unsafe {
+		let factory = CLIENT_FACTORY.as_ref()
+			.expect("Tried to create an engine before init_engine was called");
+
+		factory()
+	}
 }
 
-pub fn get_engine() -> Ref<'static, engine::Engine> {
+pub fn get_engine(id: EngineID) -> Ref<'static, engine::Engine> {
 	unsafe {
-		ENGINE.as_ref().unwrap().borrow()
+		ENGINES[id.0 as usize].borrow()
 	}
 }
 
-pub fn get_engine_mut() -> RefMut<'static, engine::Engine> {
+pub fn get_engine_mut(id: EngineID) -> RefMut<'static, engine::Engine> {
 	unsafe {
-		ENGINE.as_ref().unwrap().borrow_mut()
+		ENGINES[id.0 as usize].borrow_mut()
 	}
 }
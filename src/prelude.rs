@@ -4,6 +4,9 @@ pub use failure::{ensure, bail, format_err};
 pub use crate::DT;
 pub use crate::{Ticks, EngineResult, EngineClient};
 pub use crate::{console_log, console_warn, console_error};
+pub use crate::profile_scope;
+pub use crate::embed_asset;
+pub use crate::assets::{Asset, Reloadable};
 
 pub use crate::graphics::*;
 
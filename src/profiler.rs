@@ -0,0 +1,221 @@
+// Scoped frame profiler. Everything here compiles down to nothing unless the `profiler` feature is enabled,
+// so scopes can be left in toys permanently - build with `--features profiler` to see where frame time goes,
+// in debug or release.
+
+use crate::imports::util;
+
+/// Number of frames that min/avg/max are taken over
+pub const WINDOW_FRAMES: usize = 120;
+
+/// Times the rest of the enclosing block, under `name`. Scopes can nest, and the same name can be used more than once per frame
+#[macro_export]
+macro_rules! profile_scope {
+	($name:expr) => {
+		let _profile_scope = $crate::profiler::ProfileScope::new($name);
+	};
+}
+
+
+/// Timings for one scope over the last `WINDOW_FRAMES` frames, in milliseconds.
+/// Frames where the scope didn't run count as zero
+#[derive(Copy, Clone, Debug)]
+pub struct ScopeStats {
+	pub name: &'static str,
+	pub depth: usize,
+
+	pub min: f64,
+	pub avg: f64,
+	pub max: f64,
+
+	/// How many times the scope was entered in the most recent complete frame
+	pub calls: u32,
+}
+
+
+pub fn is_enabled() -> bool { cfg!(feature = "profiler") }
+
+/// Marks the start of an animation frame.
+/// Every engine calls this with the same timestamp within one requestAnimationFrame callback, so a new frame only starts when it changes
+#[cfg(feature = "profiler")]
+pub fn begin_frame(timestamp: f64) { profiler().begin_frame(timestamp) }
+#[cfg(not(feature = "profiler"))]
+pub fn begin_frame(_: f64) {}
+
+/// Stats for every scope seen so far, in the order they were first entered
+#[cfg(feature = "profiler")]
+pub fn stats() -> Vec<ScopeStats> { profiler().stats() }
+#[cfg(not(feature = "profiler"))]
+pub fn stats() -> Vec<ScopeStats> { Vec::new() }
+
+#[cfg(feature = "profiler")]
+pub fn reset() { unsafe { PROFILER = None; } }
+#[cfg(not(feature = "profiler"))]
+pub fn reset() {}
+
+/// Writes the current stats to the console as a table
+pub fn dump() {
+	if !is_enabled() {
+		console_warn!("Profiler is disabled - rebuild with `--features profiler`");
+		return;
+	}
+
+	console_log!("{:<24} {:>8} {:>8} {:>8} {:>6}", "scope (ms)", "min", "avg", "max", "calls");
+
+	for s in stats() {
+		let name = format!("{:indent$}{}", "", s.name, indent = s.depth * 2);
+		console_log!("{:<24} {:>8.3} {:>8.3} {:>8.3} {:>6}", name, s.min, s.avg, s.max, s.calls);
+	}
+}
+
+/// Milliseconds since some arbitrary point, from `performance.now`
+pub fn now() -> f64 {
+	unsafe { util::performance_now() }
+}
+
+
+pub struct ProfileScope {
+	#[cfg(feature = "profiler")]
+	record: usize,
+	#[cfg(feature = "profiler")]
+	start: f64,
+}
+
+impl ProfileScope {
+	#[cfg(feature = "profiler")]
+	pub fn new(name: &'static str) -> Self {
+		let record = profiler().enter(name);
+		ProfileScope { record, start: now() }
+	}
+
+	#[cfg(not(feature = "profiler"))]
+	#[inline(always)]
+	pub fn new(_: &'static str) -> Self { ProfileScope {} }
+}
+
+#[cfg(feature = "profiler")]
+impl Drop for ProfileScope {
+	fn drop(&mut self) {
+		let elapsed = now() - self.start;
+		profiler().exit(self.record, elapsed);
+	}
+}
+
+
+
+#[cfg(feature = "profiler")]
+static mut PROFILER: Option<Profiler> = None;
+
+#[cfg(feature = "profiler")]
+fn profiler() -> &'static mut Profiler {
+	unsafe { PROFILER.get_or_insert_with(Profiler::new) }
+}
+
+
+#[cfg(feature = "profiler")]
+struct ScopeRecord {
+	name: &'static str,
+	depth: usize,
+
+	frame_time: f64,
+	frame_calls: u32,
+	last_calls: u32,
+
+	history: [f64; WINDOW_FRAMES],
+}
+
+#[cfg(feature = "profiler")]
+struct Profiler {
+	scopes: Vec<ScopeRecord>,
+	depth: usize,
+
+	frame_timestamp: Option<f64>,
+	frame_index: usize,
+	frames_recorded: usize,
+}
+
+#[cfg(feature = "profiler")]
+impl Profiler {
+	fn new() -> Self {
+		Profiler {
+			scopes: Vec::new(),
+			depth: 0,
+
+			frame_timestamp: None,
+			frame_index: 0,
+			frames_recorded: 0,
+		}
+	}
+
+	fn begin_frame(&mut self, timestamp: f64) {
+		if self.frame_timestamp == Some(timestamp) { return }
+
+		if self.frame_timestamp.is_some() {
+			self.end_frame();
+		}
+
+		self.frame_timestamp = Some(timestamp);
+	}
+
+	fn end_frame(&mut self) {
+		let slot = self.frame_index % WINDOW_FRAMES;
+
+		for scope in self.scopes.iter_mut() {
+			scope.history[slot] = scope.frame_time;
+			scope.last_calls = scope.frame_calls;
+			scope.frame_time = 0.0;
+			scope.frame_calls = 0;
+		}
+
+		self.frame_index = self.frame_index.wrapping_add(1);
+		self.frames_recorded = (self.frames_recorded + 1).min(WINDOW_FRAMES);
+	}
+
+	fn enter(&mut self, name: &'static str) -> usize {
+		let depth = self.depth;
+		self.depth += 1;
+
+		if let Some(idx) = self.scopes.iter().position(|s| s.name == name) {
+			return idx;
+		}
+
+		self.scopes.push(ScopeRecord {
+			name, depth,
+			frame_time: 0.0,
+			frame_calls: 0,
+			last_calls: 0,
+			history: [0.0; WINDOW_FRAMES],
+		});
+
+		self.scopes.len() - 1
+	}
+
+	fn exit(&mut self, record: usize, elapsed: f64) {
+		self.depth = self.depth.saturating_sub(1);
+
+		// The profiler may have been reset while this scope was open
+		if let Some(scope) = self.scopes.get_mut(record) {
+			scope.frame_time += elapsed;
+			scope.frame_calls += 1;
+		}
+	}
+
+	fn stats(&self) -> Vec<ScopeStats> {
+		let recorded = self.frames_recorded.max(1);
+
+		self.scopes.iter()
+			.map(|scope| {
+				let history = &scope.history[..recorded];
+				let min = history.iter().cloned().fold(std::f64::INFINITY, f64::min);
+				let max = history.iter().cloned().fold(0.0, f64::max);
+				let avg = history.iter().sum::<f64>() / recorded as f64;
+
+				ScopeStats {
+					name: scope.name,
+					depth: scope.depth,
+					min, avg, max,
+					calls: scope.last_calls,
+				}
+			})
+			.collect()
+	}
+}
@@ -0,0 +1,27 @@
+precision highp float;
+
+uniform mat4 proj_view;
+uniform float point_size;
+
+attribute vec3 position;
+attribute vec3 id;
+
+varying vec3 v_id;
+
+void main() {
+	gl_Position = proj_view * vec4(position, 1.0);
+	gl_PointSize = point_size;
+	v_id = id;
+}
+
+
+/* @@@ */
+
+
+precision highp float;
+
+varying vec3 v_id;
+
+void main() {
+	gl_FragColor = vec4(v_id, 1.0);
+}
@@ -0,0 +1,33 @@
+precision highp float;
+
+uniform mat4 proj_view;
+
+attribute vec2 position;
+attribute vec2 uv;
+attribute vec4 color;
+
+varying vec2 v_uv;
+varying vec4 v_color;
+
+void main() {
+	gl_Position = proj_view * vec4(position, 0.0, 1.0);
+	v_uv = uv;
+	v_color = color;
+}
+
+
+/* @@@ */
+
+
+precision highp float;
+
+uniform sampler2D tex;
+
+varying vec2 v_uv;
+varying vec4 v_color;
+
+void main() {
+	if (texture2D(tex, v_uv).r < 0.5) { discard; }
+
+	gl_FragColor = vec4(v_color.rgb * v_color.a, v_color.a);
+}
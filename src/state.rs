@@ -0,0 +1,254 @@
+use crate::engine::{EngineClient, EngineResult, UpdateContext};
+
+/// A self contained mode of a client - a menu, a cutscene, gameplay.
+/// `C` is the client that owns the stack, and holds whatever resources states share.
+pub trait State<C> {
+	fn enter(&mut self, _: &mut C) {}
+	fn exit(&mut self, _: &mut C) {}
+
+	/// Only called for the topmost state, and never while a transition is in progress
+	fn simulate(&mut self, _: &mut C, _: UpdateContext<'_>) -> EngineResult<Transition<C>> { Ok(Transition::none()) }
+
+	/// Only called for the topmost state, and never while a transition is in progress
+	fn update(&mut self, _: &mut C, _: UpdateContext<'_>) -> EngineResult<Transition<C>> { Ok(Transition::none()) }
+
+	/// Called for every state in the stack, bottom up.
+	/// `transition` is only ever set for the topmost state
+	fn render(&mut self, _: &mut C, _: UpdateContext<'_>, _transition: Option<TransitionPhase>) -> EngineResult<()> { Ok(()) }
+}
+
+
+pub enum StateCommand<C> {
+	Push(Box<dyn State<C>>),
+	Pop,
+	Replace(Box<dyn State<C>>),
+}
+
+/// A change to the state stack, optionally eased in and out over time.
+/// The outgoing state keeps rendering for `fade_out` seconds before the command is applied,
+/// then the incoming state renders for `fade_in` seconds before it starts receiving updates
+pub struct Transition<C> {
+	command: Option<StateCommand<C>>,
+	fade_out: f32,
+	fade_in: f32,
+}
+
+impl<C> Transition<C> {
+	pub fn none() -> Self {
+		Transition { command: None, fade_out: 0.0, fade_in: 0.0 }
+	}
+
+	pub fn push<S: State<C> + 'static>(state: S) -> Self {
+		Transition { command: Some(StateCommand::Push(Box::new(state))), ..Transition::none() }
+	}
+
+	pub fn pop() -> Self {
+		Transition { command: Some(StateCommand::Pop), ..Transition::none() }
+	}
+
+	pub fn replace<S: State<C> + 'static>(state: S) -> Self {
+		Transition { command: Some(StateCommand::Replace(Box::new(state))), ..Transition::none() }
+	}
+
+	pub fn fade_out(self, duration: f32) -> Self { Transition { fade_out: duration, ..self } }
+	pub fn fade_in(self, duration: f32) -> Self { Transition { fade_in: duration, ..self } }
+
+	pub fn is_none(&self) -> bool { self.command.is_none() }
+}
+
+
+/// Progress through a transition, in [0, 1]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TransitionPhase {
+	Out(f32),
+	In(f32),
+}
+
+
+struct ActiveTransition<C> {
+	command: Option<StateCommand<C>>,
+	fade_out: f32,
+	fade_in: f32,
+	elapsed: f32,
+}
+
+
+pub struct StateStack<C> {
+	states: Vec<Box<dyn State<C>>>,
+	transition: Option<ActiveTransition<C>>,
+}
+
+impl<C> StateStack<C> {
+	pub fn new() -> Self {
+		StateStack {
+			states: Vec::new(),
+			transition: None,
+		}
+	}
+
+	pub fn len(&self) -> usize { self.states.len() }
+	pub fn is_empty(&self) -> bool { self.states.is_empty() }
+
+	pub fn transition_phase(&self) -> Option<TransitionPhase> {
+		let active = self.transition.as_ref()?;
+
+		let phase = if active.command.is_some() {
+			TransitionPhase::Out(progress(active.elapsed, active.fade_out))
+		} else {
+			TransitionPhase::In(progress(active.elapsed, active.fade_in))
+		};
+
+		Some(phase)
+	}
+
+	pub fn begin(&mut self, client: &mut C, transition: Transition<C>) {
+		let Transition { command, fade_out, fade_in } = transition;
+		if command.is_none() { return }
+
+		if self.transition.is_some() {
+			console_warn!("Tried to begin a state transition while another is in progress");
+			return;
+		}
+
+		self.transition = Some(ActiveTransition { command, fade_out, fade_in, elapsed: 0.0 });
+
+		// Apply immediately if there's no fade out
+		self.advance(client, 0.0);
+	}
+
+	pub fn simulate(&mut self, client: &mut C, ctx: UpdateContext<'_>) -> EngineResult<()> {
+		self.advance(client, ctx.fixed_dt);
+		if self.transition.is_some() { return Ok(()) }
+
+		if let Some(top) = self.states.last_mut() {
+			let transition = top.simulate(client, ctx)?;
+			self.begin(client, transition);
+		}
+
+		Ok(())
+	}
+
+	pub fn update(&mut self, client: &mut C, ctx: UpdateContext<'_>) -> EngineResult<()> {
+		if self.transition.is_some() { return Ok(()) }
+
+		if let Some(top) = self.states.last_mut() {
+			let transition = top.update(client, ctx)?;
+			self.begin(client, transition);
+		}
+
+		Ok(())
+	}
+
+	pub fn render(&mut self, client: &mut C, ctx: UpdateContext<'_>) -> EngineResult<()> {
+		let phase = self.transition_phase();
+		let top = self.states.len().saturating_sub(1);
+
+		for (idx, state) in self.states.iter_mut().enumerate() {
+			let phase = if idx == top { phase } else { None };
+			state.render(client, ctx, phase)?;
+		}
+
+		Ok(())
+	}
+
+	fn advance(&mut self, client: &mut C, dt: f32) {
+		let mut active = match self.transition.take() {
+			Some(active) => active,
+			None => return,
+		};
+
+		active.elapsed += dt;
+
+		if let Some(command) = active.command.take() {
+			if active.elapsed < active.fade_out {
+				active.command = Some(command);
+				self.transition = Some(active);
+				return;
+			}
+
+			self.apply(client, command);
+			active.elapsed = 0.0;
+		}
+
+		if active.elapsed < active.fade_in {
+			self.transition = Some(active);
+		}
+	}
+
+	fn apply(&mut self, client: &mut C, command: StateCommand<C>) {
+		match command {
+			StateCommand::Push(mut state) => {
+				state.enter(client);
+				self.states.push(state);
+			}
+
+			StateCommand::Pop => {
+				if let Some(mut state) = self.states.pop() {
+					state.exit(client);
+				}
+			}
+
+			StateCommand::Replace(mut state) => {
+				if let Some(mut prev) = self.states.pop() {
+					prev.exit(client);
+				}
+
+				state.enter(client);
+				self.states.push(state);
+			}
+		}
+	}
+}
+
+fn progress(elapsed: f32, duration: f32) -> f32 {
+	if duration > 0.0 {
+		(elapsed / duration).max(0.0).min(1.0)
+	} else {
+		1.0
+	}
+}
+
+
+/// Drives a client and a `StateStack` of states that share it.
+/// The client's own hooks run first each frame, then the topmost state's, then every state renders.
+pub struct StatefulClient<C> {
+	client: C,
+	states: StateStack<C>,
+	initial: Option<Transition<C>>,
+}
+
+impl<C> StatefulClient<C> {
+	pub fn new(client: C, initial: Transition<C>) -> Self {
+		StatefulClient {
+			client,
+			states: StateStack::new(),
+			initial: Some(initial),
+		}
+	}
+}
+
+impl<C: EngineClient> EngineClient for StatefulClient<C> {
+	fn init(&mut self) -> EngineResult<()> {
+		self.client.init()?;
+
+		if let Some(initial) = self.initial.take() {
+			self.states.begin(&mut self.client, initial);
+		}
+
+		Ok(())
+	}
+
+	fn simulate(&mut self, ctx: UpdateContext<'_>) -> EngineResult<()> {
+		self.client.simulate(ctx)?;
+		self.states.simulate(&mut self.client, ctx)
+	}
+
+	fn update(&mut self, ctx: UpdateContext<'_>) -> EngineResult<()> {
+		self.client.update(ctx)?;
+		self.states.update(&mut self.client, ctx)?;
+		self.states.render(&mut self.client, ctx)
+	}
+
+	fn inner(&self) -> Option<&dyn EngineClient> { Some(&self.client) }
+	fn inner_mut(&mut self) -> Option<&mut dyn EngineClient> { Some(&mut self.client) }
+}
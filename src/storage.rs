@@ -0,0 +1,201 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::string::ToString;
+
+use crate::engine::EngineResult;
+
+/// Somewhere to keep small values between visits.
+/// Keys are namespaced per toy by the backend, so toys can't see or clobber each other's values
+pub trait StorageBackend {
+	fn get(&self, key: &str) -> Option<String>;
+	fn set(&mut self, key: &str, value: &str) -> EngineResult<()>;
+	fn remove(&mut self, key: &str);
+
+	/// Removes every key in this namespace
+	fn clear(&mut self);
+}
+
+
+/// Keeps everything in a HashMap for the lifetime of the backend. The default outside of a browser
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage {
+	values: HashMap<String, String>,
+}
+
+impl MemoryStorage {
+	pub fn new() -> Self { MemoryStorage::default() }
+
+	pub fn len(&self) -> usize { self.values.len() }
+	pub fn is_empty(&self) -> bool { self.values.is_empty() }
+}
+
+impl StorageBackend for MemoryStorage {
+	fn get(&self, key: &str) -> Option<String> { self.values.get(key).cloned() }
+
+	fn set(&mut self, key: &str, value: &str) -> EngineResult<()> {
+		self.values.insert(key.to_owned(), value.to_owned());
+		Ok(())
+	}
+
+	fn remove(&mut self, key: &str) { self.values.remove(key); }
+	fn clear(&mut self) { self.values.clear() }
+}
+
+
+/// Forwards to `window.localStorage`. main.js prefixes every key with the name of the toy binary
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage;
+
+#[cfg(target_arch = "wasm32")]
+impl StorageBackend for LocalStorage {
+	fn get(&self, key: &str) -> Option<String> {
+		let value = unsafe { crate::imports::storage::storage_get(key.into()) };
+
+		if value.is_null() {
+			None
+		} else {
+			Some(value.as_str().to_owned())
+		}
+	}
+
+	fn set(&mut self, key: &str, value: &str) -> EngineResult<()> {
+		let stored = unsafe { crate::imports::storage::storage_set(key.into(), value.into()) };
+		failure::ensure!(stored, "Couldn't store '{}' - storage is unavailable or full", key);
+		Ok(())
+	}
+
+	fn remove(&mut self, key: &str) {
+		unsafe { crate::imports::storage::storage_remove(key.into()) }
+	}
+
+	fn clear(&mut self) {
+		unsafe { crate::imports::storage::storage_clear() }
+	}
+}
+
+
+thread_local! {
+	static BACKEND: RefCell<Option<Box<dyn StorageBackend>>> = RefCell::new(None);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_backend() -> Box<dyn StorageBackend> { Box::new(LocalStorage) }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_backend() -> Box<dyn StorageBackend> { Box::new(MemoryStorage::new()) }
+
+fn with_backend<R, F>(f: F) -> R where F: FnOnce(&mut dyn StorageBackend) -> R {
+	BACKEND.with(|backend| {
+		let mut backend = backend.borrow_mut();
+		let backend = backend.get_or_insert_with(default_backend);
+		f(&mut **backend)
+	})
+}
+
+/// Replaces the backend that storage on this thread goes through, returning the previous one.
+pub fn set_backend<B: StorageBackend + 'static>(backend: B) -> Option<Box<dyn StorageBackend>> {
+	BACKEND.with(|current| current.borrow_mut().replace(Box::new(backend)))
+}
+
+
+/// None if `key` isn't set, or if its value can't be parsed as a `T`
+pub fn get<T: FromStr>(key: &str) -> Option<T> {
+	let value = with_backend(|b| b.get(key))?;
+
+	match value.parse() {
+		Ok(v) => Some(v),
+		Err(_) => {
+			console_warn!("Stored value for '{}' couldn't be parsed as {}", key, std::any::type_name::<T>());
+			None
+		}
+	}
+}
+
+pub fn get_or<T: FromStr>(key: &str, default: T) -> T {
+	get(key).unwrap_or(default)
+}
+
+pub fn set<T: ToString + ?Sized>(key: &str, value: &T) -> EngineResult<()> {
+	let value = value.to_string();
+	with_backend(|b| b.set(key, &value))
+}
+
+pub fn contains(key: &str) -> bool {
+	with_backend(|b| b.get(key).is_some())
+}
+
+pub fn remove(key: &str) {
+	with_backend(|b| b.remove(key))
+}
+
+pub fn clear() {
+	with_backend(|b| b.clear())
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn values_round_trip() {
+		set_backend(MemoryStorage::new());
+
+		set("score", &42u32).unwrap();
+		set("name", "fish").unwrap();
+		set("volume", &0.5f32).unwrap();
+
+		assert_eq!(get::<u32>("score"), Some(42));
+		assert_eq!(get::<String>("name"), Some("fish".to_owned()));
+		assert_eq!(get::<f32>("volume"), Some(0.5));
+
+		set("score", &43u32).unwrap();
+		assert_eq!(get::<u32>("score"), Some(43));
+	}
+
+	#[test]
+	fn missing_and_unparseable_values() {
+		set_backend(MemoryStorage::new());
+
+		assert_eq!(get::<u32>("missing"), None);
+		assert_eq!(get_or("missing", 7u32), 7);
+		assert!(!contains("missing"));
+
+		set("name", "fish").unwrap();
+		assert_eq!(get::<u32>("name"), None);
+		assert_eq!(get_or("name", 7u32), 7);
+
+		// Failing to parse doesn't throw the value away
+		assert!(contains("name"));
+		assert_eq!(get::<String>("name"), Some("fish".to_owned()));
+	}
+
+	#[test]
+	fn remove_and_clear() {
+		set_backend(MemoryStorage::new());
+
+		set("a", &1).unwrap();
+		set("b", &2).unwrap();
+
+		remove("a");
+		remove("never_set");
+		assert!(!contains("a"));
+		assert!(contains("b"));
+
+		clear();
+		assert!(!contains("b"));
+		assert_eq!(get::<i32>("b"), None);
+	}
+
+	#[test]
+	fn set_backend_returns_the_previous_one() {
+		let mut storage = MemoryStorage::new();
+		storage.set("kept", "yes").unwrap();
+		set_backend(storage);
+
+		let prev = set_backend(MemoryStorage::new()).unwrap();
+		assert_eq!(prev.get("kept"), Some("yes".to_owned()));
+		assert!(!contains("kept"));
+	}
+}
@@ -65,6 +65,8 @@ impl StringArena {
 pub struct JSString (*const u8);
 
 impl JSString {
+	pub fn is_null(&self) -> bool { self.0.is_null() }
+
 	pub fn as_str(&self) -> &str {
 		unsafe {
 			assert!(!self.0.is_null(), "Attempting to get JSString as &str");
@@ -93,7 +95,9 @@ impl ops::Deref for JSString {
 
 impl ops::Drop for JSString {
 	fn drop(&mut self) {
-		free_str_space(self.0);
+		if !self.0.is_null() {
+			free_str_space(self.0);
+		}
 	}
 }
 